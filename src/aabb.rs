@@ -1,6 +1,6 @@
 use crate::{ray::Ray, vec3::Point3};
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct Aabb {
     pub max: Point3,
     pub min: Point3,
@@ -11,14 +11,14 @@ impl Aabb {
         Self { max: b, min: a }
     }
 
-    pub fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> bool {
+    pub fn hit(&self, r: &Ray, mut t_min: f64, mut t_max: f64) -> bool {
         for i in 0..3 {
             let t0 = ((self.min.e[i] - r.origin().e[i]) / r.direction().e[i])
                 .min((self.max.e[i] - r.origin().e[i]) / r.direction().e[i]);
             let t1 = ((self.min.e[i] - r.origin().e[i]) / r.direction().e[i])
                 .max((self.max.e[i] - r.origin().e[i]) / r.direction().e[i]);
-            let t_min = t0.max(t_min);
-            let t_max = t1.min(t_max);
+            t_min = t0.max(t_min);
+            t_max = t1.min(t_max);
             if t_max <= t_min {
                 return false;
             }
@@ -26,6 +26,26 @@ impl Aabb {
         true
     }
 
+    // The span of `r` inside the box, if any.
+    pub fn clip(&self, r: &Ray, mut t_min: f64, mut t_max: f64) -> Option<(f64, f64)> {
+        for c in 0..3 {
+            let inv = 1f64 / r.direction().e[c];
+            let t0 = (self.min.e[c] - r.origin().e[c]) * inv;
+            let t1 = (self.max.e[c] - r.origin().e[c]) * inv;
+            t_min = t_min.max(t0.min(t1));
+            t_max = t_max.min(t0.max(t1));
+            if t_max < t_min {
+                return None;
+            }
+        }
+        Some((t_min, t_max))
+    }
+
+    pub fn area(&self) -> f64 {
+        let d = self.max - self.min;
+        2f64 * (d.x() * d.y() + d.y() * d.z() + d.z() * d.x())
+    }
+
     pub fn surrounding_box(box0: &Self, box1: &Self) -> Self {
         let small = Point3::new(
             box0.min.x().min(box1.min.x()),
@@ -41,12 +61,3 @@ impl Aabb {
         Aabb::new(small, big)
     }
 }
-
-impl Default for Aabb {
-    fn default() -> Self {
-        Aabb {
-            max: Point3::default(),
-            min: Point3::default(),
-        }
-    }
-}
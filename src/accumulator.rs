@@ -0,0 +1,127 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use crate::{framebuffer::Framebuffer, vec3::Color};
+
+const CHECKPOINT_MAGIC: &[u8; 8] = b"RTWACC01";
+
+// Running per-pixel sums of radiance samples, row-major with the top row
+// first, so passes can be added over several runs.
+pub struct Accumulator {
+    width: usize,
+    height: usize,
+    sums: Vec<Color>,
+    counts: Vec<u64>,
+}
+
+impl Accumulator {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            sums: vec![Color::default(); width * height],
+            counts: vec![0; width * height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn add(&mut self, x: usize, y: usize, sum: Color, samples: u64) {
+        let index = y * self.width + x;
+        self.sums[index] += sum;
+        self.counts[index] += samples;
+    }
+
+    pub fn samples(&self, x: usize, y: usize) -> u64 {
+        self.counts[y * self.width + x]
+    }
+
+    pub fn min_samples(&self) -> u64 {
+        self.counts.iter().copied().min().unwrap_or(0)
+    }
+
+    pub fn resolve(&self) -> Framebuffer {
+        let mut framebuffer = Framebuffer::new(self.width, self.height);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let index = y * self.width + x;
+                if self.counts[index] > 0 {
+                    framebuffer.set(x, y, self.sums[index] / self.counts[index] as f64);
+                }
+            }
+        }
+        framebuffer
+    }
+
+    pub fn write_checkpoint(&self, mut out: impl Write, scene_hash: u64) -> io::Result<()> {
+        out.write_all(CHECKPOINT_MAGIC)?;
+        out.write_all(&scene_hash.to_le_bytes())?;
+        out.write_all(&(self.width as u64).to_le_bytes())?;
+        out.write_all(&(self.height as u64).to_le_bytes())?;
+        for (sum, count) in self.sums.iter().zip(&self.counts) {
+            for channel in sum.e {
+                out.write_all(&channel.to_le_bytes())?;
+            }
+            out.write_all(&count.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    pub fn read_checkpoint(mut input: impl Read, scene_hash: u64) -> io::Result<Self> {
+        let mut magic = [0u8; 8];
+        input.read_exact(&mut magic)?;
+        if &magic != CHECKPOINT_MAGIC {
+            return Err(invalid_data("not a checkpoint file"));
+        }
+        if read_u64(&mut input)? != scene_hash {
+            return Err(invalid_data(
+                "checkpoint was made for a different scene or settings",
+            ));
+        }
+        let width = read_u64(&mut input)? as usize;
+        let height = read_u64(&mut input)? as usize;
+        let mut accumulator = Accumulator::new(width, height);
+        for index in 0..width * height {
+            for channel in 0..3 {
+                accumulator.sums[index].e[channel] = f64::from_bits(read_u64(&mut input)?);
+            }
+            accumulator.counts[index] = read_u64(&mut input)?;
+        }
+        Ok(accumulator)
+    }
+
+    // Writes to a sibling temporary file first so an interrupted save never
+    // clobbers the previous checkpoint.
+    pub fn save_checkpoint(&self, path: &Path, scene_hash: u64) -> io::Result<()> {
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        let mut out = BufWriter::new(File::create(&tmp)?);
+        self.write_checkpoint(&mut out, scene_hash)?;
+        out.flush()?;
+        drop(out);
+        fs::rename(&tmp, path)
+    }
+
+    pub fn load_checkpoint(path: &Path, scene_hash: u64) -> io::Result<Self> {
+        Self::read_checkpoint(BufReader::new(File::open(path)?), scene_hash)
+    }
+}
+
+fn read_u64(input: &mut impl Read) -> io::Result<u64> {
+    let mut bytes = [0u8; 8];
+    input.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
@@ -1,26 +1,173 @@
-use std::rc::Rc;
+use std::sync::Arc;
 
-use rand::rngs::ThreadRng;
-use rand::{thread_rng, Rng};
+use rand::Rng;
 use std::cmp::Ordering;
 
 use crate::aabb::Aabb;
 use crate::hittable::Hittable;
 use crate::hittable_list::HittableList;
+use crate::sampler::Sampler;
+use crate::vec3::Point3;
+
+// Relative costs of testing a node's box and of intersecting a primitive,
+// used by the SAH builder and by `BvhStats::sah_cost`.
+const TRAVERSAL_COST: f64 = 0.125;
+const INTERSECTION_COST: f64 = 1f64;
+const SAH_BINS: usize = 12;
+const MAX_LEAF_SIZE: usize = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BvhBuilder {
+    // Random axis, split at the median of the box minima.
+    Median,
+    // Binned surface area heuristic with multi-primitive leaves.
+    Sah,
+}
+
+impl BvhBuilder {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "median" => Some(BvhBuilder::Median),
+            "sah" => Some(BvhBuilder::Sah),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BvhBuilder::Median => "median",
+            BvhBuilder::Sah => "sah",
+        }
+    }
+
+    pub fn build(self, list: &HittableList, time: [f64; 2], rng: &mut impl Rng) -> BvhNode {
+        match self {
+            BvhBuilder::Median => BvhNode::from_list(list, time, rng),
+            BvhBuilder::Sah => BvhNode::from_list_sah(list, time),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BvhLayout {
+    // `BvhNode` as built, one allocation per node.
+    Tree,
+    // `FlatBvh`: the same tree in one array, traversed without recursion.
+    Flat,
+}
+
+impl BvhLayout {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "tree" => Some(BvhLayout::Tree),
+            "flat" => Some(BvhLayout::Flat),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BvhLayout::Tree => "tree",
+            BvhLayout::Flat => "flat",
+        }
+    }
+
+    pub fn create(self, node: BvhNode) -> Arc<dyn Hittable> {
+        match self {
+            BvhLayout::Tree => Arc::new(node),
+            BvhLayout::Flat => Arc::new(FlatBvh::new(&node)),
+        }
+    }
+}
+
+// How the scene loader builds and stores its BVHs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BvhOptions {
+    pub builder: BvhBuilder,
+    pub layout: BvhLayout,
+}
+
+impl Default for BvhOptions {
+    fn default() -> Self {
+        Self {
+            builder: BvhBuilder::Sah,
+            layout: BvhLayout::Flat,
+        }
+    }
+}
+
+// Shape of a built tree. `sah_cost` is the expected cost of a ray that hits
+// the root box, in units of one primitive intersection.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BvhStats {
+    pub nodes: usize,
+    pub leaves: usize,
+    pub depth: usize,
+    pub sah_cost: f64,
+}
+
+impl BvhStats {
+    // A node holding `primitives` directly next to the given child nodes.
+    fn node(abox: &Aabb, children: &[&BvhNode], primitives: usize) -> Self {
+        let area = abox.area();
+        let mut stats = Self {
+            nodes: 1,
+            leaves: (primitives > 0) as usize,
+            depth: 1,
+            sah_cost: TRAVERSAL_COST + INTERSECTION_COST * primitives as f64,
+        };
+        for child in children {
+            stats.nodes += child.stats.nodes;
+            stats.leaves += child.stats.leaves;
+            stats.depth = stats.depth.max(child.stats.depth + 1);
+            stats.sah_cost += area_ratio(&child.abox, area) * child.stats.sah_cost;
+        }
+        stats
+    }
+}
+
+impl std::fmt::Display for BvhStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{} nodes, {} leaves, depth {}, SAH cost {:.2}",
+            self.nodes, self.leaves, self.depth, self.sah_cost
+        )
+    }
+}
+
+// The chance that a ray through a box of area `parent` also crosses `abox`.
+fn area_ratio(abox: &Aabb, parent: f64) -> f64 {
+    if parent > 0f64 {
+        abox.area() / parent
+    } else {
+        1f64
+    }
+}
+
+enum Children {
+    // Split along `axis`, with `left` nearer its negative end.
+    Nodes {
+        left: Box<BvhNode>,
+        right: Box<BvhNode>,
+        axis: usize,
+    },
+    Leaf(Vec<Arc<dyn Hittable>>),
+}
 
 pub struct BvhNode {
-    left: Rc<dyn Hittable>,
-    right: Rc<dyn Hittable>,
+    children: Children,
     abox: Aabb,
+    stats: BvhStats,
 }
 
 impl BvhNode {
     pub fn from_objects(
-        src_objects: &mut Vec<Rc<dyn Hittable>>,
+        src_objects: &mut Vec<Arc<dyn Hittable>>,
         start: usize,
         end: usize,
         time: [f64; 2],
-        rng: &mut ThreadRng,
+        rng: &mut impl Rng,
     ) -> Self {
         let axis = rng.gen_range(0..3);
         let comparator = match axis {
@@ -29,35 +176,203 @@ impl BvhNode {
             _ => box_z_compare,
         };
         let object_span = end - start;
-        let left;
-        let right;
-        let abox;
-        if object_span == 1 {
-            left = src_objects[start].clone();
-            right = left.clone();
-        } else if object_span == 2 {
-            src_objects[start..end].sort_by(comparator);
-            left = src_objects[start].clone();
-            right = src_objects[start + 1].clone();
-        } else {
+        if object_span <= 2 {
             src_objects[start..end].sort_by(comparator);
-            let mid = start + object_span / 2;
-            left = Rc::new(BvhNode::from_objects(src_objects, start, mid, time, rng));
-            right = Rc::new(BvhNode::from_objects(src_objects, mid, end, time, rng));
+            return BvhNode::leaf(src_objects[start..end].to_vec(), time);
+        }
+        src_objects[start..end].sort_by(comparator);
+        let mid = start + object_span / 2;
+        let left = BvhNode::from_objects(src_objects, start, mid, time, rng);
+        let right = BvhNode::from_objects(src_objects, mid, end, time, rng);
+        BvhNode::split(left, right, axis)
+    }
+
+    fn leaf(objects: Vec<Arc<dyn Hittable>>, time: [f64; 2]) -> Self {
+        let mut abox: Option<Aabb> = None;
+        for object in &objects {
+            let mut object_box = Aabb::default();
+            if !object.bounding_box(time, &mut object_box) {
+                eprintln!("No bounding box!");
+            }
+            abox = merge(abox, &Some(object_box));
+        }
+        let abox = abox.unwrap_or_default();
+        let stats = BvhStats::node(&abox, &[], objects.len());
+        Self {
+            children: Children::Leaf(objects),
+            abox,
+            stats,
         }
-        let mut box_left = Aabb::default();
-        let mut box_right = Aabb::default();
-        if !left.bounding_box(time, &mut box_left) || !right.bounding_box(time, &mut box_right) {
-            eprintln!("No bounding box!");
+    }
+
+    fn split(left: BvhNode, right: BvhNode, axis: usize) -> Self {
+        let abox = Aabb::surrounding_box(&left.abox, &right.abox);
+        let stats = BvhStats::node(&abox, &[&left, &right], 0);
+        Self {
+            children: Children::Nodes {
+                left: Box::new(left),
+                right: Box::new(right),
+                axis,
+            },
+            abox,
+            stats,
         }
-        abox = Aabb::surrounding_box(&box_left, &box_right);
-        Self { left, right, abox }
     }
-    pub fn from_list(list: &HittableList, time: [f64; 2]) -> Self {
+
+    pub fn from_list(list: &HittableList, time: [f64; 2], rng: &mut impl Rng) -> Self {
         let mut objects = list.objects.clone();
-        let mut rng = thread_rng();
-        BvhNode::from_objects(&mut objects, 0, list.objects.len(), time, &mut rng)
+        BvhNode::from_objects(&mut objects, 0, list.objects.len(), time, rng)
+    }
+
+    // Splits by the binned surface area heuristic: along each axis the
+    // primitive centroids are bucketed into `SAH_BINS` bins, and the node is
+    // split at the bin boundary with the lowest estimated cost, or kept as a
+    // leaf of up to `MAX_LEAF_SIZE` primitives when that is cheaper.
+    // Objects without a bounding box, such as empty lists, are left out.
+    pub fn from_list_sah(list: &HittableList, time: [f64; 2]) -> Self {
+        let mut primitives: Vec<SahPrimitive> = list
+            .objects
+            .iter()
+            .filter_map(|object| {
+                let mut abox = Aabb::default();
+                if !object.bounding_box(time, &mut abox) {
+                    return None;
+                }
+                let centroid = (abox.min + abox.max) * 0.5;
+                Some(SahPrimitive {
+                    object: object.clone(),
+                    abox,
+                    centroid,
+                })
+            })
+            .collect();
+        if primitives.is_empty() {
+            return BvhNode::leaf(Vec::new(), time);
+        }
+        BvhNode::sah(&mut primitives)
+    }
+
+    fn sah(primitives: &mut [SahPrimitive]) -> Self {
+        let abox = primitives
+            .iter()
+            .skip(1)
+            .fold(primitives[0].abox.clone(), |b, p| {
+                Aabb::surrounding_box(&b, &p.abox)
+            });
+        let n = primitives.len();
+        let leaf = |primitives: &[SahPrimitive], abox: Aabb| {
+            let stats = BvhStats::node(&abox, &[], primitives.len());
+            Self {
+                children: Children::Leaf(primitives.iter().map(|p| p.object.clone()).collect()),
+                abox,
+                stats,
+            }
+        };
+        if n == 1 {
+            return leaf(primitives, abox);
+        }
+
+        let mut bounds = Aabb::new(primitives[0].centroid, primitives[0].centroid);
+        for p in primitives.iter() {
+            bounds = Aabb::surrounding_box(&bounds, &Aabb::new(p.centroid, p.centroid));
+        }
+        let bin = |p: &SahPrimitive, axis: usize| {
+            let extent = bounds.max.e[axis] - bounds.min.e[axis];
+            let offset = (p.centroid.e[axis] - bounds.min.e[axis]) / extent;
+            ((offset * SAH_BINS as f64) as usize).min(SAH_BINS - 1)
+        };
+
+        // (cost, axis, last bin on the left)
+        let mut best: Option<(f64, usize, usize)> = None;
+        let area = abox.area();
+        for axis in 0..3 {
+            if bounds.max.e[axis] <= bounds.min.e[axis] {
+                continue;
+            }
+            let mut boxes: [Option<Aabb>; SAH_BINS] = Default::default();
+            let mut counts = [0usize; SAH_BINS];
+            for p in primitives.iter() {
+                let b = bin(p, axis);
+                counts[b] += 1;
+                boxes[b] = Some(match &boxes[b] {
+                    Some(abox) => Aabb::surrounding_box(abox, &p.abox),
+                    None => p.abox.clone(),
+                });
+            }
+            // Area times count of everything right of each boundary.
+            let mut right_costs = [0f64; SAH_BINS];
+            let (mut right_box, mut right_count) = (None::<Aabb>, 0);
+            for b in (1..SAH_BINS).rev() {
+                right_box = merge(right_box, &boxes[b]);
+                right_count += counts[b];
+                right_costs[b - 1] = side_cost(&right_box, right_count, area);
+            }
+            let (mut left_box, mut left_count) = (None::<Aabb>, 0);
+            for b in 0..SAH_BINS - 1 {
+                left_box = merge(left_box, &boxes[b]);
+                left_count += counts[b];
+                if left_count == 0 || left_count == n {
+                    continue;
+                }
+                let cost = side_cost(&left_box, left_count, area) + right_costs[b];
+                if best.is_none_or(|(best_cost, _, _)| cost < best_cost) {
+                    best = Some((cost, axis, b));
+                }
+            }
+        }
+
+        let leaf_cost = INTERSECTION_COST * n as f64;
+        let (axis, mid) = match best {
+            Some((cost, _, _)) if n <= MAX_LEAF_SIZE && leaf_cost <= cost => {
+                return leaf(primitives, abox)
+            }
+            Some((_, axis, split)) => (axis, partition(primitives, |p| bin(p, axis) <= split)),
+            // Every centroid coincides, so no plane separates them.
+            None if n <= MAX_LEAF_SIZE => return leaf(primitives, abox),
+            None => (0, n / 2),
+        };
+        let (left, right) = primitives.split_at_mut(mid);
+        BvhNode::split(BvhNode::sah(left), BvhNode::sah(right), axis)
     }
+
+    pub fn stats(&self) -> BvhStats {
+        self.stats
+    }
+}
+
+struct SahPrimitive {
+    object: Arc<dyn Hittable>,
+    abox: Aabb,
+    centroid: Point3,
+}
+
+fn merge(acc: Option<Aabb>, abox: &Option<Aabb>) -> Option<Aabb> {
+    match (acc, abox) {
+        (Some(a), Some(b)) => Some(Aabb::surrounding_box(&a, b)),
+        (a, b) => a.or_else(|| b.clone()),
+    }
+}
+
+// Expected cost of the primitives on one side of a split, relative to a ray
+// hitting the parent box of area `area`.
+fn side_cost(abox: &Option<Aabb>, count: usize, area: f64) -> f64 {
+    match abox {
+        Some(abox) => area_ratio(abox, area) * (TRAVERSAL_COST + INTERSECTION_COST * count as f64),
+        None => 0f64,
+    }
+}
+
+// Moves the items satisfying `left` to the front and returns how many there
+// are.
+fn partition<T>(items: &mut [T], left: impl Fn(&T) -> bool) -> usize {
+    let mut mid = 0;
+    for i in 0..items.len() {
+        if left(&items[i]) {
+            items.swap(i, mid);
+            mid += 1;
+        }
+    }
+    mid
 }
 
 impl Hittable for BvhNode {
@@ -67,46 +382,233 @@ impl Hittable for BvhNode {
         t_min: f64,
         t_max: f64,
         rec: &mut crate::hittable::HitRecord,
+        sampler: &mut dyn Sampler,
     ) -> bool {
         if !self.abox.hit(r, t_min, t_max) {
             return false;
         }
-        let hit_left = self.left.hit(r, t_min, t_max, rec);
-        let hit_right = self
-            .right
-            .hit(r, t_min, if hit_left { rec.t } else { t_max }, rec);
-        hit_left || hit_right
+        match &self.children {
+            Children::Nodes { left, right, .. } => {
+                let hit_left = left.hit(r, t_min, t_max, rec, sampler);
+                let hit_right =
+                    right.hit(r, t_min, if hit_left { rec.t } else { t_max }, rec, sampler);
+                hit_left || hit_right
+            }
+            Children::Leaf(objects) => {
+                let mut closest = t_max;
+                let mut hit_anything = false;
+                for object in objects {
+                    if object.hit(r, t_min, closest, rec, sampler) {
+                        hit_anything = true;
+                        closest = rec.t;
+                    }
+                }
+                hit_anything
+            }
+        }
     }
 
     fn bounding_box(&self, _time: [f64; 2], output_box: &mut Aabb) -> bool {
         *output_box = self.abox.clone();
         true
     }
+    fn collect_lights(&self, lights: &mut HittableList) {
+        match &self.children {
+            Children::Nodes { left, right, .. } => {
+                left.collect_lights(lights);
+                right.collect_lights(lights);
+            }
+            Children::Leaf(objects) => collect_lights(objects, lights),
+        }
+    }
+}
+
+// A `BvhNode` flattened depth-first into one array, so that a node's first
+// child directly follows it. Traversal keeps its own stack, visits the child
+// on the near side of the split first, and tests boxes with the ray's
+// inverse direction computed once.
+pub struct FlatBvh {
+    nodes: Vec<FlatNode>,
+    objects: Vec<Arc<dyn Hittable>>,
+    depth: usize,
+}
+
+struct FlatNode {
+    // Minimum and maximum corners.
+    bounds: [[f64; 3]; 2],
+    // The first object of a leaf, or the second child of an interior node.
+    offset: u32,
+    // Objects in a leaf; zero for interior nodes.
+    count: u32,
+    axis: u8,
+}
+
+impl FlatBvh {
+    pub fn new(root: &BvhNode) -> Self {
+        let mut flat = Self {
+            nodes: Vec::with_capacity(root.stats.nodes),
+            objects: Vec::new(),
+            depth: root.stats.depth,
+        };
+        // An empty leaf would read as an interior node.
+        if !matches!(&root.children, Children::Leaf(objects) if objects.is_empty()) {
+            flat.push(root);
+        }
+        flat
+    }
+
+    fn push(&mut self, node: &BvhNode) -> usize {
+        let index = self.nodes.len();
+        self.nodes.push(FlatNode {
+            bounds: [node.abox.min.e, node.abox.max.e],
+            offset: 0,
+            count: 0,
+            axis: 0,
+        });
+        match &node.children {
+            Children::Leaf(objects) => {
+                self.nodes[index].offset = self.objects.len() as u32;
+                self.nodes[index].count = objects.len() as u32;
+                self.objects.extend(objects.iter().cloned());
+            }
+            Children::Nodes { left, right, axis } => {
+                self.push(left);
+                self.nodes[index].offset = self.push(right) as u32;
+                self.nodes[index].axis = *axis as u8;
+            }
+        }
+        index
+    }
+}
+
+impl FlatNode {
+    fn hit(
+        &self,
+        origin: &[f64; 3],
+        inv_direction: &[f64; 3],
+        negative: &[bool; 3],
+        mut t_min: f64,
+        mut t_max: f64,
+    ) -> bool {
+        for axis in 0..3 {
+            let near = self.bounds[negative[axis] as usize][axis];
+            let far = self.bounds[1 - negative[axis] as usize][axis];
+            t_min = ((near - origin[axis]) * inv_direction[axis]).max(t_min);
+            t_max = ((far - origin[axis]) * inv_direction[axis]).min(t_max);
+            if t_max <= t_min {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+impl Hittable for FlatBvh {
+    fn hit(
+        &self,
+        r: &crate::ray::Ray,
+        t_min: f64,
+        t_max: f64,
+        rec: &mut crate::hittable::HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> bool {
+        if self.nodes.is_empty() {
+            return false;
+        }
+        let origin = r.origin().e;
+        let direction = r.direction().e;
+        let inv_direction = direction.map(|d| 1f64 / d);
+        let negative = inv_direction.map(|d| d < 0f64);
+
+        // Each level of the descent pushes at most one node.
+        let mut fixed = [0u32; 64];
+        let mut spilled = Vec::new();
+        let stack: &mut [u32] = if self.depth <= fixed.len() {
+            &mut fixed
+        } else {
+            spilled.resize(self.depth, 0);
+            &mut spilled
+        };
+        let mut top = 0;
+        let mut current = 0;
+        let mut closest = t_max;
+        let mut hit_anything = false;
+        loop {
+            let node = &self.nodes[current];
+            if node.hit(&origin, &inv_direction, &negative, t_min, closest) {
+                if node.count == 0 {
+                    let (near, far) = if negative[node.axis as usize] {
+                        (node.offset as usize, current + 1)
+                    } else {
+                        (current + 1, node.offset as usize)
+                    };
+                    stack[top] = far as u32;
+                    top += 1;
+                    current = near;
+                    continue;
+                }
+                let first = node.offset as usize;
+                for object in &self.objects[first..first + node.count as usize] {
+                    if object.hit(r, t_min, closest, rec, sampler) {
+                        hit_anything = true;
+                        closest = rec.t;
+                    }
+                }
+            }
+            if top == 0 {
+                break;
+            }
+            top -= 1;
+            current = stack[top] as usize;
+        }
+        hit_anything
+    }
+
+    fn bounding_box(&self, _time: [f64; 2], output_box: &mut Aabb) -> bool {
+        match self.nodes.first() {
+            Some(root) => {
+                *output_box = Aabb::new(
+                    Point3::new(root.bounds[0][0], root.bounds[0][1], root.bounds[0][2]),
+                    Point3::new(root.bounds[1][0], root.bounds[1][1], root.bounds[1][2]),
+                );
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn collect_lights(&self, lights: &mut HittableList) {
+        collect_lights(&self.objects, lights);
+    }
 }
 
-pub fn box_compare(a: &Rc<dyn Hittable>, b: &Rc<dyn Hittable>, axis: usize) -> bool {
+fn collect_lights(objects: &[Arc<dyn Hittable>], lights: &mut HittableList) {
+    for object in objects {
+        if object.is_emissive() {
+            lights.push(object.clone());
+        } else {
+            object.collect_lights(lights);
+        }
+    }
+}
+
+// A total order on the boxes' minimum corners. Ties must compare equal, or
+// the sort is free to leave the span unordered, which happens constantly
+// with meshes whose faces share vertices.
+pub fn box_compare(a: &Arc<dyn Hittable>, b: &Arc<dyn Hittable>, axis: usize) -> Ordering {
     let mut box_a = Aabb::default();
     let mut box_b = Aabb::default();
     if !a.bounding_box([0f64, 0f64], &mut box_a) || !b.bounding_box([0f64, 0f64], &mut box_b) {
         eprintln!("No bounding box");
     }
-    box_a.min.e[axis] < box_b.min.e[axis]
+    box_a.min.e[axis].total_cmp(&box_b.min.e[axis])
 }
-pub fn box_x_compare(a: &Rc<dyn Hittable>, b: &Rc<dyn Hittable>) -> Ordering {
-    match box_compare(a, b, 0) {
-        true => Ordering::Less,
-        false => Ordering::Greater,
-    }
+pub fn box_x_compare(a: &Arc<dyn Hittable>, b: &Arc<dyn Hittable>) -> Ordering {
+    box_compare(a, b, 0)
 }
-pub fn box_y_compare(a: &Rc<dyn Hittable>, b: &Rc<dyn Hittable>) -> Ordering {
-    match box_compare(a, b, 1) {
-        true => Ordering::Less,
-        false => Ordering::Greater,
-    }
+pub fn box_y_compare(a: &Arc<dyn Hittable>, b: &Arc<dyn Hittable>) -> Ordering {
+    box_compare(a, b, 1)
 }
-pub fn box_z_compare(a: &Rc<dyn Hittable>, b: &Rc<dyn Hittable>) -> Ordering {
-    match box_compare(a, b, 2) {
-        true => Ordering::Less,
-        false => Ordering::Greater,
-    }
+pub fn box_z_compare(a: &Arc<dyn Hittable>, b: &Arc<dyn Hittable>) -> Ordering {
+    box_compare(a, b, 2)
 }
@@ -1,7 +1,7 @@
-use rand::{rngs::ThreadRng, thread_rng, Rng};
-
 use crate::{
     ray::Ray,
+    sampler::Sampler,
+    transform::AnimatedTransform,
     vec3::{cross, random_unit_disk, Point3, Vec3},
 };
 
@@ -15,10 +15,11 @@ pub struct Camera {
     _w: Vec3,
     lens_radius: f64,
     time: [f64; 2],
-    rng: ThreadRng,
+    motion: Option<AnimatedTransform>,
 }
 
 impl Camera {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         lookfrom: Point3,
         lookfor: Point3,
@@ -43,7 +44,6 @@ impl Camera {
         let vertical = v * viewport_height * focus_dist;
         let lower_left_corner = origin - horizontal / 2.0 - vertical / 2.0 - w * focus_dist;
         let lens_radius = aperture / 2f64;
-        let rng = thread_rng();
 
         Self {
             origin,
@@ -55,17 +55,39 @@ impl Camera {
             _w: w,
             lens_radius,
             time,
-            rng,
+            motion: None,
         }
     }
 
-    pub fn get_ray(&mut self, u: f64, v: f64) -> Ray {
-        let rd = random_unit_disk(&mut self.rng) * self.lens_radius;
+    pub fn set_aspect_ratio(&mut self, aspect_ratio: f64) {
+        let center = self.lower_left_corner + self.horizontal / 2.0 + self.vertical / 2.0;
+        self.horizontal = self.u * self.vertical.modulus() * aspect_ratio;
+        self.lower_left_corner = center - self.horizontal / 2.0 - self.vertical / 2.0;
+    }
+
+    // Moves the whole camera by keyframes over the shutter interval.
+    // Rotation and scale pivot on `lookfrom`, so a keyframe rotation pans
+    // the camera in place.
+    pub fn set_motion(&mut self, motion: AnimatedTransform) {
+        self.motion = Some(motion);
+    }
+
+    pub fn get_ray(&self, u: f64, v: f64, sampler: &mut dyn Sampler) -> Ray {
+        let rd = random_unit_disk(sampler) * self.lens_radius;
         let offset = self.u * rd.x() + self.v * rd.y();
-        Ray::new(
-            self.origin + offset,
-            self.lower_left_corner + self.horizontal * u + self.vertical * v - self.origin - offset,
-            self.rng.gen_range(self.time[0]..self.time[1]),
-        )
+        let time = self.time[0] + (self.time[1] - self.time[0]) * sampler.next_1d();
+        let direction =
+            self.lower_left_corner + self.horizontal * u + self.vertical * v - self.origin - offset;
+        match &self.motion {
+            None => Ray::new(self.origin + offset, direction, time),
+            Some(motion) => {
+                let pose = motion.at(time);
+                Ray::new(
+                    self.origin + pose.point(&offset),
+                    pose.vector(&direction),
+                    time,
+                )
+            }
+        }
     }
 }
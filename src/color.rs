@@ -1,9 +1,18 @@
 use crate::vec3::Color;
 
-pub fn paint(mut pixel_color: Color, samples: usize) -> String {
-    let scale = 1.0 / samples as f64;
-    pixel_color *= scale;
-    pixel_color.sqrt();
-    pixel_color.clamp();
-    format!("{}\n", pixel_color)
+// Gamma-encodes a linear color and quantizes it to 8 bits, clamping values
+// outside [0, 1].
+pub fn encode_rgb8(linear: Color, gamma: f64) -> [u8; 3] {
+    let inv_gamma = 1f64 / gamma;
+    let mut encoded = Color::new(
+        linear.x().max(0f64).powf(inv_gamma),
+        linear.y().max(0f64).powf(inv_gamma),
+        linear.z().max(0f64).powf(inv_gamma),
+    );
+    encoded.clamp();
+    [
+        (encoded.x() * 256f64) as u8,
+        (encoded.y() * 256f64) as u8,
+        (encoded.z() * 256f64) as u8,
+    ]
 }
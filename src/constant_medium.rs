@@ -1,32 +1,31 @@
-use std::rc::Rc;
-
-use rand::{random, thread_rng, Rng};
+use std::sync::Arc;
 
 use crate::{
     hittable::{HitRecord, Hittable},
     material::{Isotropic, Scatter},
+    sampler::Sampler,
     texture::Texture,
     vec3::{Color, Vec3},
 };
 
 pub struct ConstantMedium {
-    boundary: Rc<dyn Hittable>,
-    phase_function: Rc<dyn Scatter>,
+    boundary: Arc<dyn Hittable>,
+    phase_function: Arc<dyn Scatter>,
     neg_inv_density: f64,
 }
 
 impl ConstantMedium {
-    pub fn from_texture(boundary: Rc<dyn Hittable>, d: f64, a: Rc<dyn Texture>) -> Self {
+    pub fn from_texture(boundary: Arc<dyn Hittable>, d: f64, a: Arc<dyn Texture>) -> Self {
         Self {
             boundary,
-            phase_function: Rc::new(Isotropic::from_texture(a)),
+            phase_function: Arc::new(Isotropic::from_texture(a)),
             neg_inv_density: -1f64 / d,
         }
     }
-    pub fn from_color(boundary: Rc<dyn Hittable>, d: f64, c: Color) -> Self {
+    pub fn from_color(boundary: Arc<dyn Hittable>, d: f64, c: Color) -> Self {
         Self {
             boundary,
-            phase_function: Rc::new(Isotropic::from_color(c)),
+            phase_function: Arc::new(Isotropic::from_color(c)),
             neg_inv_density: -1f64 / d,
         }
     }
@@ -39,19 +38,19 @@ impl Hittable for ConstantMedium {
         t_min: f64,
         t_max: f64,
         rec: &mut crate::hittable::HitRecord,
+        sampler: &mut dyn Sampler,
     ) -> bool {
-        const enable_debug: bool = false;
         let mut rec1 = HitRecord::default();
         let mut rec2 = HitRecord::default();
         if !self
             .boundary
-            .hit(r, -f64::INFINITY, f64::INFINITY, &mut rec1)
+            .hit(r, -f64::INFINITY, f64::INFINITY, &mut rec1, sampler)
         {
             return false;
         }
         if !self
             .boundary
-            .hit(r, rec1.t + 0.0001, f64::INFINITY, &mut rec2)
+            .hit(r, rec1.t + 0.0001, f64::INFINITY, &mut rec2, sampler)
         {
             return false;
         }
@@ -64,8 +63,7 @@ impl Hittable for ConstantMedium {
 
         let ray_len = r.direction().modulus();
         let distance_inside_boundary = (rec2.t - rec1.t) * ray_len;
-        let mut rng = thread_rng();
-        let hit_distance = self.neg_inv_density * rng.gen_range(0f64..1f64).ln();
+        let hit_distance = self.neg_inv_density * (1f64 - sampler.next_1d()).ln();
         if hit_distance > distance_inside_boundary {
             return false;
         }
@@ -0,0 +1,154 @@
+use std::sync::Arc;
+
+use crate::{
+    aabb::Aabb,
+    hittable::{HitRecord, Hittable},
+    ray::Ray,
+    sampler::Sampler,
+    vec3::Point3,
+};
+
+// How far past a child's surface the search for its next surface starts.
+const CSG_EPSILON: f64 = 1e-6;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CsgOp {
+    Union,
+    Intersection,
+    // The left child with the right one carved out of it.
+    Difference,
+}
+
+impl CsgOp {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "union" => Some(CsgOp::Union),
+            "intersection" => Some(CsgOp::Intersection),
+            "difference" => Some(CsgOp::Difference),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CsgOp::Union => "union",
+            CsgOp::Intersection => "intersection",
+            CsgOp::Difference => "difference",
+        }
+    }
+
+    fn inside(self, left: bool, right: bool) -> bool {
+        match self {
+            CsgOp::Union => left || right,
+            CsgOp::Intersection => left && right,
+            CsgOp::Difference => left && !right,
+        }
+    }
+}
+
+// Combines two closed children, whose surfaces must face outward. Along a
+// ray, each child's hits alternate between entering (front face) and
+// leaving it, and a surface of the result is wherever the combined inside
+// state flips. The surface keeps the normal, UVs and material of the child
+// it came from. CSG nodes are not sampled as lights, so emissive children
+// are only found by scattering.
+pub struct Csg {
+    op: CsgOp,
+    left: Arc<dyn Hittable>,
+    right: Arc<dyn Hittable>,
+}
+
+impl Csg {
+    pub fn new(op: CsgOp, left: Arc<dyn Hittable>, right: Arc<dyn Hittable>) -> Self {
+        Self { op, left, right }
+    }
+}
+
+fn next_hit(
+    object: &dyn Hittable,
+    r: &Ray,
+    t_min: f64,
+    sampler: &mut dyn Sampler,
+) -> Option<HitRecord> {
+    let mut rec = HitRecord::default();
+    if object.hit(r, t_min, f64::INFINITY, &mut rec, sampler) {
+        Some(rec)
+    } else {
+        None
+    }
+}
+
+impl Hittable for Csg {
+    fn hit(
+        &self,
+        r: &Ray,
+        t_min: f64,
+        t_max: f64,
+        rec: &mut HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> bool {
+        let mut hits = [
+            next_hit(&*self.left, r, t_min, sampler),
+            next_hit(&*self.right, r, t_min, sampler),
+        ];
+        // Leaving a child next means the ray starts inside it.
+        let mut inside = [
+            hits[0].as_ref().is_some_and(|h| !h.front_face),
+            hits[1].as_ref().is_some_and(|h| !h.front_face),
+        ];
+        loop {
+            let side = match (&hits[0], &hits[1]) {
+                (None, None) => return false,
+                (Some(_), None) => 0,
+                (None, Some(_)) => 1,
+                (Some(a), Some(b)) => usize::from(b.t < a.t),
+            };
+            let hit = hits[side].take().unwrap();
+            if hit.t > t_max {
+                return false;
+            }
+            let was_inside = self.op.inside(inside[0], inside[1]);
+            inside[side] = hit.front_face;
+            if self.op.inside(inside[0], inside[1]) != was_inside {
+                *rec = hit;
+                // The carved-out child's inside is the result's outside.
+                if self.op == CsgOp::Difference && side == 1 {
+                    rec.front_face = !rec.front_face;
+                }
+                return true;
+            }
+            let child = if side == 0 { &self.left } else { &self.right };
+            hits[side] = next_hit(&**child, r, hit.t + CSG_EPSILON, sampler);
+        }
+    }
+
+    fn bounding_box(&self, time: [f64; 2], output_box: &mut Aabb) -> bool {
+        let mut left = Aabb::default();
+        let mut right = Aabb::default();
+        if !self.left.bounding_box(time, &mut left) || !self.right.bounding_box(time, &mut right) {
+            return false;
+        }
+        *output_box = match self.op {
+            CsgOp::Union => Aabb::surrounding_box(&left, &right),
+            CsgOp::Intersection => {
+                let min = Point3::new(
+                    left.min.x().max(right.min.x()),
+                    left.min.y().max(right.min.y()),
+                    left.min.z().max(right.min.z()),
+                );
+                let mut max = Point3::new(
+                    left.max.x().min(right.max.x()),
+                    left.max.y().min(right.max.y()),
+                    left.max.z().min(right.max.z()),
+                );
+                // Disjoint children leave nothing; keep the box well formed.
+                for c in 0..3 {
+                    max.e[c] = max.e[c].max(min.e[c]);
+                }
+                Aabb::new(min, max)
+            }
+            CsgOp::Difference => left,
+        };
+        true
+    }
+}
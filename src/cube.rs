@@ -1,66 +1,78 @@
-use std::rc::Rc;
+use std::sync::Arc;
 
 use crate::{
     aabb::Aabb,
-    aarect::{XYRect, YZRect, ZXRect},
     hittable::Hittable,
     hittable_list::HittableList,
     material::Scatter,
-    vec3::Point3,
+    quad::Quad,
+    sampler::Sampler,
+    vec3::{cross, dot, Point3, Vec3},
 };
 
+// A box of six quads with outward-facing normals.
 pub struct Cube {
-    cube_min: Point3,
-    cube_max: Point3,
+    abox: Aabb,
     sides: HittableList,
 }
 
 impl Cube {
-    pub fn new(p0: Point3, p1: Point3, ptr: Rc<dyn Scatter>) -> Self {
-        let cube_min = p0;
-        let cube_max = p1;
-        let mut sides = HittableList::new();
-        sides.push(Rc::new(XYRect::new(
-            ptr.clone(),
-            [p0.x(), p1.x()],
-            [p0.y(), p1.y()],
-            p1.z(),
-        )));
-        sides.push(Rc::new(XYRect::new(
-            ptr.clone(),
-            [p0.x(), p1.x()],
-            [p0.y(), p1.y()],
-            p0.z(),
-        )));
+    // The axis-aligned box between two opposite corners.
+    pub fn new(p0: Point3, p1: Point3, ptr: Arc<dyn Scatter>) -> Self {
+        let min = Point3::new(p0.x().min(p1.x()), p0.y().min(p1.y()), p0.z().min(p1.z()));
+        let max = Point3::new(p0.x().max(p1.x()), p0.y().max(p1.y()), p0.z().max(p1.z()));
+        let d = max - min;
+        Self::oriented(
+            min,
+            [
+                Vec3::new(d.x(), 0f64, 0f64),
+                Vec3::new(0f64, d.y(), 0f64),
+                Vec3::new(0f64, 0f64, d.z()),
+            ],
+            ptr,
+        )
+    }
 
-        sides.push(Rc::new(ZXRect::new(
-            ptr.clone(),
-            [p0.x(), p1.x()],
-            [p0.z(), p1.z()],
-            p1.y(),
-        )));
-        sides.push(Rc::new(ZXRect::new(
-            ptr.clone(),
-            [p0.x(), p1.x()],
-            [p0.z(), p1.z()],
-            p0.y(),
-        )));
+    // The parallelepiped with one corner at `corner` and the three edges
+    // leaving it. Faces of zero area are left out, so a flat box is a
+    // two-sided quad.
+    pub fn oriented(corner: Point3, edges: [Vec3; 3], ptr: Arc<dyn Scatter>) -> Self {
+        let [mut u, mut v, w] = edges;
+        if dot(&cross(&u, &v), &w) < 0f64 {
+            (u, v) = (v, u);
+        }
+        let faces = [
+            (corner, v, u),
+            (corner + w, u, v),
+            (corner, w, v),
+            (corner + u, v, w),
+            (corner, u, w),
+            (corner + v, w, u),
+        ];
+        let mut sides = HittableList::new();
+        for (q, a, b) in faces {
+            if let Some(quad) = Quad::new(q, a, b, ptr.clone()) {
+                sides.push(Arc::new(quad));
+            }
+        }
 
-        sides.push(Rc::new(YZRect::new(
-            ptr.clone(),
-            [p0.y(), p1.y()],
-            [p0.z(), p1.z()],
-            p1.x(),
-        )));
-        sides.push(Rc::new(YZRect::new(
-            ptr.clone(),
-            [p0.y(), p1.y()],
-            [p0.z(), p1.z()],
-            p0.x(),
-        )));
+        let pad = Vec3::new(0.0001, 0.0001, 0.0001);
+        let mut min = corner;
+        let mut max = corner;
+        for i in 1..8 {
+            let mut p = corner;
+            for (bit, edge) in [u, v, w].iter().enumerate() {
+                if i & (1 << bit) != 0 {
+                    p += *edge;
+                }
+            }
+            for c in 0..3 {
+                min.e[c] = min.e[c].min(p.e[c]);
+                max.e[c] = max.e[c].max(p.e[c]);
+            }
+        }
         Self {
-            cube_min,
-            cube_max,
+            abox: Aabb::new(min - pad, max + pad),
             sides,
         }
     }
@@ -73,12 +85,16 @@ impl Hittable for Cube {
         t_min: f64,
         t_max: f64,
         rec: &mut crate::hittable::HitRecord,
+        sampler: &mut dyn Sampler,
     ) -> bool {
-        self.sides.hit(r, t_min, t_max, rec)
+        self.sides.hit(r, t_min, t_max, rec, sampler)
     }
 
     fn bounding_box(&self, _time: [f64; 2], output_box: &mut crate::aabb::Aabb) -> bool {
-        *output_box = Aabb::new(self.cube_min, self.cube_max);
+        *output_box = self.abox.clone();
         true
     }
+    fn collect_lights(&self, lights: &mut HittableList) {
+        self.sides.collect_lights(lights);
+    }
 }
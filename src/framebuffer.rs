@@ -0,0 +1,122 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use image::ImageResult;
+
+use crate::{color::encode_rgb8, vec3::Color};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageFormat {
+    // Binary P6, gamma encoded.
+    Ppm,
+    // 8-bit RGB, gamma encoded.
+    Png,
+    // Linear 32-bit float RGB.
+    Pfm,
+}
+
+impl ImageFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "ppm" => Some(ImageFormat::Ppm),
+            "png" => Some(ImageFormat::Png),
+            "pfm" => Some(ImageFormat::Pfm),
+            _ => None,
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Option<Self> {
+        Self::from_name(path.extension()?.to_str()?)
+    }
+}
+
+// Linear HDR colors, row-major with the top row first.
+pub struct Framebuffer {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color>,
+}
+
+impl Framebuffer {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![Color::default(); width * height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Color {
+        self.pixels[y * self.width + x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, color: Color) {
+        self.pixels[y * self.width + x] = color;
+    }
+
+    pub fn pixels(&self) -> &[Color] {
+        &self.pixels
+    }
+
+    pub fn to_rgb8(&self, gamma: f64) -> Vec<u8> {
+        self.pixels
+            .iter()
+            .flat_map(|&c| encode_rgb8(c, gamma))
+            .collect()
+    }
+
+    pub fn write_ppm(&self, mut out: impl Write, gamma: f64) -> io::Result<()> {
+        write!(out, "P6\n{} {}\n255\n", self.width, self.height)?;
+        out.write_all(&self.to_rgb8(gamma))
+    }
+
+    // PFM stores rows bottom to top; a negative scale marks little-endian data.
+    pub fn write_pfm(&self, mut out: impl Write) -> io::Result<()> {
+        write!(out, "PF\n{} {}\n-1.0\n", self.width, self.height)?;
+        for row in self.pixels.chunks(self.width).rev() {
+            for c in row {
+                for channel in c.e {
+                    out.write_all(&(channel as f32).to_le_bytes())?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn save(&self, path: &Path, format: ImageFormat, gamma: f64) -> ImageResult<()> {
+        match format {
+            ImageFormat::Ppm => {
+                let mut out = BufWriter::new(File::create(path)?);
+                self.write_ppm(&mut out, gamma)?;
+                out.flush()?;
+            }
+            ImageFormat::Pfm => {
+                let mut out = BufWriter::new(File::create(path)?);
+                self.write_pfm(&mut out)?;
+                out.flush()?;
+            }
+            ImageFormat::Png => {
+                image::save_buffer_with_format(
+                    path,
+                    &self.to_rgb8(gamma),
+                    self.width as u32,
+                    self.height as u32,
+                    image::ColorType::Rgb8,
+                    image::ImageFormat::Png,
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,879 @@
+use std::{collections::HashMap, fs, path::Path, sync::Arc};
+
+use rand::Rng;
+
+use crate::{
+    bvh::{BvhBuilder, BvhNode},
+    camera::Camera,
+    hittable_list::HittableList,
+    import::{ImportError, MeshData},
+    json::{self, Value},
+    material::{Dielectric, DiffuseLight, Lambertian, Metal, Scatter},
+    texture::ImageTexture,
+    transform::{Mat4, Quat, Transform},
+    triangle::TriangleMesh,
+    vec3::{Color, Point3, Vec3},
+};
+
+// A camera node, in the terms `Camera::new` takes.
+pub struct GltfCamera {
+    pub lookfrom: Point3,
+    pub lookat: Point3,
+    pub vup: Vec3,
+    pub vfov: f64,
+    pub aspect_ratio: Option<f64>,
+}
+
+impl GltfCamera {
+    pub fn camera(&self, aspect_ratio: f64) -> Camera {
+        Camera::new(
+            self.lookfrom,
+            self.lookat,
+            self.vup,
+            self.vfov,
+            aspect_ratio,
+            0f64,
+            1f64,
+            [0f64, 1f64],
+        )
+    }
+}
+
+pub struct Gltf {
+    pub world: BvhNode,
+    pub cameras: Vec<GltfCamera>,
+}
+
+// Loads the default scene of a glTF 2.0 file, either JSON (`.gltf`) with
+// external or embedded buffers or binary (`.glb`). Node transforms are
+// baked into the vertices, and all triangles share one BVH. Perspective
+// cameras are returned in node order; orthographic ones, lights and
+// animations are ignored.
+pub fn load(path: &Path, builder: BvhBuilder, rng: &mut impl Rng) -> Result<Gltf, ImportError> {
+    let data = fs::read(path).map_err(|e| ImportError::Io(path.to_path_buf(), e))?;
+    let (text, bin) = if data.starts_with(b"glTF") {
+        glb(path, &data)?
+    } else {
+        (data.as_slice(), None)
+    };
+    let text = std::str::from_utf8(text)
+        .map_err(|_| ImportError::format(path, "JSON is not valid UTF-8"))?;
+    let root = json::parse(text).map_err(|e| ImportError::syntax(path, e.line, e.message))?;
+    let mut doc = Document::new(path, &root, bin)?;
+
+    let mut out = Output {
+        triangles: HittableList::new(),
+        cameras: Vec::new(),
+    };
+    for node in doc.scene_roots()? {
+        doc.node(node, &Mat4::IDENTITY, &mut Vec::new(), &mut out)?;
+    }
+    if out.triangles.objects.is_empty() {
+        return Err(ImportError::format(path, "scene has no triangles"));
+    }
+    Ok(Gltf {
+        world: builder.build(&out.triangles, [0f64, 1f64], rng),
+        cameras: out.cameras,
+    })
+}
+
+struct Output {
+    triangles: HittableList,
+    cameras: Vec<GltfCamera>,
+}
+
+const GLB_JSON: u32 = 0x4e4f534a;
+const GLB_BIN: u32 = 0x004e4942;
+
+// Splits a GLB container into its JSON chunk and optional binary chunk.
+fn glb<'d>(path: &Path, data: &'d [u8]) -> Result<(&'d [u8], Option<Vec<u8>>), ImportError> {
+    let word = |at: usize| {
+        data.get(at..at + 4)
+            .map(|b| u32::from_le_bytes(b.try_into().unwrap()) as usize)
+            .ok_or_else(|| ImportError::format(path, "GLB file is truncated"))
+    };
+    if word(4)? != 2 {
+        return Err(ImportError::format(
+            path,
+            format!("unsupported GLB version {}", word(4)?),
+        ));
+    }
+    let end = word(8)?.min(data.len());
+    let (mut text, mut bin) = (None, None);
+    let mut pos = 12;
+    while pos + 8 <= end {
+        let length = word(pos)?;
+        let chunk = data
+            .get(pos + 8..pos + 8 + length)
+            .ok_or_else(|| ImportError::format(path, "GLB chunk runs past the end of the file"))?;
+        match word(pos + 4)? as u32 {
+            GLB_JSON if text.is_none() => text = Some(chunk),
+            GLB_BIN if bin.is_none() => bin = Some(chunk.to_vec()),
+            _ => {}
+        }
+        pos += 8 + length;
+    }
+    let text = text.ok_or_else(|| ImportError::format(path, "GLB file has no JSON chunk"))?;
+    Ok((text, bin))
+}
+
+struct Document<'a> {
+    path: &'a Path,
+    root: &'a Value,
+    buffers: Vec<Vec<u8>>,
+    materials: HashMap<usize, Arc<dyn Scatter>>,
+    textures: HashMap<usize, Arc<ImageTexture>>,
+    default_material: Arc<dyn Scatter>,
+}
+
+impl<'a> Document<'a> {
+    fn new(path: &'a Path, root: &'a Value, mut bin: Option<Vec<u8>>) -> Result<Self, ImportError> {
+        let fail = |message: String| ImportError::format(path, message);
+        let version = root
+            .get("asset")
+            .and_then(|a| a.get("version"))
+            .and_then(Value::as_str)
+            .ok_or_else(|| fail("missing `asset.version`".to_string()))?;
+        if !version.starts_with("2.") {
+            return Err(fail(format!("unsupported glTF version {}", version)));
+        }
+
+        let dir = path.parent().unwrap_or(Path::new("."));
+        let mut buffers = Vec::new();
+        for (i, buffer) in list(root, "buffers").iter().enumerate() {
+            let field = format!("buffers[{}]", i);
+            let length = usize_field(buffer, &field, "byteLength")
+                .map_err(fail)?
+                .ok_or_else(|| fail(format!("{}: missing `byteLength`", field)))?;
+            let mut bytes = match buffer.get("uri").and_then(Value::as_str) {
+                Some(uri) => read_uri(path, dir, uri)?,
+                // A GLB's binary chunk is the first buffer, which has no URI.
+                None if i == 0 && bin.is_some() => bin.take().unwrap_or_default(),
+                None => return Err(fail(format!("{}: missing `uri`", field))),
+            };
+            if bytes.len() < length {
+                return Err(fail(format!(
+                    "{}: holds {} bytes, expected {}",
+                    field,
+                    bytes.len(),
+                    length
+                )));
+            }
+            bytes.truncate(length);
+            buffers.push(bytes);
+        }
+
+        Ok(Self {
+            path,
+            root,
+            buffers,
+            materials: HashMap::new(),
+            textures: HashMap::new(),
+            default_material: Arc::new(Lambertian::from_color(Color::new(0.8, 0.8, 0.8))),
+        })
+    }
+
+    fn fail(&self, message: String) -> ImportError {
+        ImportError::format(self.path, message)
+    }
+
+    fn item(&self, array: &str, index: usize) -> Result<&'a Value, ImportError> {
+        list(self.root, array)
+            .get(index)
+            .ok_or_else(|| self.fail(format!("`{}` has no entry {}", array, index)))
+    }
+
+    fn index(&self, v: &Value, field: &str, key: &str) -> Result<Option<usize>, ImportError> {
+        usize_field(v, field, key).map_err(|e| self.fail(e))
+    }
+
+    fn required(&self, v: &Value, field: &str, key: &str) -> Result<usize, ImportError> {
+        self.index(v, field, key)?
+            .ok_or_else(|| self.fail(format!("{}: missing `{}`", field, key)))
+    }
+
+    // The roots of the default scene, or every parentless node when the file
+    // has no scenes.
+    fn scene_roots(&self) -> Result<Vec<usize>, ImportError> {
+        if self.root.get("scenes").is_some() {
+            let scene = self.index(self.root, "", "scene")?.unwrap_or(0);
+            let v = self.item("scenes", scene)?;
+            let field = format!("scenes[{}]", scene);
+            return indices(v, &field, "nodes").map_err(|e| self.fail(e));
+        }
+        let mut is_child = vec![false; list(self.root, "nodes").len()];
+        for (i, node) in list(self.root, "nodes").iter().enumerate() {
+            let field = format!("nodes[{}]", i);
+            for child in indices(node, &field, "children").map_err(|e| self.fail(e))? {
+                if let Some(flag) = is_child.get_mut(child) {
+                    *flag = true;
+                }
+            }
+        }
+        Ok((0..is_child.len()).filter(|&i| !is_child[i]).collect())
+    }
+
+    fn node(
+        &mut self,
+        index: usize,
+        parent: &Mat4,
+        ancestors: &mut Vec<usize>,
+        out: &mut Output,
+    ) -> Result<(), ImportError> {
+        if ancestors.contains(&index) {
+            return Err(self.fail(format!("node {} is its own ancestor", index)));
+        }
+        let v = self.item("nodes", index)?;
+        let field = format!("nodes[{}]", index);
+        let local = local_matrix(v, &field).map_err(|e| self.fail(e))?;
+        let world = *parent * local;
+
+        if let Some(mesh) = self.index(v, &field, "mesh")? {
+            let mesh_field = format!("meshes[{}]", mesh);
+            let primitives = list(self.item("meshes", mesh)?, "primitives");
+            for (i, primitive) in primitives.iter().enumerate() {
+                let field = format!("{}.primitives[{}]", mesh_field, i);
+                self.primitive(primitive, &field, &world, &mut out.triangles)?;
+            }
+        }
+        if let Some(camera) = self.index(v, &field, "camera")? {
+            if let Some(camera) = self.camera(camera, &world)? {
+                out.cameras.push(camera);
+            }
+        }
+        ancestors.push(index);
+        for child in indices(v, &field, "children").map_err(|e| self.fail(e))? {
+            self.node(child, &world, ancestors, out)?;
+        }
+        ancestors.pop();
+        Ok(())
+    }
+
+    // glTF cameras look down their local -Z axis with +Y up.
+    fn camera(&self, index: usize, world: &Mat4) -> Result<Option<GltfCamera>, ImportError> {
+        let v = self.item("cameras", index)?;
+        let field = format!("cameras[{}]", index);
+        let perspective = match v.get("perspective") {
+            Some(p) => p,
+            None => return Ok(None),
+        };
+        let number = |key: &str| number_field(perspective, &field, key).map_err(|e| self.fail(e));
+        let yfov = number("yfov")?
+            .ok_or_else(|| self.fail(format!("{}: missing `perspective.yfov`", field)))?;
+        let lookfrom = world.point(&Point3::default());
+        let forward = world.vector(&Vec3::new(0f64, 0f64, -1f64));
+        Ok(Some(GltfCamera {
+            lookfrom,
+            lookat: lookfrom + forward.unit(),
+            vup: world.vector(&Vec3::new(0f64, 1f64, 0f64)),
+            vfov: yfov.to_degrees(),
+            aspect_ratio: number("aspectRatio")?,
+        }))
+    }
+
+    fn primitive(
+        &mut self,
+        v: &Value,
+        field: &str,
+        world: &Mat4,
+        triangles: &mut HittableList,
+    ) -> Result<(), ImportError> {
+        let mode = self.index(v, field, "mode")?.unwrap_or(4);
+        match mode {
+            // Points and lines have no surface to hit.
+            0..=3 => return Ok(()),
+            4..=6 => {}
+            _ => return Err(self.fail(format!("{}: unknown mode {}", field, mode))),
+        }
+        let attributes = v
+            .get("attributes")
+            .ok_or_else(|| self.fail(format!("{}: missing `attributes`", field)))?;
+        let attributes_field = format!("{}.attributes", field);
+        let attribute = |key: &str, components: usize| -> Result<Option<Vec<f64>>, ImportError> {
+            match self.index(attributes, &attributes_field, key)? {
+                Some(accessor) => self.accessor(accessor, components).map(Some),
+                None => Ok(None),
+            }
+        };
+
+        let positions = attribute("POSITION", 3)?
+            .ok_or_else(|| self.fail(format!("{}: missing `POSITION`", attributes_field)))?;
+        let normals = attribute("NORMAL", 3)?.unwrap_or_default();
+        // glTF puts the UV origin at the top left of an image.
+        let uvs = attribute("TEXCOORD_0", 2)?.unwrap_or_default();
+        let vertex_count = positions.len() / 3;
+        let order: Vec<usize> = match self.index(v, field, "indices")? {
+            Some(accessor) => self
+                .accessor(accessor, 1)?
+                .into_iter()
+                .map(|i| i as usize)
+                .collect(),
+            None => (0..vertex_count).collect(),
+        };
+
+        let mut indices: Vec<[usize; 3]> = match mode {
+            4 => order.chunks_exact(3).map(|c| [c[0], c[1], c[2]]).collect(),
+            5 => (0..order.len().saturating_sub(2))
+                .map(|k| match k % 2 {
+                    0 => [order[k], order[k + 1], order[k + 2]],
+                    _ => [order[k + 1], order[k], order[k + 2]],
+                })
+                .collect(),
+            _ => (1..order.len().saturating_sub(1))
+                .map(|k| [order[0], order[k], order[k + 1]])
+                .collect(),
+        };
+        // A node scaled down to nothing has no surface.
+        let transform = match Transform::new(*world) {
+            Some(transform) => transform,
+            None => return Ok(()),
+        };
+        // A mirroring transform turns counter-clockwise faces clockwise.
+        if world.determinant3() < 0f64 {
+            for face in &mut indices {
+                face.swap(1, 2);
+            }
+        }
+
+        let mesh = MeshData {
+            positions: positions
+                .chunks_exact(3)
+                .map(|p| transform.point(&Vec3::new(p[0], p[1], p[2])))
+                .collect(),
+            normals: normals
+                .chunks_exact(3)
+                .map(|n| transform.normal(&Vec3::new(n[0], n[1], n[2])))
+                .collect(),
+            uvs: uvs.chunks_exact(2).map(|t| [t[0], 1f64 - t[1]]).collect(),
+            colors: Vec::new(),
+            indices,
+        };
+        let material = match self.index(v, field, "material")? {
+            Some(material) => self.material(material)?,
+            None => self.default_material.clone(),
+        };
+        let mesh = mesh
+            .into_mesh(material)
+            .map_err(|e| ImportError::Mesh(self.path.to_path_buf(), e))?;
+        triangles
+            .objects
+            .extend(TriangleMesh::triangles(&Arc::new(mesh)).objects);
+        Ok(())
+    }
+
+    fn view(&self, index: usize) -> Result<&[u8], ImportError> {
+        let v = self.item("bufferViews", index)?;
+        let field = format!("bufferViews[{}]", index);
+        let buffer = self.required(v, &field, "buffer")?;
+        let offset = self.index(v, &field, "byteOffset")?.unwrap_or(0);
+        let length = self.required(v, &field, "byteLength")?;
+        self.buffers
+            .get(buffer)
+            .ok_or_else(|| self.fail(format!("{}: there is no buffer {}", field, buffer)))?
+            .get(offset..offset.saturating_add(length))
+            .ok_or_else(|| self.fail(format!("{}: runs past the end of its buffer", field)))
+    }
+
+    // Reads an accessor with `components` values per element into one flat
+    // list. Normalized integers are mapped to [0, 1] or [-1, 1].
+    fn accessor(&self, index: usize, components: usize) -> Result<Vec<f64>, ImportError> {
+        let v = self.item("accessors", index)?;
+        let field = format!("accessors[{}]", index);
+        if v.get("sparse").is_some() {
+            return Err(self.fail(format!("{}: sparse accessors are not supported", field)));
+        }
+        let expected = ["SCALAR", "VEC2", "VEC3", "VEC4"][components - 1];
+        let kind = v.get("type").and_then(Value::as_str);
+        if kind != Some(expected) {
+            return Err(self.fail(format!(
+                "{}: expected type {}, found {}",
+                field,
+                expected,
+                kind.unwrap_or("nothing")
+            )));
+        }
+        let count = self.required(v, &field, "count")?;
+        let component_type = self.required(v, &field, "componentType")?;
+        let normalized = v.get("normalized").and_then(Value::as_bool) == Some(true);
+        let (size, scale) = match component_type {
+            5120 => (1, 127f64),
+            5121 => (1, 255f64),
+            5122 => (2, 32767f64),
+            5123 => (2, 65535f64),
+            5125 => (4, 1f64),
+            5126 => (4, 1f64),
+            _ => {
+                return Err(self.fail(format!(
+                    "{}: unknown componentType {}",
+                    field, component_type
+                )))
+            }
+        };
+        let element = size * components;
+        let view_index = match self.index(v, &field, "bufferView")? {
+            Some(view) => view,
+            // Without a view every value is zero. The count is still held to
+            // what the file's buffers could store, so a bad one cannot ask
+            // for any amount of memory.
+            None => {
+                let stored: usize = self.buffers.iter().map(Vec::len).sum();
+                if count
+                    .checked_mul(element)
+                    .is_none_or(|bytes| bytes > stored)
+                {
+                    return Err(self.fail(format!(
+                        "{}: count {} is larger than the file's buffers",
+                        field, count
+                    )));
+                }
+                return Ok(vec![0f64; count * components]);
+            }
+        };
+        let view = self.view(view_index)?;
+        let stride =
+            match self.index(self.item("bufferViews", view_index)?, &field, "byteStride")? {
+                Some(stride) if stride > 0 => stride,
+                _ => element,
+            };
+        let offset = self.index(v, &field, "byteOffset")?.unwrap_or(0);
+        let end = count
+            .checked_sub(1)
+            .map(|last| {
+                last.checked_mul(stride)?
+                    .checked_add(offset)?
+                    .checked_add(element)
+            })
+            .unwrap_or(Some(0));
+        if end.is_none_or(|end| end > view.len()) {
+            return Err(self.fail(format!("{}: runs past the end of its buffer view", field)));
+        }
+
+        let mut out = Vec::with_capacity(count * components);
+        for i in 0..count {
+            for c in 0..components {
+                let at = offset + i * stride + c * size;
+                let b = &view[at..at + size];
+                let value = match component_type {
+                    5120 => b[0] as i8 as f64,
+                    5121 => b[0] as f64,
+                    5122 => i16::from_le_bytes([b[0], b[1]]) as f64,
+                    5123 => u16::from_le_bytes([b[0], b[1]]) as f64,
+                    5125 => u32::from_le_bytes(b.try_into().unwrap()) as f64,
+                    _ => f32::from_le_bytes(b.try_into().unwrap()) as f64,
+                };
+                out.push(if normalized && component_type != 5126 {
+                    (value / scale).max(-1f64)
+                } else {
+                    value
+                });
+            }
+        }
+        Ok(out)
+    }
+
+    // Metallic-roughness materials become the closest Scatter: emitters
+    // lights, transmissive or blended ones glass, mostly-metallic ones Metal
+    // with roughness as fuzz, and the rest Lambertian. A base color texture
+    // replaces the base color factor rather than scaling it.
+    fn material(&mut self, index: usize) -> Result<Arc<dyn Scatter>, ImportError> {
+        if let Some(material) = self.materials.get(&index) {
+            return Ok(material.clone());
+        }
+        let v = self.item("materials", index)?;
+        let field = format!("materials[{}]", index);
+        let number = |v: Option<&Value>, key: &str, default: f64| -> Result<f64, ImportError> {
+            match v {
+                Some(v) => Ok(number_field(v, &field, key)
+                    .map_err(|e| self.fail(e))?
+                    .unwrap_or(default)),
+                None => Ok(default),
+            }
+        };
+        let color = |v: Option<&Value>, key: &str, default: [f64; 4]| {
+            let values = v
+                .map(|v| numbers_field(v, &field, key))
+                .transpose()
+                .map_err(|e| self.fail(e))?
+                .flatten();
+            match values {
+                Some(values) if values.len() >= 3 => {
+                    let mut out = default;
+                    out[..values.len().min(4)].copy_from_slice(&values[..values.len().min(4)]);
+                    Ok(out)
+                }
+                Some(_) => Err(self.fail(format!("{}: `{}` needs 3 or 4 numbers", field, key))),
+                None => Ok(default),
+            }
+        };
+        let extension = |name: &str| v.get("extensions").and_then(|e| e.get(name));
+
+        let pbr = v.get("pbrMetallicRoughness");
+        let base = color(pbr, "baseColorFactor", [1f64; 4])?;
+        let metallic = number(pbr, "metallicFactor", 1f64)?;
+        let roughness = number(pbr, "roughnessFactor", 1f64)?;
+        let emissive = color(Some(v), "emissiveFactor", [0f64; 4])?;
+        let strength = number(
+            extension("KHR_materials_emissive_strength"),
+            "emissiveStrength",
+            1f64,
+        )?;
+        let transmission = number(
+            extension("KHR_materials_transmission"),
+            "transmissionFactor",
+            0f64,
+        )?;
+        let ior = number(extension("KHR_materials_ior"), "ior", 1.5)?;
+        let blend = v.get("alphaMode").and_then(Value::as_str) == Some("BLEND");
+        let texture = match pbr.and_then(|p| p.get("baseColorTexture")) {
+            Some(t) => Some(self.required(t, &field, "index")?),
+            None => None,
+        };
+
+        let base_color = Color::new(base[0], base[1], base[2]);
+        let material: Arc<dyn Scatter> = if emissive[..3].iter().any(|&c| c > 0f64) {
+            let emit = Color::new(emissive[0], emissive[1], emissive[2]) * strength;
+            Arc::new(DiffuseLight::from_color(emit))
+        } else if transmission > 0f64 || (blend && base[3] < 1f64) {
+            Arc::new(Dielectric::new(ior))
+        } else if metallic >= 0.5 {
+            Arc::new(Metal::new(base_color, roughness))
+        } else if let Some(texture) = texture {
+            Arc::new(Lambertian::from_texture(self.texture(texture)?))
+        } else {
+            Arc::new(Lambertian::from_color(base_color))
+        };
+        self.materials.insert(index, material.clone());
+        Ok(material)
+    }
+
+    fn texture(&mut self, index: usize) -> Result<Arc<ImageTexture>, ImportError> {
+        if let Some(texture) = self.textures.get(&index) {
+            return Ok(texture.clone());
+        }
+        let field = format!("textures[{}]", index);
+        let source = self.required(self.item("textures", index)?, &field, "source")?;
+        let image = self.item("images", source)?;
+        let field = format!("images[{}]", source);
+        let image_error = |path: &Path, e| ImportError::Image(path.to_path_buf(), e);
+        let texture = match image.get("uri").and_then(Value::as_str) {
+            Some(uri) if !uri.starts_with("data:") => {
+                let dir = self.path.parent().unwrap_or(Path::new("."));
+                let file = dir.join(percent_decode(uri));
+                ImageTexture::open(&file).map_err(|e| image_error(&file, e))?
+            }
+            Some(uri) => {
+                let bytes = read_uri(self.path, Path::new("."), uri)?;
+                ImageTexture::from_memory(&bytes).map_err(|e| image_error(self.path, e))?
+            }
+            None => {
+                let view = self.required(image, &field, "bufferView")?;
+                ImageTexture::from_memory(self.view(view)?)
+                    .map_err(|e| image_error(self.path, e))?
+            }
+        };
+        let texture = Arc::new(texture);
+        self.textures.insert(index, texture.clone());
+        Ok(texture)
+    }
+}
+
+fn list<'a>(v: &'a Value, key: &str) -> &'a [Value] {
+    v.get(key).and_then(Value::as_array).unwrap_or_default()
+}
+
+fn number_field(v: &Value, field: &str, key: &str) -> Result<Option<f64>, String> {
+    match v.get(key) {
+        None => Ok(None),
+        Some(n) => n
+            .as_f64()
+            .map(Some)
+            .ok_or_else(|| format!("{}: `{}` must be a number", field, key)),
+    }
+}
+
+fn usize_field(v: &Value, field: &str, key: &str) -> Result<Option<usize>, String> {
+    match number_field(v, field, key)? {
+        None => Ok(None),
+        Some(n) if n >= 0f64 && n.fract() == 0f64 => Ok(Some(n as usize)),
+        Some(n) => Err(format!(
+            "{}: `{}` must be a non-negative integer, found {}",
+            field, key, n
+        )),
+    }
+}
+
+fn numbers_field(v: &Value, field: &str, key: &str) -> Result<Option<Vec<f64>>, String> {
+    let items = match v.get(key) {
+        None => return Ok(None),
+        Some(items) => items
+            .as_array()
+            .ok_or_else(|| format!("{}: `{}` must be an array", field, key))?,
+    };
+    items
+        .iter()
+        .map(|n| {
+            n.as_f64()
+                .ok_or_else(|| format!("{}: `{}` must hold numbers", field, key))
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map(Some)
+}
+
+fn indices(v: &Value, field: &str, key: &str) -> Result<Vec<usize>, String> {
+    let numbers = numbers_field(v, field, key)?.unwrap_or_default();
+    numbers
+        .into_iter()
+        .map(|n| {
+            if n >= 0f64 && n.fract() == 0f64 {
+                Ok(n as usize)
+            } else {
+                Err(format!(
+                    "{}: `{}` must hold indices, found {}",
+                    field, key, n
+                ))
+            }
+        })
+        .collect()
+}
+
+// Resolves a buffer or image URI: base64 `data:` URIs are decoded in place,
+// anything else is a file relative to the glTF file.
+fn read_uri(path: &Path, dir: &Path, uri: &str) -> Result<Vec<u8>, ImportError> {
+    if let Some(data) = uri.strip_prefix("data:") {
+        let payload = match data.split_once(";base64,") {
+            Some((_, payload)) => payload,
+            None => {
+                return Err(ImportError::format(
+                    path,
+                    "only base64 data URIs are supported",
+                ))
+            }
+        };
+        return base64(payload)
+            .ok_or_else(|| ImportError::format(path, "data URI is not valid base64"));
+    }
+    let file = dir.join(percent_decode(uri));
+    fs::read(&file).map_err(|e| ImportError::Io(file, e))
+}
+
+fn base64(text: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(text.len() / 4 * 3);
+    let (mut bits, mut count) = (0u32, 0);
+    for c in text.bytes() {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            b'=' => break,
+            _ => return None,
+        };
+        bits = (bits << 6) | value as u32;
+        count += 6;
+        if count >= 8 {
+            count -= 8;
+            out.push((bits >> count) as u8);
+            bits &= (1 << count) - 1;
+        }
+    }
+    Some(out)
+}
+
+fn percent_decode(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                out.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+// A node's `matrix` (column-major) or its translation * rotation * scale.
+fn local_matrix(v: &Value, field: &str) -> Result<Mat4, String> {
+    let vector = |key: &str, len: usize| -> Result<Option<Vec<f64>>, String> {
+        match numbers_field(v, field, key)? {
+            Some(values) if values.len() != len => {
+                Err(format!("{}: `{}` needs {} numbers", field, key, len))
+            }
+            values => Ok(values),
+        }
+    };
+    if let Some(m) = vector("matrix", 16)? {
+        let mut out = Mat4::IDENTITY;
+        for (c, column) in m.chunks_exact(4).enumerate() {
+            for (r, value) in column.iter().enumerate() {
+                out.m[r][c] = *value;
+            }
+        }
+        return Ok(out);
+    }
+    let t = vector("translation", 3)?.unwrap_or(vec![0f64; 3]);
+    let q = vector("rotation", 4)?.unwrap_or(vec![0f64, 0f64, 0f64, 1f64]);
+    let s = vector("scale", 3)?.unwrap_or(vec![1f64; 3]);
+    let rotation = Quat::new(q[0], q[1], q[2], q[3]).to_mat4();
+    Ok(Mat4::translate(&Vec3::new(t[0], t[1], t[2]))
+        * rotation
+        * Mat4::scale(&Vec3::new(s[0], s[1], s[2])))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{aabb::Aabb, hittable::Hittable, sampler::Pcg32};
+
+    // The corners (0, 0, 0), (1, 0, 0) and (0, 1, 0) as little-endian
+    // floats, then the indices 0, 1, 2 as shorts.
+    fn triangle() -> Vec<u8> {
+        let mut data: Vec<u8> = [0f32, 0f32, 0f32, 1f32, 0f32, 0f32, 0f32, 1f32, 0f32]
+            .iter()
+            .flat_map(|c| c.to_le_bytes())
+            .collect();
+        (0..3u16).for_each(|i| data.extend(i.to_le_bytes()));
+        data
+    }
+
+    // A document drawing the triangle from `buffer`, moved to z = 5, with a
+    // camera at z = 10.
+    fn document(buffer: &str, accessors: &str) -> String {
+        format!(
+            r#"{{
+  "asset": {{"version": "2.0"}},
+  "scene": 0,
+  "scenes": [{{"nodes": [0, 1]}}],
+  "nodes": [
+    {{"mesh": 0, "translation": [0, 0, 5]}},
+    {{"camera": 0, "translation": [0, 0, 10]}}
+  ],
+  "cameras": [{{"type": "perspective", "perspective": {{"yfov": 0.5, "znear": 0.1}}}}],
+  "meshes": [{{"primitives": [{{"attributes": {{"POSITION": 0}}, "indices": 1}}]}}],
+  "buffers": [{}],
+  "bufferViews": [
+    {{"buffer": 0, "byteLength": 36}},
+    {{"buffer": 0, "byteOffset": 36, "byteLength": 6}}
+  ],
+  "accessors": [{}]
+}}"#,
+            buffer, accessors
+        )
+    }
+
+    const ACCESSORS: &str = r#"{"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3"},
+    {"bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR"}"#;
+
+    fn load_files(name: &str, main: &str, files: &[(&str, &[u8])]) -> Result<Gltf, ImportError> {
+        let dir = std::env::temp_dir().join(format!("rtw-gltf-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for (file, bytes) in files {
+            fs::write(dir.join(file), bytes).unwrap();
+        }
+        let model = load(&dir.join(main), BvhBuilder::Sah, &mut Pcg32::new(0, 0));
+        fs::remove_dir_all(&dir).unwrap();
+        model
+    }
+
+    fn check_triangle(model: &Gltf) {
+        let mut abox = Aabb::default();
+        assert!(model.world.bounding_box([0f64, 1f64], &mut abox));
+        assert!((abox.min.z() - 5f64).abs() < 1e-3 && (abox.max.z() - 5f64).abs() < 1e-3);
+        assert!((abox.max.x() - 1f64).abs() < 1e-3 && (abox.max.y() - 1f64).abs() < 1e-3);
+        assert_eq!(model.cameras.len(), 1);
+        assert_eq!(model.cameras[0].lookfrom.z(), 10f64);
+        assert_eq!(model.cameras[0].lookat.z(), 9f64);
+        assert_eq!(model.cameras[0].vfov, 0.5f64.to_degrees());
+    }
+
+    fn message(result: Result<Gltf, ImportError>) -> String {
+        match result {
+            Err(ImportError::Format { message, .. }) => message,
+            Err(e) => panic!("expected a format error, found `{}`", e),
+            Ok(_) => panic!("expected a format error"),
+        }
+    }
+
+    #[test]
+    fn reads_external_and_embedded_buffers() {
+        let external = document(r#"{"uri": "tri.bin", "byteLength": 42}"#, ACCESSORS);
+        let model = load_files(
+            "external",
+            "tri.gltf",
+            &[("tri.gltf", external.as_bytes()), ("tri.bin", &triangle())],
+        );
+        check_triangle(&model.unwrap());
+
+        let embedded = document(
+            r#"{"uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAAAAABAAIA", "byteLength": 42}"#,
+            ACCESSORS,
+        );
+        let model = load_files("embedded", "tri.gltf", &[("tri.gltf", embedded.as_bytes())]);
+        check_triangle(&model.unwrap());
+    }
+
+    #[test]
+    fn reads_glb() {
+        let mut json = document(r#"{"byteLength": 42}"#, ACCESSORS).into_bytes();
+        json.resize(json.len().next_multiple_of(4), b' ');
+        let mut bin = triangle();
+        bin.resize(bin.len().next_multiple_of(4), 0);
+        let length = 12 + 8 + json.len() + 8 + bin.len();
+        let mut glb = b"glTF".to_vec();
+        glb.extend(2u32.to_le_bytes());
+        glb.extend((length as u32).to_le_bytes());
+        glb.extend((json.len() as u32).to_le_bytes());
+        glb.extend(GLB_JSON.to_le_bytes());
+        glb.extend(json);
+        glb.extend((bin.len() as u32).to_le_bytes());
+        glb.extend(GLB_BIN.to_le_bytes());
+        glb.extend(bin);
+        check_triangle(&load_files("glb", "tri.glb", &[("tri.glb", &glb)]).unwrap());
+    }
+
+    #[test]
+    fn reports_bad_files() {
+        let bad = |name: &str, json: String| {
+            message(load_files(
+                name,
+                "bad.gltf",
+                &[("bad.gltf", json.as_bytes()), ("tri.bin", &triangle())],
+            ))
+        };
+        let buffer = r#"{"uri": "tri.bin", "byteLength": 42}"#;
+
+        let past_view = r#"{"bufferView": 0, "componentType": 5126, "count": 4, "type": "VEC3"}"#;
+        assert_eq!(
+            bad("past-view", document(buffer, past_view)),
+            "accessors[0]: runs past the end of its buffer view"
+        );
+        let huge = r#"{"componentType": 5126, "count": 1000000000000, "type": "VEC3"}"#;
+        assert_eq!(
+            bad("huge", document(buffer, huge)),
+            "accessors[0]: count 1000000000000 is larger than the file's buffers"
+        );
+        let wrong_type = r#"{"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC2"}"#;
+        assert_eq!(
+            bad("wrong-type", document(buffer, wrong_type)),
+            "accessors[0]: expected type VEC3, found VEC2"
+        );
+        let cycle = document(buffer, ACCESSORS).replace(
+            r#"{"mesh": 0, "translation": [0, 0, 5]}"#,
+            r#"{"mesh": 0, "children": [0]}"#,
+        );
+        assert_eq!(bad("cycle", cycle), "node 0 is its own ancestor");
+        let old = document(buffer, ACCESSORS).replace("\"2.0\"", "\"1.0\"");
+        assert_eq!(bad("version", old), "unsupported glTF version 1.0");
+    }
+}
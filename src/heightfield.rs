@@ -0,0 +1,312 @@
+use std::{path::Path, sync::Arc};
+
+use image::ImageResult;
+
+use crate::{
+    aabb::Aabb,
+    hittable::{HitRecord, Hittable},
+    material::Scatter,
+    perlin::Perlin,
+    ray::Ray,
+    sampler::Sampler,
+    triangle::{face_normal, intersect},
+    vec3::{Point3, Vec3},
+};
+
+// A grid of heights between 0 and 1, `width` samples along x by `depth`
+// along z, stored row by row from the lowest z.
+pub struct HeightMap {
+    width: usize,
+    depth: usize,
+    heights: Vec<f64>,
+}
+
+impl HeightMap {
+    // Returns `None` unless there are `width * depth` heights.
+    pub fn new(width: usize, depth: usize, heights: Vec<f64>) -> Option<Self> {
+        if heights.len() != width * depth {
+            return None;
+        }
+        Some(Self {
+            width,
+            depth,
+            heights,
+        })
+    }
+
+    // White is the highest. The top row of the image is the far (+z) edge,
+    // so the image reads like a map and lines up with an `ImageTexture` of
+    // it under the heightfield's UVs.
+    pub fn open(file: &Path) -> ImageResult<Self> {
+        let data = image::io::Reader::open(file)?.decode()?.into_luma16();
+        let (width, depth) = (data.width() as usize, data.height() as usize);
+        let mut heights = Vec::with_capacity(width * depth);
+        for j in (0..depth).rev() {
+            for i in 0..width {
+                heights.push(data.get_pixel(i as u32, j as u32)[0] as f64 / 65535f64);
+            }
+        }
+        Ok(Self {
+            width,
+            depth,
+            heights,
+        })
+    }
+
+    // Sums `octaves` layers of `noise`, each at twice the frequency and half
+    // the weight of the last, and stretches the result over 0 to 1.
+    // `frequency` is the number of noise cells across the map.
+    pub fn perlin(
+        noise: &Perlin,
+        width: usize,
+        depth: usize,
+        frequency: f64,
+        octaves: u32,
+    ) -> Self {
+        let mut heights = Vec::with_capacity(width * depth);
+        for j in 0..depth {
+            for i in 0..width {
+                let p = Point3::new(
+                    i as f64 / (width.max(2) - 1) as f64,
+                    0f64,
+                    j as f64 / (depth.max(2) - 1) as f64,
+                ) * frequency;
+                let (mut sum, mut weight) = (0f64, 1f64);
+                for octave in 0..octaves {
+                    sum += weight * noise.noise(&(p * 2f64.powi(octave as i32)));
+                    weight *= 0.5;
+                }
+                heights.push(sum);
+            }
+        }
+        let lo = heights.iter().cloned().fold(f64::INFINITY, f64::min);
+        let hi = heights.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        if hi > lo {
+            for h in &mut heights {
+                *h = (*h - lo) / (hi - lo);
+            }
+        }
+        Self {
+            width,
+            depth,
+            heights,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    fn at(&self, i: usize, j: usize) -> f64 {
+        self.heights[j * self.width + i]
+    }
+}
+
+// Terrain over the box from `corner` to `corner + size`: each cell of the
+// height map is split into two triangles, with heights scaled to `size.y`.
+// Rays walk the cells they cross in order, as seen from above, and stop at
+// the first hit. Normals are interpolated from the vertices, and the UVs
+// run from 0 to 1 across the whole terrain.
+pub struct Heightfield {
+    map: HeightMap,
+    corner: Point3,
+    size: Vec3,
+    cell: [f64; 2],
+    normals: Vec<Vec3>,
+    // The lowest and highest point of each cell, to skip the cells a ray
+    // passes over or under.
+    ranges: Vec<[f64; 2]>,
+    abox: Aabb,
+    mat_ptr: Arc<dyn Scatter>,
+}
+
+impl Heightfield {
+    // Returns `None` for maps smaller than 2 x 2 or sizes that are not
+    // positive.
+    pub fn new(
+        map: HeightMap,
+        corner: Point3,
+        size: Vec3,
+        mat_ptr: Arc<dyn Scatter>,
+    ) -> Option<Self> {
+        let (width, depth) = (map.width, map.depth);
+        if width < 2 || depth < 2 || size.x() <= 0f64 || size.y() <= 0f64 || size.z() <= 0f64 {
+            return None;
+        }
+        let cell = [size.x() / (width - 1) as f64, size.z() / (depth - 1) as f64];
+
+        let mut normals = Vec::with_capacity(width * depth);
+        for j in 0..depth {
+            for i in 0..width {
+                let (i0, i1) = (i.saturating_sub(1), (i + 1).min(width - 1));
+                let (j0, j1) = (j.saturating_sub(1), (j + 1).min(depth - 1));
+                let dx = (map.at(i1, j) - map.at(i0, j)) * size.y() / ((i1 - i0) as f64 * cell[0]);
+                let dz = (map.at(i, j1) - map.at(i, j0)) * size.y() / ((j1 - j0) as f64 * cell[1]);
+                normals.push(Vec3::new(-dx, 1f64, -dz).unit());
+            }
+        }
+
+        let mut ranges = Vec::with_capacity((width - 1) * (depth - 1));
+        let (mut lo, mut hi) = (f64::INFINITY, f64::NEG_INFINITY);
+        for j in 0..depth - 1 {
+            for i in 0..width - 1 {
+                let corners = [
+                    map.at(i, j),
+                    map.at(i + 1, j),
+                    map.at(i, j + 1),
+                    map.at(i + 1, j + 1),
+                ];
+                let cell_lo = corners.iter().cloned().fold(f64::INFINITY, f64::min);
+                let cell_hi = corners.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+                let range = [
+                    corner.y() + cell_lo * size.y(),
+                    corner.y() + cell_hi * size.y(),
+                ];
+                lo = lo.min(range[0]);
+                hi = hi.max(range[1]);
+                ranges.push(range);
+            }
+        }
+        // Padded like the triangles, as a flat map has no height.
+        let pad = Vec3::new(0.0001, 0.0001, 0.0001);
+        let abox = Aabb::new(
+            Point3::new(corner.x(), lo, corner.z()) - pad,
+            Point3::new(corner.x() + size.x(), hi, corner.z() + size.z()) + pad,
+        );
+        Some(Self {
+            map,
+            corner,
+            size,
+            cell,
+            normals,
+            ranges,
+            abox,
+            mat_ptr,
+        })
+    }
+
+    fn vertex(&self, i: usize, j: usize) -> Point3 {
+        self.corner
+            + Vec3::new(
+                i as f64 * self.cell[0],
+                self.map.at(i, j) * self.size.y(),
+                j as f64 * self.cell[1],
+            )
+    }
+
+    // The nearer hit with the two triangles of cell (i, j).
+    fn hit_cell(
+        &self,
+        r: &Ray,
+        i: usize,
+        j: usize,
+        t_min: f64,
+        t_max: f64,
+        rec: &mut HitRecord,
+    ) -> bool {
+        let w = self.map.width;
+        let corners = [(i, j), (i, j + 1), (i + 1, j + 1), (i + 1, j)];
+        let mut best = None;
+        let mut closest = t_max;
+        for tri in [[0, 1, 2], [0, 2, 3]] {
+            let ids = tri.map(|k| corners[k]);
+            let p = ids.map(|(i, j)| self.vertex(i, j));
+            if let Some((t, b1, b2)) = intersect(r, &p, t_min, closest) {
+                closest = t;
+                best = Some((t, b1, b2, p, ids));
+            }
+        }
+        let (t, b1, b2, p, ids) = match best {
+            Some(hit) => hit,
+            None => return false,
+        };
+        let [n0, n1, n2] = ids.map(|(i, j)| self.normals[j * w + i]);
+        rec.t = t;
+        rec.point = r.at(t);
+        rec.u = (rec.point.x() - self.corner.x()) / self.size.x();
+        rec.v = 1f64 - (rec.point.z() - self.corner.z()) / self.size.z();
+        rec.set_face_normal(r, &face_normal(&p));
+        let shading = (n0 * (1f64 - b1 - b2) + n1 * b1 + n2 * b2).unit();
+        rec.normal = if rec.front_face { shading } else { -shading };
+        rec.mat_ptr = Some(self.mat_ptr.clone());
+        true
+    }
+}
+
+impl Hittable for Heightfield {
+    fn hit(
+        &self,
+        r: &Ray,
+        t_min: f64,
+        t_max: f64,
+        rec: &mut HitRecord,
+        _sampler: &mut dyn Sampler,
+    ) -> bool {
+        let (t_enter, t_exit) = match self.abox.clip(r, t_min, t_max) {
+            Some(span) => span,
+            None => return false,
+        };
+        let (o, d) = (r.origin(), r.direction());
+        let cells = [self.map.width - 1, self.map.depth - 1];
+        let origin = [self.corner.x(), self.corner.z()];
+        let (o, d) = ([o.x(), o.z()], [d.x(), d.z()]);
+
+        // A 2D DDA over the cells: `next` is where the ray crosses into the
+        // next column or row, and `delta` how far apart those crossings are.
+        let start = [o[0] + t_enter * d[0], o[1] + t_enter * d[1]];
+        let mut cell = [0usize; 2];
+        let mut step = [0isize; 2];
+        let mut next = [f64::INFINITY; 2];
+        let mut delta = [f64::INFINITY; 2];
+        for a in 0..2 {
+            let g = ((start[a] - origin[a]) / self.cell[a]).floor();
+            cell[a] = g.clamp(0f64, (cells[a] - 1) as f64) as usize;
+            if d[a] > 0f64 {
+                step[a] = 1;
+                let edge = origin[a] + (cell[a] + 1) as f64 * self.cell[a];
+                next[a] = (edge - o[a]) / d[a];
+                delta[a] = self.cell[a] / d[a];
+            } else if d[a] < 0f64 {
+                step[a] = -1;
+                let edge = origin[a] + cell[a] as f64 * self.cell[a];
+                next[a] = (edge - o[a]) / d[a];
+                delta[a] = -self.cell[a] / d[a];
+            }
+        }
+
+        let (y, dy) = (r.origin().y(), r.direction().y());
+        let mut t0 = t_enter;
+        loop {
+            let t1 = next[0].min(next[1]).min(t_exit);
+            let [lo, hi] = self.ranges[cell[1] * cells[0] + cell[0]];
+            let (ya, yb) = (y + t0 * dy, y + t1 * dy);
+            let margin = 1e-9 * self.size.y();
+            if ya.min(yb) <= hi + margin
+                && ya.max(yb) >= lo - margin
+                && self.hit_cell(r, cell[0], cell[1], t_min, t_max, rec)
+            {
+                return true;
+            }
+            if t1 >= t_exit {
+                return false;
+            }
+            let a = if next[0] < next[1] { 0 } else { 1 };
+            let moved = cell[a] as isize + step[a];
+            if moved < 0 || moved >= cells[a] as isize {
+                return false;
+            }
+            cell[a] = moved as usize;
+            t0 = next[a];
+            next[a] += delta[a];
+        }
+    }
+
+    fn bounding_box(&self, _time: [f64; 2], output_box: &mut Aabb) -> bool {
+        *output_box = self.abox.clone();
+        true
+    }
+}
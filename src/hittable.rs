@@ -1,8 +1,11 @@
-use std::rc::Rc;
+use std::sync::Arc;
 
 use crate::aabb::Aabb;
+use crate::hittable_list::HittableList;
 use crate::material::Scatter;
 use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::transform::{AnimatedTransform, Transform};
 use crate::vec3::{dot, Point3, Vec3};
 
 pub struct HitRecord {
@@ -12,7 +15,7 @@ pub struct HitRecord {
     pub u: f64,
     pub v: f64,
     pub front_face: bool,
-    pub mat_ptr: Option<Rc<dyn Scatter>>,
+    pub mat_ptr: Option<Arc<dyn Scatter>>,
 }
 
 impl HitRecord {
@@ -39,123 +42,223 @@ impl Default for HitRecord {
     }
 }
 
-pub trait Hittable {
-    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool;
+pub trait Hittable: Send + Sync {
+    fn hit(
+        &self,
+        r: &Ray,
+        t_min: f64,
+        t_max: f64,
+        rec: &mut HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> bool;
     fn bounding_box(&self, time: [f64; 2], output_box: &mut Aabb) -> bool;
+
+    // Light sampling. Primitives that can be lights report whether their
+    // material emits and implement `pdf_value`, the solid-angle density of
+    // `random` picking `direction` from `origin`. Containers instead push
+    // their emissive children onto `lights`, since only they hold the Arcs.
+    fn is_emissive(&self) -> bool {
+        false
+    }
+    fn collect_lights(&self, _lights: &mut HittableList) {}
+    fn pdf_value(
+        &self,
+        _origin: &Point3,
+        _direction: &Vec3,
+        _time: f64,
+        _sampler: &mut dyn Sampler,
+    ) -> f64 {
+        0f64
+    }
+    fn random(&self, _origin: &Point3, _time: f64, _sampler: &mut dyn Sampler) -> Vec3 {
+        Vec3::new(1f64, 0f64, 0f64)
+    }
 }
 
-pub struct Translate {
-    offset: Vec3,
-    ptr: Rc<dyn Hittable>,
+// An object under an arbitrary affine transform. Rays are taken into object
+// space instead of moving the object, so one object can back many
+// instances.
+pub struct TransformedInstance {
+    to_world: Transform,
+    to_object: Transform,
+    ptr: Arc<dyn Hittable>,
 }
 
-impl Translate {
-    pub fn new(offset: Vec3, ptr: Rc<dyn Hittable>) -> Self {
-        Self { offset, ptr }
+impl TransformedInstance {
+    pub fn new(transform: Transform, ptr: Arc<dyn Hittable>) -> Self {
+        Self {
+            to_world: transform,
+            to_object: transform.inverse(),
+            ptr,
+        }
     }
 }
 
-impl Hittable for Translate {
-    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
-        let moved_r = Ray::new(r.origin() - self.offset, r.direction(), r.time());
-        if !self.ptr.hit(&moved_r, t_min, t_max, rec) {
-            return false;
-        }
-        rec.point += self.offset;
-        let normal = rec.normal;
-        rec.set_face_normal(&moved_r, &normal);
-        true
+impl Hittable for TransformedInstance {
+    fn hit(
+        &self,
+        r: &Ray,
+        t_min: f64,
+        t_max: f64,
+        rec: &mut HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> bool {
+        instance_hit(
+            &*self.ptr,
+            &self.to_world,
+            &self.to_object,
+            r,
+            t_min,
+            t_max,
+            rec,
+            sampler,
+        )
     }
     fn bounding_box(&self, time: [f64; 2], output_box: &mut Aabb) -> bool {
         if !self.ptr.bounding_box(time, output_box) {
             return false;
         }
-        *output_box = Aabb::new(output_box.min + self.offset, output_box.max + self.offset);
+        *output_box = self.to_world.bbox(output_box);
         true
     }
+    fn is_emissive(&self) -> bool {
+        self.ptr.is_emissive()
+    }
+    // Lights inside a transformed container need the same transform.
+    fn collect_lights(&self, lights: &mut HittableList) {
+        let mut inner = HittableList::new();
+        self.ptr.collect_lights(&mut inner);
+        for light in inner.objects {
+            lights.push(Arc::new(TransformedInstance::new(self.to_world, light)));
+        }
+    }
+    fn pdf_value(
+        &self,
+        origin: &Point3,
+        direction: &Vec3,
+        time: f64,
+        sampler: &mut dyn Sampler,
+    ) -> f64 {
+        instance_pdf_value(
+            &*self.ptr,
+            &self.to_world,
+            &self.to_object,
+            origin,
+            direction,
+            time,
+            sampler,
+        )
+    }
+    fn random(&self, origin: &Point3, time: f64, sampler: &mut dyn Sampler) -> Vec3 {
+        let direction = self
+            .ptr
+            .random(&self.to_object.point(origin), time, sampler);
+        self.to_world.vector(&direction)
+    }
 }
 
-pub struct RotateY {
-    sin: f64,
-    cos: f64,
-    valid: bool,
-    bbox: Aabb,
-    ptr: Rc<dyn Hittable>,
+// An object moved by keyframes, posed at each ray's time.
+pub struct AnimatedInstance {
+    motion: AnimatedTransform,
+    ptr: Arc<dyn Hittable>,
 }
 
-impl RotateY {
-    pub fn new(angle: f64, ptr: Rc<dyn Hittable>) -> Self {
-        let angle = angle / 180f64 * std::f64::consts::PI;
-        let sin = angle.sin();
-        let cos = angle.cos();
-        let mut bbox = Aabb::default();
-        let valid = ptr.bounding_box([0f64, 1f64], &mut bbox);
-        let mut min = Point3::new(std::f64::INFINITY, std::f64::INFINITY, std::f64::INFINITY);
-        let mut max = Point3::new(
-            -std::f64::INFINITY,
-            -std::f64::INFINITY,
-            -std::f64::INFINITY,
-        );
-
-        for i in 0..2 {
-            for j in 0..2 {
-                for k in 0..2 {
-                    let x = i as f64 * bbox.max.x() + (1 - i) as f64 * bbox.min.x();
-                    let y = j as f64 * bbox.max.y() + (1 - j) as f64 * bbox.min.y();
-                    let z = k as f64 * bbox.max.z() + (1 - k) as f64 * bbox.min.z();
-
-                    let new_x = cos * x + sin * z;
-                    let new_z = -sin * x + cos * z;
-
-                    let tester = Vec3::new(new_x, y, new_z);
-                    for c in 0..3 {
-                        min.e[c] = min.e[c].min(tester.e[c]);
-                        max.e[c] = max.e[c].max(tester.e[c]);
-                    }
-                }
-            }
-        }
-        bbox = Aabb::new(min, max);
-        Self {
-            sin,
-            cos,
-            valid,
-            bbox,
-            ptr,
-        }
+impl AnimatedInstance {
+    pub fn new(motion: AnimatedTransform, ptr: Arc<dyn Hittable>) -> Self {
+        Self { motion, ptr }
     }
 }
 
-impl Hittable for RotateY {
-    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
-        let mut origin = r.origin();
-        let mut direction = r.direction();
-        origin.e[0] = self.cos * origin.e[0] - self.sin * origin.e[2];
-        origin.e[2] = self.sin * r.origin().e[0] + self.cos * origin.e[2];
-
-        direction.e[0] = self.cos * direction.e[0] - self.sin * direction.e[2];
-        direction.e[2] = self.sin * r.direction().e[0] + self.cos * direction.e[2];
-
-        let rotated_r = Ray::new(origin, direction, r.time());
-        if !self.ptr.hit(&rotated_r, t_min, t_max, rec) {
+impl Hittable for AnimatedInstance {
+    fn hit(
+        &self,
+        r: &Ray,
+        t_min: f64,
+        t_max: f64,
+        rec: &mut HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> bool {
+        let to_world = self.motion.at(r.time());
+        let to_object = to_world.inverse();
+        instance_hit(
+            &*self.ptr, &to_world, &to_object, r, t_min, t_max, rec, sampler,
+        )
+    }
+    fn bounding_box(&self, time: [f64; 2], output_box: &mut Aabb) -> bool {
+        if !self.ptr.bounding_box(time, output_box) {
             return false;
         }
-        let mut point = rec.point;
-        let mut normal = rec.normal;
-
-        point.e[0] = self.cos * rec.point.e[0] + self.sin * rec.point.e[2];
-        point.e[2] = -self.sin * rec.point.e[0] + self.cos * rec.point.e[2];
-
-        normal.e[0] = self.cos * rec.normal.e[0] + self.sin * rec.normal.e[2];
-        normal.e[2] = -self.sin * rec.normal.e[0] + self.cos * rec.normal.e[2];
-
-        rec.point = point;
-        rec.set_face_normal(&rotated_r, &normal);
+        *output_box = self.motion.bbox(output_box, time);
         true
     }
+    fn is_emissive(&self) -> bool {
+        self.ptr.is_emissive()
+    }
+    fn collect_lights(&self, lights: &mut HittableList) {
+        let mut inner = HittableList::new();
+        self.ptr.collect_lights(&mut inner);
+        for light in inner.objects {
+            lights.push(Arc::new(AnimatedInstance::new(self.motion.clone(), light)));
+        }
+    }
+    fn pdf_value(
+        &self,
+        origin: &Point3,
+        direction: &Vec3,
+        time: f64,
+        sampler: &mut dyn Sampler,
+    ) -> f64 {
+        let to_world = self.motion.at(time);
+        let to_object = to_world.inverse();
+        instance_pdf_value(
+            &*self.ptr, &to_world, &to_object, origin, direction, time, sampler,
+        )
+    }
+    fn random(&self, origin: &Point3, time: f64, sampler: &mut dyn Sampler) -> Vec3 {
+        let to_world = self.motion.at(time);
+        let to_object = to_world.inverse();
+        let direction = self.ptr.random(&to_object.point(origin), time, sampler);
+        to_world.vector(&direction)
+    }
+}
 
-    fn bounding_box(&self, _time: [f64; 2], output_box: &mut Aabb) -> bool {
-        *output_box = self.bbox.clone();
-        self.valid
+#[allow(clippy::too_many_arguments)]
+fn instance_hit(
+    ptr: &dyn Hittable,
+    to_world: &Transform,
+    to_object: &Transform,
+    r: &Ray,
+    t_min: f64,
+    t_max: f64,
+    rec: &mut HitRecord,
+    sampler: &mut dyn Sampler,
+) -> bool {
+    let object_r = to_object.ray(r);
+    if !ptr.hit(&object_r, t_min, t_max, rec, sampler) {
+        return false;
     }
+    // The normal already faces against the object-space ray, and the
+    // inverse transpose keeps it facing against `r`, so `front_face` carries
+    // over.
+    rec.point = to_world.point(&rec.point);
+    rec.normal = to_world.normal(&rec.normal).unit();
+    true
+}
+
+// Solid angles are only preserved by rigid transforms and uniform scales.
+// In general a direction w maps to Aw / |Aw|, which stretches solid angle by
+// |det A| / |Aw|^3 for unit w.
+fn instance_pdf_value(
+    ptr: &dyn Hittable,
+    to_world: &Transform,
+    to_object: &Transform,
+    origin: &Point3,
+    direction: &Vec3,
+    time: f64,
+    sampler: &mut dyn Sampler,
+) -> f64 {
+    let object_direction = to_object.vector(direction);
+    let pdf = ptr.pdf_value(&to_object.point(origin), &object_direction, time, sampler);
+    let stretch = to_world.vector(&object_direction.unit()).modulus();
+    pdf * stretch.powi(3) / to_world.matrix().determinant3().abs()
 }
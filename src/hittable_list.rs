@@ -1,65 +1,58 @@
-use std::rc::Rc;
-
-use rand::{rngs::ThreadRng, thread_rng};
+use std::sync::Arc;
 
 use crate::{
     aabb::Aabb,
     hittable::{HitRecord, Hittable},
     ray::Ray,
-    vec3::{Color, Point3, Vec3},
+    sampler::Sampler,
+    vec3::{Point3, Vec3},
 };
 
 #[derive(Clone)]
 pub struct HittableList {
-    pub objects: Vec<Rc<dyn Hittable>>,
-    pub rng: ThreadRng,
+    pub objects: Vec<Arc<dyn Hittable>>,
 }
 
 impl HittableList {
     pub fn new() -> Self {
         HittableList {
             objects: Vec::new(),
-            rng: thread_rng(),
         }
     }
 
-    pub fn push(&mut self, object: Rc<dyn Hittable>) {
+    pub fn push(&mut self, object: Arc<dyn Hittable>) {
         self.objects.push(object);
     }
 
-    pub fn ray_color(&mut self, r: Ray, background: &Color, depth: usize) -> Color {
-        if depth == 0 {
-            return Color::default();
-        }
-        let mut rec = HitRecord::default();
-        if !self.hit(&r, 0.001, f64::INFINITY, &mut rec) {
-            return *background;
-        }
-        match rec.mat_ptr.as_ref() {
-            Some(p) => {
-                let mut attenuation = Vec3::default();
-                let mut scattered = Ray::new(Point3::default(), Vec3::default(), 0f64);
-                let emitted = p.emitted(rec.u, rec.v, &rec.point);
-                if !p.scatter(&r, &rec, &mut self.rng, &mut attenuation, &mut scattered) {
-                    return emitted;
-                }
-                return attenuation * self.ray_color(scattered, background, depth - 1) + emitted;
-            }
-            None => {
-                return Color::default();
-            }
-        }
+    // The emissive primitives of the scene, for light sampling.
+    pub fn lights(&self) -> HittableList {
+        let mut lights = HittableList::new();
+        self.collect_lights(&mut lights);
+        lights
+    }
+}
+
+impl Default for HittableList {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 impl Hittable for HittableList {
-    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+    fn hit(
+        &self,
+        r: &Ray,
+        t_min: f64,
+        t_max: f64,
+        rec: &mut HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> bool {
         let mut temp_rec = HitRecord::default();
         let mut hit_anything = false;
         let mut closest_so_far = t_max;
 
         for object in self.objects.iter() {
-            if object.hit(r, t_min, closest_so_far, &mut temp_rec) {
+            if object.hit(r, t_min, closest_so_far, &mut temp_rec, sampler) {
                 hit_anything = true;
                 closest_so_far = temp_rec.t;
                 rec.t = temp_rec.t;
@@ -98,4 +91,35 @@ impl Hittable for HittableList {
 
         true
     }
+
+    fn collect_lights(&self, lights: &mut HittableList) {
+        for object in self.objects.iter() {
+            if object.is_emissive() {
+                lights.push(object.clone());
+            } else {
+                object.collect_lights(lights);
+            }
+        }
+    }
+
+    // Picks one member uniformly, so the density is the mean of theirs.
+    fn pdf_value(
+        &self,
+        origin: &Point3,
+        direction: &Vec3,
+        time: f64,
+        sampler: &mut dyn Sampler,
+    ) -> f64 {
+        let weight = 1f64 / self.objects.len() as f64;
+        self.objects
+            .iter()
+            .map(|object| weight * object.pdf_value(origin, direction, time, sampler))
+            .sum()
+    }
+
+    fn random(&self, origin: &Point3, time: f64, sampler: &mut dyn Sampler) -> Vec3 {
+        let n = self.objects.len();
+        let k = ((sampler.next_1d() * n as f64) as usize).min(n - 1);
+        self.objects[k].random(origin, time, sampler)
+    }
 }
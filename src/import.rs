@@ -0,0 +1,131 @@
+use std::{
+    error::Error,
+    fmt, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use image::ImageError;
+
+use crate::{
+    material::Scatter,
+    texture::VertexColorTexture,
+    triangle::{MeshError, TriangleMesh},
+    vec3::{Color, Point3, Vec3},
+};
+
+// Everything that can go wrong reading a model file or the files it
+// references. Paths are kept so messages point at the offending file.
+#[derive(Debug)]
+pub enum ImportError {
+    Io(PathBuf, io::Error),
+    Image(PathBuf, ImageError),
+    // A malformed line of a text format.
+    Syntax {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+    // Malformed binary data or a structural problem not tied to a line.
+    Format {
+        path: PathBuf,
+        message: String,
+    },
+    Mesh(PathBuf, MeshError),
+}
+
+impl ImportError {
+    pub fn syntax(path: &Path, line: usize, message: impl Into<String>) -> Self {
+        ImportError::Syntax {
+            path: path.to_path_buf(),
+            line,
+            message: message.into(),
+        }
+    }
+
+    pub fn format(path: &Path, message: impl Into<String>) -> Self {
+        ImportError::Format {
+            path: path.to_path_buf(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::Io(path, e) => write!(f, "{}: {}", path.display(), e),
+            ImportError::Image(path, e) => write!(f, "{}: {}", path.display(), e),
+            ImportError::Syntax {
+                path,
+                line,
+                message,
+            } => write!(f, "{}:{}: {}", path.display(), line, message),
+            ImportError::Format { path, message } => write!(f, "{}: {}", path.display(), message),
+            ImportError::Mesh(path, e) => write!(f, "{}: {}", path.display(), e),
+        }
+    }
+}
+
+impl Error for ImportError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ImportError::Io(_, e) => Some(e),
+            ImportError::Image(_, e) => Some(e),
+            ImportError::Mesh(_, e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+// A mesh as read from a file, before a material is chosen for it. Any
+// attribute list may be empty; the others hold one entry per position.
+#[derive(Default)]
+pub struct MeshData {
+    pub positions: Vec<Point3>,
+    pub normals: Vec<Vec3>,
+    pub uvs: Vec<[f64; 2]>,
+    pub colors: Vec<Color>,
+    pub indices: Vec<[usize; 3]>,
+}
+
+impl MeshData {
+    // Moves the vertex colors into a texture for `Lambertian::from_texture`.
+    // Every face gets its own three vertices whose UVs address the texture,
+    // replacing any UVs the mesh had.
+    pub fn vertex_color_texture(&mut self) -> Option<VertexColorTexture> {
+        if self.colors.is_empty() {
+            return None;
+        }
+        let mut positions = Vec::with_capacity(3 * self.indices.len());
+        let mut normals = Vec::new();
+        let mut uvs = Vec::with_capacity(3 * self.indices.len());
+        let mut faces = Vec::with_capacity(self.indices.len());
+        for (face, corners) in self.indices.iter_mut().enumerate() {
+            faces.push(corners.map(|i| self.colors[i]));
+            uvs.extend(VertexColorTexture::face_uvs(face));
+            for corner in corners.iter_mut() {
+                positions.push(self.positions[*corner]);
+                if !self.normals.is_empty() {
+                    normals.push(self.normals[*corner]);
+                }
+                *corner = positions.len() - 1;
+            }
+        }
+        self.positions = positions;
+        self.normals = normals;
+        self.uvs = uvs;
+        self.colors = Vec::new();
+        Some(VertexColorTexture::new(faces))
+    }
+
+    pub fn into_mesh(self, material: Arc<dyn Scatter>) -> Result<TriangleMesh, MeshError> {
+        TriangleMesh::new(
+            self.positions,
+            self.normals,
+            self.uvs,
+            self.indices,
+            material,
+        )
+    }
+}
@@ -0,0 +1,374 @@
+use crate::{
+    aabb::Aabb,
+    hittable::{HitRecord, Hittable},
+    hittable_list::HittableList,
+    ray::Ray,
+    sampler::Sampler,
+    scene::Scene,
+    vec3::{random_unit_sphere, Color, Point3, Vec3},
+};
+
+// Computes the radiance arriving at the camera along `r`.
+pub trait Integrator: Send + Sync {
+    fn li(&self, scene: &Scene, r: Ray, sampler: &mut dyn Sampler) -> Color;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IntegratorKind {
+    Naive,
+    Path,
+    Direct,
+    Normals,
+    Albedo,
+    AmbientOcclusion,
+}
+
+impl IntegratorKind {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "naive" => Some(IntegratorKind::Naive),
+            "path" => Some(IntegratorKind::Path),
+            "direct" => Some(IntegratorKind::Direct),
+            "normals" => Some(IntegratorKind::Normals),
+            "albedo" => Some(IntegratorKind::Albedo),
+            "ao" => Some(IntegratorKind::AmbientOcclusion),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            IntegratorKind::Naive => "naive",
+            IntegratorKind::Path => "path",
+            IntegratorKind::Direct => "direct",
+            IntegratorKind::Normals => "normals",
+            IntegratorKind::Albedo => "albedo",
+            IntegratorKind::AmbientOcclusion => "ao",
+        }
+    }
+
+    pub fn create(self, scene: &Scene, max_depth: usize, rr_depth: usize) -> Box<dyn Integrator> {
+        match self {
+            IntegratorKind::Naive => Box::new(NaivePathTracer::new(max_depth, rr_depth)),
+            IntegratorKind::Path => Box::new(PathTracer::new(scene, max_depth, rr_depth)),
+            IntegratorKind::Direct => Box::new(DirectLighting::new(scene, max_depth)),
+            IntegratorKind::Normals => Box::new(NormalsIntegrator),
+            IntegratorKind::Albedo => Box::new(AlbedoIntegrator),
+            IntegratorKind::AmbientOcclusion => Box::new(AmbientOcclusion::for_scene(scene)),
+        }
+    }
+}
+
+// Follows one BSDF-sampled path and only counts emission the path happens
+// to hit.
+pub struct NaivePathTracer {
+    max_depth: usize,
+    rr_depth: usize,
+}
+
+impl NaivePathTracer {
+    pub fn new(max_depth: usize, rr_depth: usize) -> Self {
+        Self {
+            max_depth,
+            rr_depth,
+        }
+    }
+}
+
+impl Integrator for NaivePathTracer {
+    fn li(&self, scene: &Scene, mut r: Ray, sampler: &mut dyn Sampler) -> Color {
+        let mut radiance = Color::default();
+        let mut throughput = Color::new(1f64, 1f64, 1f64);
+        for depth in 0..self.max_depth {
+            let mut rec = HitRecord::default();
+            if !scene.world.hit(&r, 0.001, f64::INFINITY, &mut rec, sampler) {
+                radiance += throughput * scene.background;
+                break;
+            }
+            let p = match rec.mat_ptr.as_ref() {
+                Some(p) => p,
+                None => break,
+            };
+            radiance += throughput * p.emitted(rec.u, rec.v, &rec.point);
+            let mut attenuation = Vec3::default();
+            let mut scattered = Ray::new(Point3::default(), Vec3::default(), 0f64);
+            if !p.scatter(&r, &rec, sampler, &mut attenuation, &mut scattered) {
+                break;
+            }
+            throughput = throughput * attenuation;
+            r = scattered;
+            if depth + 1 >= self.rr_depth && !russian_roulette(&mut throughput, sampler) {
+                break;
+            }
+        }
+        radiance
+    }
+}
+
+// Next-event estimation: every diffuse bounce also samples a point on a
+// light directly, and emission found by either strategy is weighted with the
+// power heuristic.
+pub struct PathTracer {
+    max_depth: usize,
+    rr_depth: usize,
+    lights: HittableList,
+}
+
+impl PathTracer {
+    pub fn new(scene: &Scene, max_depth: usize, rr_depth: usize) -> Self {
+        Self {
+            max_depth,
+            rr_depth,
+            lights: scene.world.lights(),
+        }
+    }
+}
+
+impl Integrator for PathTracer {
+    fn li(&self, scene: &Scene, r: Ray, sampler: &mut dyn Sampler) -> Color {
+        mis_li(
+            scene,
+            &self.lights,
+            r,
+            self.max_depth,
+            self.rr_depth,
+            usize::MAX,
+            sampler,
+        )
+    }
+}
+
+// Light reaching the first diffuse surface straight from an emitter or the
+// background, seen through any number of specular bounces.
+pub struct DirectLighting {
+    max_depth: usize,
+    lights: HittableList,
+}
+
+impl DirectLighting {
+    pub fn new(scene: &Scene, max_depth: usize) -> Self {
+        Self {
+            max_depth,
+            lights: scene.world.lights(),
+        }
+    }
+}
+
+impl Integrator for DirectLighting {
+    fn li(&self, scene: &Scene, r: Ray, sampler: &mut dyn Sampler) -> Color {
+        // A single diffuse bounce is not worth a roulette.
+        mis_li(
+            scene,
+            &self.lights,
+            r,
+            self.max_depth,
+            usize::MAX,
+            1,
+            sampler,
+        )
+    }
+}
+
+// A path stops scattering once it has made `diffuse_bounces` diffuse
+// bounces. `bsdf_pdf` is the density the last bounce sampled `r` with, or
+// `None` when light sampling could not have produced it (the camera ray,
+// specular bounces).
+fn mis_li(
+    scene: &Scene,
+    lights: &HittableList,
+    mut r: Ray,
+    max_depth: usize,
+    rr_depth: usize,
+    mut diffuse_bounces: usize,
+    sampler: &mut dyn Sampler,
+) -> Color {
+    let mut radiance = Color::default();
+    let mut throughput = Color::new(1f64, 1f64, 1f64);
+    let mut bsdf_pdf = None;
+    for depth in 0..max_depth {
+        let mut rec = HitRecord::default();
+        if !scene.world.hit(&r, 0.001, f64::INFINITY, &mut rec, sampler) {
+            radiance += throughput * scene.background;
+            break;
+        }
+        let p = match rec.mat_ptr.clone() {
+            Some(p) => p,
+            None => break,
+        };
+
+        let mut emitted = p.emitted(rec.u, rec.v, &rec.point);
+        if let Some(bsdf_pdf) = bsdf_pdf {
+            if p.is_emissive() {
+                let light_pdf = lights.pdf_value(&r.origin(), &r.direction(), r.time(), sampler);
+                emitted *= power_heuristic(bsdf_pdf, light_pdf);
+            }
+        }
+        radiance += throughput * emitted;
+
+        let mut attenuation = Vec3::default();
+        let mut scattered = Ray::new(Point3::default(), Vec3::default(), 0f64);
+        if !p.scatter(&r, &rec, sampler, &mut attenuation, &mut scattered) {
+            break;
+        }
+        let scattering_pdf = p.scattering_pdf(&r, &rec, &scattered);
+        if scattering_pdf > 0f64 {
+            if diffuse_bounces == 0 {
+                break;
+            }
+            diffuse_bounces -= 1;
+            radiance += throughput * attenuation * sample_light(scene, lights, &r, &rec, sampler);
+            bsdf_pdf = Some(scattering_pdf);
+        } else {
+            bsdf_pdf = None;
+        }
+
+        throughput = throughput * attenuation;
+        r = scattered;
+        if depth + 1 >= rr_depth && !russian_roulette(&mut throughput, sampler) {
+            break;
+        }
+    }
+    radiance
+}
+
+// Emission from one point sampled on `lights`, MIS-weighted against the
+// BSDF. The caller multiplies in the attenuation.
+fn sample_light(
+    scene: &Scene,
+    lights: &HittableList,
+    r: &Ray,
+    rec: &HitRecord,
+    sampler: &mut dyn Sampler,
+) -> Color {
+    let p = match rec.mat_ptr.as_ref() {
+        Some(p) if !lights.objects.is_empty() => p,
+        _ => return Color::default(),
+    };
+    let direction = lights.random(&rec.point, r.time(), sampler);
+    let to_light = Ray::new(rec.point, direction, r.time());
+    let light_pdf = lights.pdf_value(&rec.point, &direction, r.time(), sampler);
+    let bsdf_pdf = p.scattering_pdf(r, rec, &to_light);
+    let mut light_rec = HitRecord::default();
+    if light_pdf <= 0f64
+        || bsdf_pdf <= 0f64
+        || !scene
+            .world
+            .hit(&to_light, 0.001, f64::INFINITY, &mut light_rec, sampler)
+    {
+        return Color::default();
+    }
+    match light_rec.mat_ptr.as_ref() {
+        Some(light) => {
+            light.emitted(light_rec.u, light_rec.v, &light_rec.point)
+                * (bsdf_pdf / light_pdf * power_heuristic(light_pdf, bsdf_pdf))
+        }
+        None => Color::default(),
+    }
+}
+
+// Ends the path with probability one minus its largest throughput
+// component, and scales survivors up to keep the estimate unbiased. The
+// survival chance is capped so bright paths still terminate eventually.
+fn russian_roulette(throughput: &mut Color, sampler: &mut dyn Sampler) -> bool {
+    let survival = throughput
+        .x()
+        .max(throughput.y())
+        .max(throughput.z())
+        .min(0.95);
+    if survival <= 0f64 || sampler.next_1d() >= survival {
+        return false;
+    }
+    *throughput /= survival;
+    true
+}
+
+fn power_heuristic(f_pdf: f64, g_pdf: f64) -> f64 {
+    let (f, g) = (f_pdf * f_pdf, g_pdf * g_pdf);
+    f / (f + g)
+}
+
+// Shading normals of the first hit mapped from [-1, 1] to [0, 1].
+pub struct NormalsIntegrator;
+
+impl Integrator for NormalsIntegrator {
+    fn li(&self, scene: &Scene, r: Ray, sampler: &mut dyn Sampler) -> Color {
+        let mut rec = HitRecord::default();
+        if !scene.world.hit(&r, 0.001, f64::INFINITY, &mut rec, sampler) {
+            return Color::default();
+        }
+        (rec.normal + Vec3::new(1f64, 1f64, 1f64)) * 0.5
+    }
+}
+
+// The attenuation of the first hit, or its emission for lights.
+pub struct AlbedoIntegrator;
+
+impl Integrator for AlbedoIntegrator {
+    fn li(&self, scene: &Scene, r: Ray, sampler: &mut dyn Sampler) -> Color {
+        let mut rec = HitRecord::default();
+        if !scene.world.hit(&r, 0.001, f64::INFINITY, &mut rec, sampler) {
+            return scene.background;
+        }
+        let p = match rec.mat_ptr.as_ref() {
+            Some(p) => p,
+            None => return Color::default(),
+        };
+        let mut attenuation = Vec3::default();
+        let mut scattered = Ray::new(Point3::default(), Vec3::default(), 0f64);
+        if p.scatter(&r, &rec, sampler, &mut attenuation, &mut scattered) {
+            attenuation
+        } else {
+            p.emitted(rec.u, rec.v, &rec.point)
+        }
+    }
+}
+
+// White where a cosine-distributed ray from the first hit escapes within
+// `distance`, black where it is blocked.
+pub struct AmbientOcclusion {
+    distance: f64,
+}
+
+impl AmbientOcclusion {
+    pub fn new(distance: f64) -> Self {
+        Self { distance }
+    }
+
+    // A tenth of the scene's extent, so closed scenes such as the Cornell
+    // box are not fully occluded.
+    pub fn for_scene(scene: &Scene) -> Self {
+        let mut bbox = Aabb::default();
+        if !scene.world.bounding_box([0f64, 1f64], &mut bbox) {
+            return Self::new(f64::INFINITY);
+        }
+        let extent = (bbox.max - bbox.min).modulus();
+        Self::new(if extent.is_finite() && extent > 0f64 {
+            0.1 * extent
+        } else {
+            f64::INFINITY
+        })
+    }
+}
+
+impl Integrator for AmbientOcclusion {
+    fn li(&self, scene: &Scene, r: Ray, sampler: &mut dyn Sampler) -> Color {
+        let mut rec = HitRecord::default();
+        if !scene.world.hit(&r, 0.001, f64::INFINITY, &mut rec, sampler) {
+            return Color::new(1f64, 1f64, 1f64);
+        }
+        let mut direction = rec.normal + random_unit_sphere(sampler);
+        if direction.near_zero() {
+            direction = rec.normal;
+        }
+        let occluder = Ray::new(rec.point, direction.unit(), r.time());
+        let mut occluder_rec = HitRecord::default();
+        if scene
+            .world
+            .hit(&occluder, 0.001, self.distance, &mut occluder_rec, sampler)
+        {
+            Color::default()
+        } else {
+            Color::new(1f64, 1f64, 1f64)
+        }
+    }
+}
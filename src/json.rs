@@ -0,0 +1,384 @@
+use std::fmt;
+
+#[derive(Debug, Clone)]
+pub enum ValueKind {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+#[derive(Debug, Clone)]
+pub struct Value {
+    pub kind: ValueKind,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Value {
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match &self.kind {
+            ValueKind::Object(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self.kind {
+            ValueKind::Number(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self.kind {
+            ValueKind::Bool(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match &self.kind {
+            ValueKind::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match &self.kind {
+            ValueKind::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&[(String, Value)]> {
+        match &self.kind {
+            ValueKind::Object(entries) => Some(entries),
+            _ => None,
+        }
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self.kind {
+            ValueKind::Null => "null",
+            ValueKind::Bool(_) => "boolean",
+            ValueKind::Number(_) => "number",
+            ValueKind::String(_) => "string",
+            ValueKind::Array(_) => "array",
+            ValueKind::Object(_) => "object",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+// Plain JSON, plus `//` line comments and trailing commas so scene files
+// can be annotated and edited by hand.
+pub fn parse(src: &str) -> Result<Value, ParseError> {
+    let mut parser = Parser {
+        chars: src.chars().collect(),
+        pos: 0,
+        line: 1,
+        column: 1,
+    };
+    let value = parser.value()?;
+    parser.skip_whitespace();
+    if parser.peek().is_some() {
+        return Err(parser.error("trailing characters after document"));
+    }
+    Ok(value)
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+    column: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn error(&self, message: &str) -> ParseError {
+        ParseError {
+            line: self.line,
+            column: self.column,
+            message: message.to_string(),
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), ParseError> {
+        if self.peek() == Some(c) {
+            self.bump();
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected `{}`", c)))
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() {
+                self.bump();
+            } else if c == '/' && self.chars.get(self.pos + 1) == Some(&'/') {
+                while let Some(c) = self.bump() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, ParseError> {
+        self.skip_whitespace();
+        let (line, column) = (self.line, self.column);
+        let kind = match self.peek() {
+            None => return Err(self.error("unexpected end of input")),
+            Some('{') => self.object()?,
+            Some('[') => self.array()?,
+            Some('"') => ValueKind::String(self.string()?),
+            Some(c) if c == '-' || c.is_ascii_digit() => ValueKind::Number(self.number()?),
+            Some(c) if c.is_ascii_alphabetic() => match self.word().as_str() {
+                "true" => ValueKind::Bool(true),
+                "false" => ValueKind::Bool(false),
+                "null" => ValueKind::Null,
+                w => {
+                    return Err(ParseError {
+                        line,
+                        column,
+                        message: format!("unexpected identifier `{}`", w),
+                    })
+                }
+            },
+            Some(c) => return Err(self.error(&format!("unexpected character `{}`", c))),
+        };
+        Ok(Value { kind, line, column })
+    }
+
+    fn word(&mut self) -> String {
+        let mut w = String::new();
+        while let Some(c) = self.peek() {
+            if !c.is_ascii_alphanumeric() && c != '_' {
+                break;
+            }
+            w.push(c);
+            self.bump();
+        }
+        w
+    }
+
+    fn number(&mut self) -> Result<f64, ParseError> {
+        let (line, column) = (self.line, self.column);
+        let mut text = String::new();
+        while let Some(c) = self.peek() {
+            if !(c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E')) {
+                break;
+            }
+            text.push(c);
+            self.bump();
+        }
+        text.parse().map_err(|_| ParseError {
+            line,
+            column,
+            message: format!("invalid number `{}`", text),
+        })
+    }
+
+    fn string(&mut self) -> Result<String, ParseError> {
+        self.expect('"')?;
+        let mut s = String::new();
+        loop {
+            match self.bump() {
+                None => return Err(self.error("unterminated string")),
+                Some('"') => return Ok(s),
+                Some('\\') => match self.bump() {
+                    Some('"') => s.push('"'),
+                    Some('\\') => s.push('\\'),
+                    Some('/') => s.push('/'),
+                    Some('n') => s.push('\n'),
+                    Some('t') => s.push('\t'),
+                    Some('r') => s.push('\r'),
+                    Some('b') => s.push('\u{8}'),
+                    Some('f') => s.push('\u{c}'),
+                    Some('u') => {
+                        let mut code = 0u32;
+                        for _ in 0..4 {
+                            let digit = self
+                                .bump()
+                                .and_then(|c| c.to_digit(16))
+                                .ok_or_else(|| self.error("invalid unicode escape"))?;
+                            code = code * 16 + digit;
+                        }
+                        s.push(char::from_u32(code).unwrap_or('\u{fffd}'));
+                    }
+                    _ => return Err(self.error("invalid escape sequence")),
+                },
+                Some(c) => s.push(c),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<ValueKind, ParseError> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        loop {
+            self.skip_whitespace();
+            if self.peek() == Some(']') {
+                self.bump();
+                return Ok(ValueKind::Array(items));
+            }
+            items.push(self.value()?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => {
+                    self.bump();
+                }
+                Some(']') => {}
+                _ => return Err(self.error("expected `,` or `]`")),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<ValueKind, ParseError> {
+        self.expect('{')?;
+        let mut entries: Vec<(String, Value)> = Vec::new();
+        loop {
+            self.skip_whitespace();
+            if self.peek() == Some('}') {
+                self.bump();
+                return Ok(ValueKind::Object(entries));
+            }
+            let (line, column) = (self.line, self.column);
+            let key = self.string()?;
+            if entries.iter().any(|(k, _)| *k == key) {
+                return Err(ParseError {
+                    line,
+                    column,
+                    message: format!("duplicate key `{}`", key),
+                });
+            }
+            self.skip_whitespace();
+            self.expect(':')?;
+            let value = self.value()?;
+            entries.push((key, value));
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => {
+                    self.bump();
+                }
+                Some('}') => {}
+                _ => return Err(self.error("expected `,` or `}`")),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(src: &str) -> (usize, usize, String) {
+        let e = parse(src).unwrap_err();
+        (e.line, e.column, e.message)
+    }
+
+    #[test]
+    fn parses_every_kind_of_value() {
+        let v = parse(r#"{"a": [1, -2.5e1, true, false, null], "b": {"c": "x\n\"A"}}"#).unwrap();
+        let a = v.get("a").and_then(Value::as_array).unwrap();
+        assert_eq!(a[0].as_f64(), Some(1f64));
+        assert_eq!(a[1].as_f64(), Some(-25f64));
+        assert_eq!(a[2].as_bool(), Some(true));
+        assert_eq!(a[3].as_bool(), Some(false));
+        assert_eq!(a[4].type_name(), "null");
+        let c = v.get("b").and_then(|b| b.get("c")).and_then(Value::as_str);
+        assert_eq!(c, Some("x\n\"A"));
+    }
+
+    #[test]
+    fn allows_comments_and_trailing_commas() {
+        let v =
+            parse("// a scene\n{\n  \"a\": [1, 2,], // two\n  \"b\": {\"c\": 3,},\n}\n// done\n")
+                .unwrap();
+        assert_eq!(
+            v.get("a").and_then(Value::as_array).map(<[_]>::len),
+            Some(2)
+        );
+        assert_eq!(
+            v.get("b").and_then(|b| b.get("c")).and_then(Value::as_f64),
+            Some(3f64)
+        );
+    }
+
+    #[test]
+    fn records_where_values_start() {
+        let v = parse("{\n  \"a\": [1, 2]\n}").unwrap();
+        assert_eq!((v.line, v.column), (1, 1));
+        let two = &v.get("a").and_then(Value::as_array).unwrap()[1];
+        assert_eq!((two.line, two.column), (2, 12));
+    }
+
+    #[test]
+    fn reports_errors_with_positions() {
+        assert_eq!(
+            error("{\n  \"a\": [1 2]\n}"),
+            (2, 11, "expected `,` or `]`".to_string())
+        );
+        assert_eq!(
+            error("{\"a\": 1,\n \"a\": 2}"),
+            (2, 2, "duplicate key `a`".to_string())
+        );
+        assert_eq!(
+            error("[tru]"),
+            (1, 2, "unexpected identifier `tru`".to_string())
+        );
+        assert_eq!(
+            error("[1.2.3]"),
+            (1, 2, "invalid number `1.2.3`".to_string())
+        );
+        assert_eq!(error("\"abc"), (1, 5, "unterminated string".to_string()));
+        assert_eq!(
+            error("{} x"),
+            (1, 4, "trailing characters after document".to_string())
+        );
+        assert_eq!(error(""), (1, 1, "unexpected end of input".to_string()));
+    }
+
+    #[test]
+    fn displays_the_position_first() {
+        let e = parse("[1,\n ?]").unwrap_err();
+        assert_eq!(e.to_string(), "2:2: unexpected character `?`");
+    }
+}
@@ -1,16 +1,35 @@
 pub mod aabb;
-pub mod aarect;
+pub mod accumulator;
 pub mod bvh;
 pub mod camera;
 pub mod color;
 pub mod constant_medium;
+pub mod csg;
 pub mod cube;
+pub mod framebuffer;
+pub mod gltf;
+pub mod heightfield;
 pub mod hittable;
 pub mod hittable_list;
+pub mod import;
+pub mod integrator;
+pub mod json;
 pub mod material;
+pub mod obj;
+pub mod onb;
 pub mod perlin;
+pub mod ply;
+pub mod quad;
+pub mod quadric;
 pub mod ray;
+pub mod render;
 pub mod rtw;
+pub mod sampler;
+pub mod scene;
+pub mod sdf;
 pub mod sphere;
+pub mod stl;
 pub mod texture;
+pub mod transform;
+pub mod triangle;
 pub mod vec3;
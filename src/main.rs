@@ -1,231 +1,574 @@
-use rand::Rng;
-use rtw::aarect::{XYRect, YZRect, ZXRect};
+use rtw::accumulator::Accumulator;
+use rtw::bvh::{BvhBuilder, BvhLayout, BvhOptions};
 use rtw::camera::Camera;
-use rtw::color::paint;
 use rtw::cube::Cube;
-use rtw::hittable::{RotateY, Translate};
+use rtw::framebuffer::ImageFormat;
+use rtw::hittable::TransformedInstance;
 use rtw::hittable_list::HittableList;
-use rtw::material::{Dielectric, DiffuseLight, Lambertian, Metal};
+use rtw::integrator::IntegratorKind;
+use rtw::material::{Dielectric, DiffuseLight, Lambertian, Metal, Scatter};
+use rtw::quad::Quad;
+use rtw::render::{self, tiles, RenderSettings};
+use rtw::rtw::fnv1a;
+use rtw::sampler::SamplerKind;
+use rtw::scene::{self, Scene, SceneError};
 use rtw::sphere::Sphere;
 use rtw::texture::{CheckerTexture, ImageTexture};
+use rtw::transform::{Mat4, Transform};
 use rtw::vec3::*;
 
-use std::path::Path;
-use std::rc::Rc;
-use std::{
-    fs::{self},
-    sync::mpsc,
-    thread,
-};
-const THREAD_NUM: usize = 16;
-
-fn main() -> std::io::Result<()> {
-    const IMAGE_WIDTH: usize = 920;
-    const ASPECT_RATIO: f64 = 1f64;
-    const IMAGE_HEIGHT: usize = (IMAGE_WIDTH as f64 / ASPECT_RATIO) as usize;
-    const TOT_SIZE: usize = 12 * IMAGE_HEIGHT * IMAGE_WIDTH + 20;
-    const SAMPLES_PER_PIXEL: usize = 100000;
-    const MAX_DEPTH: usize = 50;
-    const HPTHREAD: usize = IMAGE_HEIGHT / THREAD_NUM + 1;
-
-    let background = Color::default();
-    let mut image: String = String::with_capacity(TOT_SIZE);
-    image.push_str(&format!("P3\n{IMAGE_WIDTH} {IMAGE_HEIGHT}\n255\n"));
-    let mut handles = vec![];
-    let mut buffer = vec![];
-
-    let (tx, rx) = mpsc::channel();
-    for t in (0..THREAD_NUM).rev() {
-        let tx = tx.clone();
-
-        handles.push(thread::spawn(move || {
-            // camera
-            let lookfrom = Point3::new(278f64, 278f64, -800f64);
-            let lookat = Point3::new(278f64, 278f64, 0f64);
-            let mut camera = Camera::new(
-                lookfrom,
-                lookat,
-                Vec3::new(0f64, 1f64, 0f64),
-                40f64,
-                ASPECT_RATIO,
-                0.0f64,
-                (lookfrom - lookat).modulus(),
-                [0f64, 1f64],
-            );
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use std::{fs, process};
+
+const USAGE: &str = "\
+Usage: rtw [OPTIONS]
+
+Options:
+  -s, --scene <FILE>        render a JSON scene file or a glTF model (.gltf, .glb)
+  -b, --builtin <NAME>      render a built-in scene: cornell, earth, spheres [default: cornell]
+  -w, --width <PIXELS>      image width [default: 920]
+      --height <PIXELS>     image height [default: width / scene aspect ratio]
+  -n, --spp <N>             samples per pixel [default: 100000]
+  -d, --max-depth <N>       maximum ray bounce depth [default: 50]
+      --rr-depth <N>        bounces before Russian roulette may end a path [default: 3]
+  -j, --threads <N>         number of render threads [default: 16]
+  -t, --tile-size <PIXELS>  edge length of the square tiles handed to threads [default: 32]
+      --seed <N>            seed for the sample streams; equal seeds give identical images [default: 0]
+      --sampler <NAME>      sample generator: independent, stratified, halton, sobol
+                            [default: independent]
+  -i, --integrator <NAME>   light transport: naive, path (with light sampling), direct,
+                            normals, albedo, ao [default: naive]
+  -p, --pass-spp <N>        render in passes of N samples per pixel, rewriting the output
+                            image after each pass as a preview [default: all at once]
+  -c, --checkpoint <FILE>   save the accumulated samples to FILE after every pass
+      --resume              continue from the checkpoint file if it exists
+      --bvh <NAME>          BVH builder for meshes and `bvh` objects: sah, median [default: sah]
+      --bvh-layout <NAME>   BVH storage: flat (one array) or tree (linked nodes) [default: flat]
+      --bvh-stats           print the node count, depth and SAH cost of every BVH built
+  -o, --output <FILE>       output image path [default: rtw.ppm]
+  -f, --format <FORMAT>     output format: ppm, png, pfm [default: from the output extension]
+  -g, --gamma <GAMMA>       display gamma for ppm and png output; pfm stays linear [default: 2]
+  -h, --help                print this help
+";
+
+#[derive(Clone)]
+enum SceneSource {
+    File(PathBuf),
+    Builtin(String),
+}
+
+#[derive(Clone)]
+struct Options {
+    scene: SceneSource,
+    width: usize,
+    height: Option<usize>,
+    samples_per_pixel: usize,
+    max_depth: usize,
+    rr_depth: usize,
+    threads: usize,
+    tile_size: usize,
+    seed: u64,
+    sampler: SamplerKind,
+    integrator: IntegratorKind,
+    output: PathBuf,
+    format: Option<ImageFormat>,
+    gamma: f64,
+    pass_spp: Option<usize>,
+    checkpoint: Option<PathBuf>,
+    resume: bool,
+    bvh: BvhOptions,
+    bvh_stats: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            scene: SceneSource::Builtin("cornell".to_string()),
+            width: 920,
+            height: None,
+            samples_per_pixel: 100000,
+            max_depth: 50,
+            rr_depth: 3,
+            threads: 16,
+            tile_size: 32,
+            seed: 0,
+            sampler: SamplerKind::Independent,
+            integrator: IntegratorKind::Naive,
+            output: PathBuf::from("rtw.ppm"),
+            format: None,
+            gamma: 2f64,
+            pass_spp: None,
+            checkpoint: None,
+            resume: false,
+            bvh: BvhOptions::default(),
+            bvh_stats: false,
+        }
+    }
+}
 
-            // world
-            let mut world = scene(2);
-
-            let mut buffer = String::with_capacity(TOT_SIZE / THREAD_NUM);
-            for i in (t * HPTHREAD..((t + 1) * HPTHREAD).min(IMAGE_HEIGHT)).rev() {
-                for j in 0..IMAGE_WIDTH {
-                    let mut pixel_color = Color::new(0.0, 0.0, 0.0);
-                    for _ in 0..SAMPLES_PER_PIXEL {
-                        let u =
-                            (j as f64 + world.rng.gen_range(0.0..1.0)) / (IMAGE_WIDTH - 1) as f64;
-                        let v =
-                            (i as f64 + world.rng.gen_range(0.0..1.0)) / (IMAGE_HEIGHT - 1) as f64;
-                        let r = camera.get_ray(u, v);
-                        pixel_color += world.ray_color(r, &background, MAX_DEPTH);
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Option<Options>, String> {
+    let mut options = Options::default();
+    while let Some(arg) = args.next() {
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => {
+                (flag.to_string(), Some(value.to_string()))
+            }
+            _ => (arg, None),
+        };
+        match flag.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "--resume" => {
+                options.resume = true;
+                continue;
+            }
+            "--bvh-stats" => {
+                options.bvh_stats = true;
+                continue;
+            }
+            _ => {}
+        }
+        let mut value = || {
+            inline
+                .clone()
+                .or_else(|| args.next())
+                .ok_or_else(|| format!("missing value for `{}`", flag))
+        };
+        match flag.as_str() {
+            "-s" | "--scene" => options.scene = SceneSource::File(PathBuf::from(value()?)),
+            "-b" | "--builtin" => options.scene = SceneSource::Builtin(value()?),
+            "-w" | "--width" => options.width = positive(&flag, &value()?)?,
+            "--height" => options.height = Some(positive(&flag, &value()?)?),
+            "-n" | "--spp" => options.samples_per_pixel = positive(&flag, &value()?)?,
+            "-d" | "--max-depth" => options.max_depth = positive(&flag, &value()?)?,
+            "--rr-depth" => options.rr_depth = positive(&flag, &value()?)?,
+            "-j" | "--threads" => options.threads = positive(&flag, &value()?)?,
+            "-t" | "--tile-size" => options.tile_size = positive(&flag, &value()?)?,
+            "-p" | "--pass-spp" => options.pass_spp = Some(positive(&flag, &value()?)?),
+            "-c" | "--checkpoint" => options.checkpoint = Some(PathBuf::from(value()?)),
+            "--seed" => {
+                let seed = value()?;
+                options.seed = seed
+                    .parse()
+                    .map_err(|_| format!("`{}` expects an integer, got `{}`", flag, seed))?
+            }
+            "--sampler" => {
+                let name = value()?;
+                options.sampler = SamplerKind::from_name(&name)
+                    .ok_or_else(|| format!("unknown sampler `{}`", name))?
+            }
+            "-i" | "--integrator" => {
+                let name = value()?;
+                options.integrator = IntegratorKind::from_name(&name)
+                    .ok_or_else(|| format!("unknown integrator `{}`", name))?
+            }
+            "--bvh" => {
+                let name = value()?;
+                options.bvh.builder = BvhBuilder::from_name(&name)
+                    .ok_or_else(|| format!("unknown BVH builder `{}`", name))?
+            }
+            "--bvh-layout" => {
+                let name = value()?;
+                options.bvh.layout = BvhLayout::from_name(&name)
+                    .ok_or_else(|| format!("unknown BVH layout `{}`", name))?
+            }
+            "-o" | "--output" => options.output = PathBuf::from(value()?),
+            "-f" | "--format" => {
+                let name = value()?;
+                options.format = Some(
+                    ImageFormat::from_name(&name)
+                        .ok_or_else(|| format!("unknown output format `{}`", name))?,
+                )
+            }
+            "-g" | "--gamma" => {
+                let gamma = value()?;
+                options.gamma = match gamma.parse::<f64>() {
+                    Ok(g) if g > 0f64 => g,
+                    _ => {
+                        return Err(format!(
+                            "`{}` expects a positive number, got `{}`",
+                            flag, gamma
+                        ))
                     }
-                    buffer.push_str(&paint(pixel_color, SAMPLES_PER_PIXEL));
                 }
             }
-            tx.send((t, buffer)).unwrap();
-        }));
+            _ => return Err(format!("unknown option `{}`", flag)),
+        }
+    }
+    Ok(Some(options))
+}
+
+fn positive(flag: &str, value: &str) -> Result<usize, String> {
+    match value.parse::<usize>() {
+        Ok(n) if n > 0 => Ok(n),
+        _ => Err(format!(
+            "`{}` expects a positive integer, got `{}`",
+            flag, value
+        )),
+    }
+}
+
+fn main() {
+    let options = match parse_args(std::env::args().skip(1)) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            print!("{}", USAGE);
+            return;
+        }
+        Err(e) => {
+            eprintln!("rtw: {}\n\n{}", e, USAGE);
+            process::exit(2);
+        }
+    };
+    if let Err(e) = render(&options) {
+        eprintln!("rtw: {}", e);
+        process::exit(1);
     }
+}
 
-    for _ in 0..THREAD_NUM {
-        let product = rx.recv().unwrap();
-        println!("Thread {} completed", product.0);
-        buffer.push(product);
+fn load_scene(source: &SceneSource, bvh: BvhOptions) -> Result<Scene, String> {
+    match source {
+        SceneSource::File(path) => {
+            // Import errors already name the file they come from.
+            scene::load(path, bvh).map_err(|e| match e {
+                SceneError::Import(e) => e.to_string(),
+                e => format!("{}: {}", path.display(), e),
+            })
+        }
+        SceneSource::Builtin(name) => builtin_scene(name),
     }
-    buffer.sort_by_key(|x| x.0);
-    for (_, st) in buffer.iter().rev() {
-        image.push_str(st);
+}
+
+fn render(options: &Options) -> Result<(), Box<dyn Error>> {
+    let mut scene = load_scene(&options.scene, options.bvh)?;
+    if options.bvh_stats {
+        for (field, stats) in &scene.bvh_stats {
+            eprintln!("BVH {} ({}): {}", field, options.bvh.builder.name(), stats);
+        }
+    }
+    let image_width = options.width;
+    let image_height = options
+        .height
+        .unwrap_or((image_width as f64 / scene.aspect_ratio) as usize)
+        .max(2);
+    if options.height.is_some() {
+        scene
+            .camera
+            .set_aspect_ratio(image_width as f64 / image_height as f64);
     }
+    let settings = RenderSettings {
+        width: image_width,
+        height: image_height,
+        samples_per_pixel: options.samples_per_pixel,
+        max_depth: options.max_depth,
+        rr_depth: options.rr_depth,
+        threads: options.threads,
+        tile_size: options.tile_size,
+        seed: options.seed,
+        sampler: options.sampler,
+        integrator: options.integrator,
+    };
 
-    fs::write("rtw.ppm", &image)?;
-    print!("\nDone.\n");
+    let format = options
+        .format
+        .or_else(|| ImageFormat::from_path(&options.output))
+        .unwrap_or(ImageFormat::Ppm);
+    let scene_hash = scene_hash(options, image_width, image_height)?;
+    let mut accumulator = match &options.checkpoint {
+        Some(path) if options.resume && path.exists() => {
+            let accumulator = Accumulator::load_checkpoint(path, scene_hash)
+                .map_err(|e| format!("{}: {}", path.display(), e))?;
+            eprintln!(
+                "Resuming from {} at {} samples per pixel",
+                path.display(),
+                accumulator.min_samples()
+            );
+            accumulator
+        }
+        _ => Accumulator::new(image_width, image_height),
+    };
+
+    let tile_count = tiles(image_width, image_height, options.tile_size).len();
+    let pass_spp = options.pass_spp.unwrap_or(options.samples_per_pixel);
+    let mut pass = 0;
+    while (accumulator.min_samples() as usize) < options.samples_per_pixel {
+        let samples = pass_spp.min(options.samples_per_pixel - accumulator.min_samples() as usize);
+        pass += 1;
+        let start = Instant::now();
+        let mut done = 0;
+        let stats = render::render_pass(&scene, &settings, samples, &mut accumulator, |_| {
+            done += 1;
+            eprint!("\rPass {}: tiles {}/{}", pass, done, tile_count);
+        });
+        let slowest = stats.iter().map(|s| s.elapsed).max().unwrap_or_default();
+        let mean = stats.iter().map(|s| s.elapsed).sum::<Duration>() / stats.len().max(1) as u32;
+        eprintln!(
+            "\rPass {}: {}/{} spp in {:.2?} (mean tile {:.2?}, slowest {:.2?})",
+            pass,
+            accumulator.min_samples(),
+            options.samples_per_pixel,
+            start.elapsed(),
+            mean,
+            slowest
+        );
+
+        if let Some(path) = &options.checkpoint {
+            accumulator.save_checkpoint(path, scene_hash)?;
+        }
+        if (accumulator.min_samples() as usize) < options.samples_per_pixel {
+            accumulator
+                .resolve()
+                .save(&options.output, format, options.gamma)?;
+        }
+    }
+
+    accumulator
+        .resolve()
+        .save(&options.output, format, options.gamma)?;
+    println!("Done.");
     Ok(())
 }
 
-fn scene(case: u8) -> HittableList {
-    match case {
-        1 => {
+// Identifies what a checkpoint's samples were computed from, so a resume
+// refuses samples of a different scene file, resolution, seed or estimator.
+// The stratified sampler sizes its strata from the total spp, so with it
+// the spp counts too. Files the scene refers to, such as meshes and
+// textures, are not read, so editing them between runs goes unnoticed.
+fn scene_hash(options: &Options, width: usize, height: usize) -> Result<u64, Box<dyn Error>> {
+    let mut key = match &options.scene {
+        SceneSource::File(path) => fs::read(path)?,
+        SceneSource::Builtin(name) => format!("builtin:{}", name).into_bytes(),
+    };
+    key.extend(
+        format!(
+            "\n{}x{} depth {} rr-depth {} seed {} sampler {} integrator {}",
+            width,
+            height,
+            options.max_depth,
+            options.rr_depth,
+            options.seed,
+            options.sampler.name(),
+            options.integrator.name()
+        )
+        .bytes(),
+    );
+    if options.sampler == SamplerKind::Stratified {
+        key.extend(format!(" spp {}", options.samples_per_pixel).bytes());
+    }
+    Ok(fnv1a(&key))
+}
+
+fn builtin_scene(name: &str) -> Result<Scene, String> {
+    match name {
+        "earth" => {
             let mut world = HittableList::new();
-            let checker = Rc::new(CheckerTexture::from_color(
+            let checker = Arc::new(CheckerTexture::from_color(
                 Color::new(0.2, 0.3, 0.1),
                 Color::new(0.9, 0.9, 0.9),
             ));
-            let ground_material = Rc::new(Lambertian::from_texture(checker));
-            world.push(Rc::new(Sphere::new(
+            let ground_material = Arc::new(Lambertian::from_texture(checker));
+            world.push(Arc::new(Sphere::new(
                 Point3::new(0f64, -1000f64, 0f64),
                 1000f64,
                 ground_material.clone(),
             )));
-            let material = Rc::new(Lambertian::from_texture(Rc::new(ImageTexture::new(
-                &Path::new("./assets/earthmap.jpg"),
-            ))));
-            world.push(Rc::new(Sphere::new(
+            let material = Arc::new(Lambertian::from_texture(Arc::new(earth_texture()?)));
+            world.push(Arc::new(Sphere::new(
                 Point3::new(0f64, 2f64, 0f64),
                 2f64,
                 material,
             )));
-            let difflight = Rc::new(DiffuseLight::from_color(Color::new(4f64, 4f64, 4f64)));
-            world.push(Rc::new(XYRect::new(
+            let difflight = Arc::new(DiffuseLight::from_color(Color::new(4f64, 4f64, 4f64)));
+            world.push(quad(
+                Point3::new(3f64, 1f64, -2f64),
+                Vec3::new(2f64, 0f64, 0f64),
+                Vec3::new(0f64, 2f64, 0f64),
                 difflight,
-                [3f64, 5f64],
-                [1f64, 3f64],
-                -2f64,
-            )));
-            world
+            ));
+
+            let aspect_ratio = 1.5;
+            let lookfrom = Point3::new(26f64, 3f64, 6f64);
+            let lookat = Point3::new(0f64, 2f64, 0f64);
+            let camera = Camera::new(
+                lookfrom,
+                lookat,
+                Vec3::new(0f64, 1f64, 0f64),
+                20f64,
+                aspect_ratio,
+                0.0f64,
+                (lookfrom - lookat).modulus(),
+                [0f64, 1f64],
+            );
+            Ok(Scene {
+                world,
+                camera,
+                aspect_ratio,
+                background: Color::default(),
+                bvh_stats: Vec::new(),
+            })
         }
-        2 => {
+        "cornell" => {
             let mut world = HittableList::new();
-            let red = Rc::new(Lambertian::from_color(Color::new(0.65, 0.05, 0.05)));
-            let white = Rc::new(Lambertian::from_color(Color::new(0.73, 0.73, 0.73)));
-            let green = Rc::new(Lambertian::from_color(Color::new(0.12, 0.45, 0.15)));
-            let light = Rc::new(DiffuseLight::from_color(Color::new(15f64, 15f64, 15f64)));
+            let red = Arc::new(Lambertian::from_color(Color::new(0.65, 0.05, 0.05)));
+            let white = Arc::new(Lambertian::from_color(Color::new(0.73, 0.73, 0.73)));
+            let green = Arc::new(Lambertian::from_color(Color::new(0.12, 0.45, 0.15)));
+            let light = Arc::new(DiffuseLight::from_color(Color::new(15f64, 15f64, 15f64)));
 
-            world.push(Rc::new(YZRect::new(
+            world.push(quad(
+                Point3::new(555f64, 0f64, 0f64),
+                Vec3::new(0f64, 555f64, 0f64),
+                Vec3::new(0f64, 0f64, 555f64),
                 green.clone(),
-                [0f64, 555f64],
-                [0f64, 555f64],
-                555f64,
-            )));
-            world.push(Rc::new(YZRect::new(
+            ));
+            world.push(quad(
+                Point3::new(0f64, 0f64, 0f64),
+                Vec3::new(0f64, 555f64, 0f64),
+                Vec3::new(0f64, 0f64, 555f64),
                 red.clone(),
-                [0f64, 555f64],
-                [0f64, 555f64],
-                0f64,
-            )));
-            world.push(Rc::new(ZXRect::new(
+            ));
+            world.push(quad(
+                Point3::new(213f64, 554f64, 227f64),
+                Vec3::new(130f64, 0f64, 0f64),
+                Vec3::new(0f64, 0f64, 105f64),
                 light,
-                [213f64, 343f64],
-                [227f64, 332f64],
-                554f64,
-            )));
-            world.push(Rc::new(ZXRect::new(
+            ));
+            world.push(quad(
+                Point3::new(0f64, 0f64, 0f64),
+                Vec3::new(555f64, 0f64, 0f64),
+                Vec3::new(0f64, 0f64, 555f64),
                 white.clone(),
-                [0f64, 555f64],
-                [0f64, 555f64],
-                0f64,
-            )));
-            world.push(Rc::new(ZXRect::new(
+            ));
+            world.push(quad(
+                Point3::new(0f64, 555f64, 0f64),
+                Vec3::new(555f64, 0f64, 0f64),
+                Vec3::new(0f64, 0f64, 555f64),
                 white.clone(),
-                [0f64, 555f64],
-                [0f64, 555f64],
-                555f64,
-            )));
-            world.push(Rc::new(XYRect::new(
+            ));
+            world.push(quad(
+                Point3::new(0f64, 0f64, 555f64),
+                Vec3::new(555f64, 0f64, 0f64),
+                Vec3::new(0f64, 555f64, 0f64),
                 white.clone(),
-                [0f64, 555f64],
-                [0f64, 555f64],
-                555f64,
-            )));
+            ));
 
-            let box1 = Rc::new(Cube::new(
+            let box1 = Arc::new(Cube::new(
                 Point3::new(0f64, 0f64, 0f64),
                 Point3::new(165f64, 330f64, 165f64),
                 white.clone(),
             ));
-            let box1 = Rc::new(RotateY::new(15f64, box1));
-            let box1 = Rc::new(Translate::new(Vec3::new(265f64, 0f64, 295f64), box1));
+            let box1 = Arc::new(TransformedInstance::new(
+                placement(Vec3::new(265f64, 0f64, 295f64), 15f64),
+                box1,
+            ));
             world.push(box1);
 
-            let box2 = Rc::new(Cube::new(
+            let box2 = Arc::new(Cube::new(
                 Point3::new(0f64, 0f64, 0f64),
                 Point3::new(165f64, 165f64, 165f64),
                 white.clone(),
             ));
-            let box2 = Rc::new(RotateY::new(-18f64, box2));
-            let box2 = Rc::new(Translate::new(Vec3::new(130f64, 0f64, 65f64), box2));
+            let box2 = Arc::new(TransformedInstance::new(
+                placement(Vec3::new(130f64, 0f64, 65f64), -18f64),
+                box2,
+            ));
             world.push(box2);
 
-            world
+            let aspect_ratio = 1f64;
+            let lookfrom = Point3::new(278f64, 278f64, -800f64);
+            let lookat = Point3::new(278f64, 278f64, 0f64);
+            let camera = Camera::new(
+                lookfrom,
+                lookat,
+                Vec3::new(0f64, 1f64, 0f64),
+                40f64,
+                aspect_ratio,
+                0.0f64,
+                (lookfrom - lookat).modulus(),
+                [0f64, 1f64],
+            );
+            Ok(Scene {
+                world,
+                camera,
+                aspect_ratio,
+                background: Color::default(),
+                bvh_stats: Vec::new(),
+            })
         }
-        _ => {
+        "spheres" => {
             let mut world = HittableList::new();
 
-            let checker = Rc::new(CheckerTexture::from_color(
+            let checker = Arc::new(CheckerTexture::from_color(
                 Color::new(0.2, 0.3, 0.1),
                 Color::new(0.9, 0.9, 0.9),
             ));
-            let ground_material = Rc::new(Lambertian::from_texture(checker));
-            world.push(Rc::new(Sphere::new(
+            let ground_material = Arc::new(Lambertian::from_texture(checker));
+            world.push(Arc::new(Sphere::new(
                 Point3::new(0f64, -1000f64, 0f64),
                 1000f64,
                 ground_material.clone(),
             )));
 
-            let material = Rc::new(Dielectric::new(1.5));
-            world.push(Rc::new(Sphere::new(
+            let material = Arc::new(Dielectric::new(1.5));
+            world.push(Arc::new(Sphere::new(
                 Point3::new(0f64, 1f64, 0f64),
                 1f64,
                 material.clone(),
             )));
-            let material = Rc::new(Lambertian::from_color(Color::new(0.4, 0.2, 0.1)));
-            world.push(Rc::new(Sphere::new(
+            let material = Arc::new(Lambertian::from_color(Color::new(0.4, 0.2, 0.1)));
+            world.push(Arc::new(Sphere::new(
                 Point3::new(-4f64, 1f64, 0f64),
                 1f64,
                 material.clone(),
             )));
-            let material = Rc::new(Metal::new(Color::new(0.7, 0.6, 0.5), 0f64));
-            world.push(Rc::new(Sphere::new(
+            let material = Arc::new(Metal::new(Color::new(0.7, 0.6, 0.5), 0f64));
+            world.push(Arc::new(Sphere::new(
                 Point3::new(4f64, 1f64, 0f64),
                 1f64,
                 material.clone(),
             )));
-            let material = Rc::new(Lambertian::from_texture(Rc::new(ImageTexture::new(
-                &Path::new("./assets/earthmap.jpg"),
-            ))));
-            world.push(Rc::new(Sphere::new(
+            let material = Arc::new(Lambertian::from_texture(Arc::new(earth_texture()?)));
+            world.push(Arc::new(Sphere::new(
                 Point3::new(3.5f64, 0.7, 2.1),
                 0.7,
                 material.clone(),
             )));
-            world
+
+            let aspect_ratio = 1.5;
+            let camera = Camera::new(
+                Point3::new(13f64, 2f64, 3f64),
+                Point3::new(0f64, 0f64, 0f64),
+                Vec3::new(0f64, 1f64, 0f64),
+                20f64,
+                aspect_ratio,
+                0.1f64,
+                10f64,
+                [0f64, 1f64],
+            );
+            Ok(Scene {
+                world,
+                camera,
+                aspect_ratio,
+                background: Color::new(0.7, 0.8, 1.0),
+                bvh_stats: Vec::new(),
+            })
         }
+        _ => Err(format!("unknown built-in scene `{}`", name)),
     }
 }
+
+// Turns the Cornell boxes about their corner, then moves them into place.
+fn placement(offset: Vec3, degrees: f64) -> Transform {
+    Transform::new(Mat4::translate(&offset) * Mat4::rotate_y(degrees))
+        .expect("rotations and translations are invertible")
+}
+
+fn quad(q: Point3, u: Vec3, v: Vec3, mat: Arc<dyn Scatter>) -> Arc<Quad> {
+    Arc::new(Quad::new(q, u, v, mat).expect("built-in quads have nonzero area"))
+}
+
+fn earth_texture() -> Result<ImageTexture, String> {
+    let path = Path::new("./assets/earthmap.jpg");
+    ImageTexture::open(path).map_err(|e| format!("{}: {}", path.display(), e))
+}
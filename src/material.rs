@@ -1,25 +1,35 @@
-use std::rc::Rc;
-
-use rand::{rngs::ThreadRng, Rng};
+use std::{f64::consts::PI, sync::Arc};
 
 use crate::{
     hittable::HitRecord,
     ray::Ray,
+    sampler::Sampler,
     texture::{SolidColor, Texture},
     vec3::{dot, random_unit_sphere, reflect, refract, Color, Point3},
 };
-pub trait Scatter {
+pub trait Scatter: Send + Sync {
     fn scatter(
         &self,
         r_in: &Ray,
         rec: &HitRecord,
-        rng: &mut ThreadRng,
+        sampler: &mut dyn Sampler,
         attenuation: &mut Color,
         scattered: &mut Ray,
     ) -> bool;
     fn emitted(&self, _u: f64, _v: f64, _p: &Point3) -> Color {
         Color::default()
     }
+    fn is_emissive(&self) -> bool {
+        false
+    }
+    // The density `scatter` samples `scattered` with, or 0 for materials
+    // that scatter into a single direction, which light sampling cannot
+    // hit. Where it is nonzero, `attenuation * scattering_pdf` is the BSDF
+    // times the cosine term for any direction, so `attenuation` must not
+    // depend on the direction picked.
+    fn scattering_pdf(&self, _r_in: &Ray, _rec: &HitRecord, _scattered: &Ray) -> f64 {
+        0f64
+    }
 }
 
 pub trait CoScatter: Clone + Scatter {}
@@ -27,16 +37,16 @@ impl<T: Clone + Scatter> CoScatter for T {}
 
 #[derive(Clone)]
 pub struct Lambertian {
-    albedo: Rc<dyn Texture>,
+    albedo: Arc<dyn Texture>,
 }
 
 impl Lambertian {
     pub fn from_color(cl: Color) -> Self {
         Self {
-            albedo: Rc::new(SolidColor::from_color(cl)),
+            albedo: Arc::new(SolidColor::from_color(cl)),
         }
     }
-    pub fn from_texture(t: Rc<dyn Texture>) -> Self {
+    pub fn from_texture(t: Arc<dyn Texture>) -> Self {
         Self { albedo: t }
     }
 }
@@ -46,11 +56,11 @@ impl Scatter for Lambertian {
         &self,
         r_in: &Ray,
         rec: &HitRecord,
-        rng: &mut ThreadRng,
+        sampler: &mut dyn Sampler,
         attenuation: &mut Color,
         scattered: &mut Ray,
     ) -> bool {
-        let mut scatter_direction = rec.normal + random_unit_sphere(rng);
+        let mut scatter_direction = rec.normal + random_unit_sphere(sampler);
         if scatter_direction.near_zero() {
             scatter_direction = rec.normal;
         }
@@ -58,6 +68,12 @@ impl Scatter for Lambertian {
         *scattered = Ray::new(rec.point, scatter_direction, r_in.time());
         true
     }
+    // The normal plus a uniform point on the unit sphere is cosine
+    // distributed about the normal.
+    fn scattering_pdf(&self, _r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        let cosine = dot(&rec.normal, &scattered.direction().unit());
+        cosine.max(0f64) / PI
+    }
 }
 
 #[derive(Clone)]
@@ -80,7 +96,7 @@ impl Scatter for Metal {
         &self,
         r_in: &Ray,
         rec: &HitRecord,
-        rng: &mut ThreadRng,
+        sampler: &mut dyn Sampler,
         attenuation: &mut Color,
         scattered: &mut Ray,
     ) -> bool {
@@ -88,7 +104,7 @@ impl Scatter for Metal {
         *attenuation = self.albedo;
         *scattered = Ray::new(
             rec.point,
-            reflected + random_unit_sphere(rng) * self.fuzz,
+            reflected + random_unit_sphere(sampler) * self.fuzz,
             r_in.time(),
         );
         dot(&scattered.direction(), &rec.normal) > 0.0
@@ -119,7 +135,7 @@ impl Scatter for Dielectric {
         &self,
         r_in: &Ray,
         rec: &HitRecord,
-        rng: &mut ThreadRng,
+        sampler: &mut dyn Sampler,
         attenuation: &mut Color,
         scattered: &mut Ray,
     ) -> bool {
@@ -134,7 +150,7 @@ impl Scatter for Dielectric {
 
         let nrefract = refraction_ratio * sin_theta > 1f64;
         let direction = if nrefract
-            || Dielectric::reflectance(cos_theta, refraction_ratio) > rng.gen_range(0f64..1f64)
+            || Dielectric::reflectance(cos_theta, refraction_ratio) > sampler.next_1d()
         {
             reflect(&unit_direction, &rec.normal)
         } else {
@@ -147,17 +163,17 @@ impl Scatter for Dielectric {
 }
 
 pub struct DiffuseLight {
-    emit: Rc<dyn Texture>,
+    emit: Arc<dyn Texture>,
 }
 
 impl DiffuseLight {
-    pub fn from_texture(emit: Rc<dyn Texture>) -> Self {
+    pub fn from_texture(emit: Arc<dyn Texture>) -> Self {
         Self { emit }
     }
 
     pub fn from_color(c: Color) -> Self {
         Self {
-            emit: Rc::new(SolidColor::from_color(c)),
+            emit: Arc::new(SolidColor::from_color(c)),
         }
     }
 }
@@ -167,7 +183,7 @@ impl Scatter for DiffuseLight {
         &self,
         _r_in: &Ray,
         _rec: &HitRecord,
-        _rng: &mut ThreadRng,
+        _sampler: &mut dyn Sampler,
         _attenuation: &mut Color,
         _scattered: &mut Ray,
     ) -> bool {
@@ -176,19 +192,22 @@ impl Scatter for DiffuseLight {
     fn emitted(&self, u: f64, v: f64, p: &Point3) -> Color {
         self.emit.value(u, v, p)
     }
+    fn is_emissive(&self) -> bool {
+        true
+    }
 }
 
 pub struct Isotropic {
-    albedo: Rc<dyn Texture>,
+    albedo: Arc<dyn Texture>,
 }
 
 impl Isotropic {
     pub fn from_color(c: Color) -> Self {
         Self {
-            albedo: Rc::new(SolidColor::from_color(c)),
+            albedo: Arc::new(SolidColor::from_color(c)),
         }
     }
-    pub fn from_texture(a: Rc<dyn Texture>) -> Self {
+    pub fn from_texture(a: Arc<dyn Texture>) -> Self {
         Self { albedo: a }
     }
 }
@@ -198,12 +217,15 @@ impl Scatter for Isotropic {
         &self,
         r_in: &Ray,
         rec: &HitRecord,
-        rng: &mut ThreadRng,
+        sampler: &mut dyn Sampler,
         attenuation: &mut Color,
         scattered: &mut Ray,
     ) -> bool {
-        *scattered = Ray::new(rec.point, random_unit_sphere(rng), r_in.time());
+        *scattered = Ray::new(rec.point, random_unit_sphere(sampler), r_in.time());
         *attenuation = self.albedo.value(rec.u, rec.v, &rec.point);
         true
     }
+    fn scattering_pdf(&self, _r_in: &Ray, _rec: &HitRecord, _scattered: &Ray) -> f64 {
+        1f64 / (4f64 * PI)
+    }
 }
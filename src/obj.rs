@@ -0,0 +1,340 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use rand::Rng;
+
+use crate::{
+    bvh::{BvhBuilder, BvhNode},
+    hittable_list::HittableList,
+    import::ImportError,
+    material::{Dielectric, DiffuseLight, Lambertian, Metal, Scatter},
+    texture::ImageTexture,
+    triangle::{MeshError, TriangleMesh},
+    vec3::{Color, Point3, Vec3},
+};
+
+// Loads a Wavefront OBJ file as one BVH over all of its faces. Polygons are
+// split into fans, and faces before any `usemtl`, or after one naming an
+// unknown material, get `default_material`.
+// Materials come from the `mtllib` files next to the OBJ.
+pub fn load(
+    path: &Path,
+    default_material: Arc<dyn Scatter>,
+    builder: BvhBuilder,
+    rng: &mut impl Rng,
+) -> Result<BvhNode, ImportError> {
+    let src = fs::read_to_string(path).map_err(|e| ImportError::Io(path.to_path_buf(), e))?;
+    let dir = path.parent().unwrap_or(Path::new("."));
+
+    let mut positions = Vec::new();
+    let mut normals = Vec::new();
+    let mut uvs = Vec::new();
+    let mut materials = HashMap::new();
+    let mut groups = vec![Group::new(default_material)];
+    let mut current = 0;
+    let mut group_of: HashMap<String, usize> = HashMap::new();
+
+    for (n, raw) in src.lines().enumerate() {
+        let line = n + 1;
+        let syntax = |message: String| ImportError::syntax(path, line, message);
+        let mut tokens = raw.split('#').next().unwrap_or("").split_whitespace();
+        let keyword = match tokens.next() {
+            Some(k) => k,
+            None => continue,
+        };
+        let args: Vec<&str> = tokens.collect();
+        match keyword {
+            "v" => {
+                let [x, y, z] = floats::<3>(&args).map_err(syntax)?;
+                positions.push(Point3::new(x, y, z));
+            }
+            "vn" => {
+                let [x, y, z] = floats::<3>(&args).map_err(syntax)?;
+                normals.push(Vec3::new(x, y, z));
+            }
+            "vt" => {
+                // A third (w) coordinate is allowed and ignored.
+                let args = if args.len() == 3 { &args[..2] } else { &args };
+                let uv = if args.len() == 1 {
+                    [float(args[0]).map_err(syntax)?, 0f64]
+                } else {
+                    floats::<2>(args).map_err(syntax)?
+                };
+                uvs.push(uv);
+            }
+            "f" => {
+                if args.len() < 3 {
+                    return Err(syntax(format!(
+                        "a face needs at least 3 vertices, found {}",
+                        args.len()
+                    )));
+                }
+                let counts = [positions.len(), uvs.len(), normals.len()];
+                let corners = args
+                    .iter()
+                    .map(|corner| face_corner(corner, counts))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(syntax)?;
+                let group = &mut groups[current];
+                let vertices: Vec<usize> = corners
+                    .into_iter()
+                    .map(|key| group.vertex(key, &positions, &uvs, &normals))
+                    .collect();
+                for k in 1..vertices.len() - 1 {
+                    group
+                        .indices
+                        .push([vertices[0], vertices[k], vertices[k + 1]]);
+                }
+            }
+            "mtllib" => {
+                if args.is_empty() {
+                    return Err(syntax("`mtllib` needs a file name".to_string()));
+                }
+                for file in args {
+                    materials.extend(load_mtl(&dir.join(file))?);
+                }
+            }
+            "usemtl" => {
+                let name = args.join(" ");
+                // Exporters often name materials the MTL does not define;
+                // their faces get the default material.
+                current = match materials.get(&name) {
+                    Some(material) => *group_of.entry(name).or_insert_with(|| {
+                        groups.push(Group::new(material.clone()));
+                        groups.len() - 1
+                    }),
+                    None => 0,
+                };
+            }
+            // Objects, groups, smoothing groups and the like do not affect
+            // the mesh.
+            _ => {}
+        }
+    }
+
+    let mut triangles = HittableList::new();
+    for group in groups {
+        if group.indices.is_empty() {
+            continue;
+        }
+        let mesh = group
+            .into_mesh()
+            .map_err(|e| ImportError::Mesh(path.to_path_buf(), e))?;
+        triangles
+            .objects
+            .extend(TriangleMesh::triangles(&Arc::new(mesh)).objects);
+    }
+    if triangles.objects.is_empty() {
+        return Err(ImportError::format(path, "file has no faces"));
+    }
+    Ok(builder.build(&triangles, [0f64, 1f64], rng))
+}
+
+// Faces of one material. OBJ indexes positions, UVs and normals separately,
+// so every distinct combination becomes its own mesh vertex.
+struct Group {
+    material: Arc<dyn Scatter>,
+    vertices: HashMap<Corner, usize>,
+    positions: Vec<Point3>,
+    uvs: Vec<Option<[f64; 2]>>,
+    normals: Vec<Option<Vec3>>,
+    indices: Vec<[usize; 3]>,
+}
+
+type Corner = (usize, Option<usize>, Option<usize>);
+
+impl Group {
+    fn new(material: Arc<dyn Scatter>) -> Self {
+        Self {
+            material,
+            vertices: HashMap::new(),
+            positions: Vec::new(),
+            uvs: Vec::new(),
+            normals: Vec::new(),
+            indices: Vec::new(),
+        }
+    }
+
+    fn vertex(
+        &mut self,
+        key: Corner,
+        positions: &[Point3],
+        uvs: &[[f64; 2]],
+        normals: &[Vec3],
+    ) -> usize {
+        if let Some(&index) = self.vertices.get(&key) {
+            return index;
+        }
+        let (p, uv, n) = key;
+        self.positions.push(positions[p]);
+        self.uvs.push(uv.map(|i| uvs[i]));
+        self.normals.push(n.map(|i| normals[i]));
+        self.vertices.insert(key, self.positions.len() - 1);
+        self.positions.len() - 1
+    }
+
+    // Normals and UVs are only kept when every vertex has them.
+    fn into_mesh(self) -> Result<TriangleMesh, MeshError> {
+        let normals = self.normals.into_iter().collect::<Option<Vec<_>>>();
+        let uvs = self.uvs.into_iter().collect::<Option<Vec<_>>>();
+        TriangleMesh::new(
+            self.positions,
+            normals.unwrap_or_default(),
+            uvs.unwrap_or_default(),
+            self.indices,
+            self.material,
+        )
+    }
+}
+
+// Parses `v`, `v/vt`, `v//vn` or `v/vt/vn`, resolving negative (relative)
+// indices against the element counts seen so far.
+fn face_corner(corner: &str, counts: [usize; 3]) -> Result<Corner, String> {
+    let mut parts = corner.split('/');
+    let mut next = |k: usize| -> Result<Option<usize>, String> {
+        match parts.next() {
+            None | Some("") if k > 0 => Ok(None),
+            None | Some("") => Err(format!("missing vertex index in `{}`", corner)),
+            Some(text) => {
+                let index: i64 = text
+                    .parse()
+                    .map_err(|_| format!("invalid index `{}` in `{}`", text, corner))?;
+                let count = counts[k] as i64;
+                let resolved = if index > 0 { index - 1 } else { count + index };
+                if index == 0 || resolved < 0 || resolved >= count {
+                    return Err(format!("index {} in `{}` is out of range", index, corner));
+                }
+                Ok(Some(resolved as usize))
+            }
+        }
+    };
+    let position = next(0)?.unwrap_or_default();
+    let uv = next(1)?;
+    let normal = next(2)?;
+    Ok((position, uv, normal))
+}
+
+fn float(text: &str) -> Result<f64, String> {
+    text.parse()
+        .map_err(|_| format!("expected a number, found `{}`", text))
+}
+
+fn floats<const N: usize>(args: &[&str]) -> Result<[f64; N], String> {
+    if args.len() != N {
+        return Err(format!("expected {} numbers, found {}", N, args.len()));
+    }
+    let mut out = [0f64; N];
+    for (slot, text) in out.iter_mut().zip(args) {
+        *slot = float(text)?;
+    }
+    Ok(out)
+}
+
+// `Kd 0.5` is shorthand for `Kd 0.5 0.5 0.5`.
+fn color(args: &[&str]) -> Result<Color, String> {
+    if args.len() == 1 {
+        let c = float(args[0])?;
+        return Ok(Color::new(c, c, c));
+    }
+    let [r, g, b] = floats::<3>(args)?;
+    Ok(Color::new(r, g, b))
+}
+
+#[derive(Clone)]
+struct MtlEntry {
+    kd: Color,
+    ks: Color,
+    ke: Color,
+    ns: f64,
+    ni: Option<f64>,
+    d: f64,
+    map_kd: Option<PathBuf>,
+}
+
+impl Default for MtlEntry {
+    fn default() -> Self {
+        Self {
+            kd: Color::new(0.8, 0.8, 0.8),
+            ks: Color::default(),
+            ke: Color::default(),
+            ns: 0f64,
+            ni: None,
+            d: 1f64,
+            map_kd: None,
+        }
+    }
+}
+
+// Reads the materials of an MTL file, mapped onto the closest of the
+// renderer's materials.
+pub fn load_mtl(path: &Path) -> Result<HashMap<String, Arc<dyn Scatter>>, ImportError> {
+    let src = fs::read_to_string(path).map_err(|e| ImportError::Io(path.to_path_buf(), e))?;
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let mut entries: Vec<(String, MtlEntry)> = Vec::new();
+
+    for (n, raw) in src.lines().enumerate() {
+        let line = n + 1;
+        let syntax = |message: String| ImportError::syntax(path, line, message);
+        let mut tokens = raw.split('#').next().unwrap_or("").split_whitespace();
+        let keyword = match tokens.next() {
+            Some(k) => k,
+            None => continue,
+        };
+        let args: Vec<&str> = tokens.collect();
+        if keyword == "newmtl" {
+            entries.push((args.join(" "), MtlEntry::default()));
+            continue;
+        }
+        let entry = match entries.last_mut() {
+            Some((_, entry)) => entry,
+            None => return Err(syntax(format!("`{}` before any `newmtl`", keyword))),
+        };
+        match keyword {
+            "Kd" => entry.kd = color(&args).map_err(syntax)?,
+            "Ks" => entry.ks = color(&args).map_err(syntax)?,
+            "Ke" => entry.ke = color(&args).map_err(syntax)?,
+            "Ns" => entry.ns = floats::<1>(&args).map_err(syntax)?[0],
+            "Ni" => entry.ni = Some(floats::<1>(&args).map_err(syntax)?[0]),
+            "d" => entry.d = floats::<1>(&args).map_err(syntax)?[0],
+            "Tr" => entry.d = 1f64 - floats::<1>(&args).map_err(syntax)?[0],
+            // Texture options such as `-s 1 1 1` precede the file name.
+            "map_Kd" => match args.last() {
+                Some(file) => entry.map_kd = Some(dir.join(file)),
+                None => return Err(syntax("`map_Kd` needs a file name".to_string())),
+            },
+            _ => {}
+        }
+    }
+
+    let mut materials = HashMap::new();
+    for (name, entry) in entries {
+        materials.insert(name, mtl_material(&entry)?);
+    }
+    Ok(materials)
+}
+
+// Emitters become lights and see-through materials glass. Otherwise the
+// stronger of the diffuse and specular colors decides between Lambertian
+// and Metal, with the Phong exponent turned into fuzz.
+fn mtl_material(entry: &MtlEntry) -> Result<Arc<dyn Scatter>, ImportError> {
+    let max = |c: &Color| c.x().max(c.y()).max(c.z());
+    if max(&entry.ke) > 0f64 {
+        return Ok(Arc::new(DiffuseLight::from_color(entry.ke)));
+    }
+    if entry.d < 1f64 {
+        return Ok(Arc::new(Dielectric::new(entry.ni.unwrap_or(1.5))));
+    }
+    if let Some(file) = &entry.map_kd {
+        let texture = ImageTexture::open(file).map_err(|e| ImportError::Image(file.clone(), e))?;
+        return Ok(Arc::new(Lambertian::from_texture(Arc::new(texture))));
+    }
+    if max(&entry.ks) > max(&entry.kd) {
+        let fuzz = (2f64 / (entry.ns.max(0f64) + 2f64)).sqrt();
+        return Ok(Arc::new(Metal::new(entry.ks, fuzz)));
+    }
+    Ok(Arc::new(Lambertian::from_color(entry.kd)))
+}
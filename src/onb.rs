@@ -0,0 +1,27 @@
+use crate::vec3::{cross, Vec3};
+
+// An orthonormal basis around `w`, used to turn directions sampled around
+// the z axis into world space.
+pub struct Onb {
+    u: Vec3,
+    v: Vec3,
+    w: Vec3,
+}
+
+impl Onb {
+    pub fn from_w(n: &Vec3) -> Self {
+        let w = n.unit();
+        let a = if w.x().abs() > 0.9 {
+            Vec3::new(0f64, 1f64, 0f64)
+        } else {
+            Vec3::new(1f64, 0f64, 0f64)
+        };
+        let v = cross(&w, &a).unit();
+        let u = cross(&w, &v);
+        Self { u, v, w }
+    }
+
+    pub fn local(&self, a: f64, b: f64, c: f64) -> Vec3 {
+        self.u * a + self.v * b + self.w * c
+    }
+}
@@ -1,6 +1,6 @@
-use rand::{rngs::ThreadRng, thread_rng, Rng};
+use rand::{Rng, SeedableRng};
 
-use crate::vec3::Point3;
+use crate::{sampler::Pcg32, vec3::Point3};
 
 const POINT_COUNT: usize = 256;
 #[derive(Clone)]
@@ -12,22 +12,21 @@ pub struct Perlin {
 }
 
 impl Perlin {
-    fn perlin_generate_perm(rng: &mut ThreadRng) -> Vec<usize> {
-        let mut p: Vec<usize> = (0..POINT_COUNT - 1).collect();
+    fn perlin_generate_perm(rng: &mut impl Rng) -> Vec<usize> {
+        let mut p: Vec<usize> = (0..POINT_COUNT).collect();
         for i in (1..p.len()).rev() {
-            let target = rng.gen_range(0..i);
+            let target = rng.gen_range(0..=i);
             p.swap(i, target);
         }
         p
     }
-    pub fn new() -> Self {
-        let mut rng = thread_rng();
+    pub fn new(rng: &mut impl Rng) -> Self {
         let ranfloat: Vec<f64> = (0..POINT_COUNT)
             .map(|_| rng.gen_range(0f64..1f64))
             .collect();
-        let perm_x = Perlin::perlin_generate_perm(&mut rng);
-        let perm_y = Perlin::perlin_generate_perm(&mut rng);
-        let perm_z = Perlin::perlin_generate_perm(&mut rng);
+        let perm_x = Perlin::perlin_generate_perm(rng);
+        let perm_y = Perlin::perlin_generate_perm(rng);
+        let perm_z = Perlin::perlin_generate_perm(rng);
         Self {
             ranfloat,
             perm_x,
@@ -47,10 +46,10 @@ impl Perlin {
         let j = p.y().floor() as usize;
         let k = p.z().floor() as usize;
         let mut c = [[[0f64; 2]; 2]; 2];
-        for di in 0..2 {
-            for dj in 0..2 {
-                for dk in 0..2 {
-                    c[di][dj][dk] = self.ranfloat[self.perm_x[(i + di) & 255]
+        for (di, plane) in c.iter_mut().enumerate() {
+            for (dj, row) in plane.iter_mut().enumerate() {
+                for (dk, corner) in row.iter_mut().enumerate() {
+                    *corner = self.ranfloat[self.perm_x[(i + di) & 255]
                         ^ self.perm_y[(j + dj) & 255]
                         ^ self.perm_z[(k + dk) & 255]];
                 }
@@ -60,16 +59,22 @@ impl Perlin {
     }
     fn trilinear_interp(c: [[[f64; 2]; 2]; 2], u: f64, v: f64, w: f64) -> f64 {
         let mut acc = 0f64;
-        for i in 0..2 {
-            for j in 0..2 {
-                for k in 0..2 {
+        for (i, plane) in c.iter().enumerate() {
+            for (j, row) in plane.iter().enumerate() {
+                for (k, corner) in row.iter().enumerate() {
                     acc += (i as f64 * u + (1 - i) as f64 * (1f64 - u))
                         * (j as f64 * v + (1 - j) as f64 * (1f64 - v))
                         * (k as f64 * w + (1 - k) as f64 * (1f64 - w))
-                        * c[i][j][k];
+                        * corner;
                 }
             }
         }
         acc
     }
 }
+
+impl Default for Perlin {
+    fn default() -> Self {
+        Self::new(&mut Pcg32::seed_from_u64(0))
+    }
+}
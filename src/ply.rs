@@ -0,0 +1,475 @@
+use std::{fs, path::Path};
+
+use crate::{
+    import::{ImportError, MeshData},
+    vec3::{Color, Point3, Vec3},
+};
+
+#[derive(Clone, Copy)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "char" | "int8" => Some(Scalar::I8),
+            "uchar" | "uint8" => Some(Scalar::U8),
+            "short" | "int16" => Some(Scalar::I16),
+            "ushort" | "uint16" => Some(Scalar::U16),
+            "int" | "int32" => Some(Scalar::I32),
+            "uint" | "uint32" => Some(Scalar::U32),
+            "float" | "float32" => Some(Scalar::F32),
+            "double" | "float64" => Some(Scalar::F64),
+            _ => None,
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Scalar::I8 | Scalar::U8 => 1,
+            Scalar::I16 | Scalar::U16 => 2,
+            Scalar::I32 | Scalar::U32 | Scalar::F32 => 4,
+            Scalar::F64 => 8,
+        }
+    }
+
+    // Integer color channels are scaled to [0, 1]; float ones already are.
+    fn max_color(self) -> f64 {
+        match self {
+            Scalar::U8 => 255f64,
+            Scalar::U16 => 65535f64,
+            _ => 1f64,
+        }
+    }
+}
+
+enum Property {
+    Scalar(String, Scalar),
+    List(String, Scalar, Scalar),
+}
+
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+#[derive(PartialEq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+}
+
+// Reads a PLY file in ASCII or binary little-endian form. Vertices may carry
+// normals (`nx`, `ny`, `nz`), colors (`red`, `green`, `blue`) and texture
+// coordinates (`u`/`v` or `s`/`t`); polygons are split into fans and other
+// elements are skipped.
+pub fn read(path: &Path) -> Result<MeshData, ImportError> {
+    let data = fs::read(path).map_err(|e| ImportError::Io(path.to_path_buf(), e))?;
+    let (format, elements, body) = header(path, &data)?;
+    let mut body = Body {
+        path,
+        data: body,
+        pos: 0,
+        format,
+    };
+
+    let mut mesh = MeshData::default();
+    let mut vertex_count = None;
+    for element in &elements {
+        match element.name.as_str() {
+            "vertex" => {
+                read_vertices(&mut body, element, &mut mesh)?;
+                vertex_count = Some(element.count);
+            }
+            "face" => {
+                let vertex_count = vertex_count.ok_or_else(|| {
+                    ImportError::format(path, "faces must follow the vertex element")
+                })?;
+                read_faces(&mut body, element, vertex_count, &mut mesh)?;
+            }
+            _ => {
+                for _ in 0..element.count {
+                    for property in &element.properties {
+                        body.property(property)?;
+                    }
+                }
+            }
+        }
+    }
+    if mesh.indices.is_empty() {
+        return Err(ImportError::format(path, "file has no faces"));
+    }
+    Ok(mesh)
+}
+
+fn header<'a>(
+    path: &Path,
+    data: &'a [u8],
+) -> Result<(Format, Vec<Element>, &'a [u8]), ImportError> {
+    const END: &[u8] = b"end_header";
+    let end = data
+        .windows(END.len())
+        .position(|w| w == END)
+        .ok_or_else(|| ImportError::format(path, "missing `end_header`"))?;
+    let body_start = match data[end..].iter().position(|&b| b == b'\n') {
+        Some(newline) => end + newline + 1,
+        None => data.len(),
+    };
+    let text = String::from_utf8_lossy(&data[..end]);
+
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+    for (n, raw) in text.lines().enumerate() {
+        let line = n + 1;
+        let syntax = |message: &str| ImportError::syntax(path, line, message);
+        let tokens: Vec<&str> = raw.split_whitespace().collect();
+        match tokens.as_slice() {
+            ["ply"] if line == 1 => {}
+            _ if line == 1 => return Err(syntax("not a PLY file")),
+            [] | ["comment", ..] | ["obj_info", ..] => {}
+            ["format", "ascii", _] => format = Some(Format::Ascii),
+            ["format", "binary_little_endian", _] => format = Some(Format::BinaryLittleEndian),
+            ["format", kind, ..] => {
+                return Err(syntax(&format!("unsupported format `{}`", kind)));
+            }
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count
+                    .parse()
+                    .map_err(|_| syntax(&format!("invalid element count `{}`", count)))?,
+                properties: Vec::new(),
+            }),
+            ["property", "list", count, item, name] => {
+                let count =
+                    Scalar::from_name(count).ok_or_else(|| syntax("unknown list count type"))?;
+                let item =
+                    Scalar::from_name(item).ok_or_else(|| syntax("unknown list item type"))?;
+                elements
+                    .last_mut()
+                    .ok_or_else(|| syntax("property before any element"))?
+                    .properties
+                    .push(Property::List(name.to_string(), count, item));
+            }
+            ["property", kind, name] => {
+                let kind =
+                    Scalar::from_name(kind).ok_or_else(|| syntax("unknown property type"))?;
+                elements
+                    .last_mut()
+                    .ok_or_else(|| syntax("property before any element"))?
+                    .properties
+                    .push(Property::Scalar(name.to_string(), kind));
+            }
+            _ => return Err(syntax(&format!("unexpected header line `{}`", raw.trim()))),
+        }
+    }
+    let format = format.ok_or_else(|| ImportError::format(path, "missing `format` line"))?;
+    Ok((format, elements, &data[body_start..]))
+}
+
+struct Body<'a> {
+    path: &'a Path,
+    data: &'a [u8],
+    pos: usize,
+    format: Format,
+}
+
+impl Body<'_> {
+    fn truncated(&self) -> ImportError {
+        ImportError::format(self.path, "unexpected end of file")
+    }
+
+    fn value(&mut self, kind: Scalar) -> Result<f64, ImportError> {
+        if self.format == Format::Ascii {
+            while self
+                .data
+                .get(self.pos)
+                .is_some_and(|b| b.is_ascii_whitespace())
+            {
+                self.pos += 1;
+            }
+            let start = self.pos;
+            while self
+                .data
+                .get(self.pos)
+                .is_some_and(|b| !b.is_ascii_whitespace())
+            {
+                self.pos += 1;
+            }
+            if start == self.pos {
+                return Err(self.truncated());
+            }
+            let text = String::from_utf8_lossy(&self.data[start..self.pos]);
+            return text
+                .parse()
+                .map_err(|_| ImportError::format(self.path, format!("invalid number `{}`", text)));
+        }
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + kind.size())
+            .ok_or_else(|| self.truncated())?;
+        self.pos += kind.size();
+        Ok(match kind {
+            Scalar::I8 => bytes[0] as i8 as f64,
+            Scalar::U8 => bytes[0] as f64,
+            Scalar::I16 => i16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            Scalar::U16 => u16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            Scalar::I32 => i32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+            Scalar::U32 => u32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+            Scalar::F32 => f32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+            Scalar::F64 => f64::from_le_bytes(bytes.try_into().unwrap()),
+        })
+    }
+
+    // Reads a whole property, returning its values.
+    fn property(&mut self, property: &Property) -> Result<Vec<f64>, ImportError> {
+        match *property {
+            Property::Scalar(_, kind) => Ok(vec![self.value(kind)?]),
+            Property::List(_, count, item) => {
+                let count = self.value(count)?;
+                if count < 0f64 || count.fract() != 0f64 {
+                    return Err(ImportError::format(
+                        self.path,
+                        format!("invalid list length {}", count),
+                    ));
+                }
+                (0..count as usize).map(|_| self.value(item)).collect()
+            }
+        }
+    }
+}
+
+fn read_vertices(
+    body: &mut Body,
+    element: &Element,
+    mesh: &mut MeshData,
+) -> Result<(), ImportError> {
+    let slot = |names: &[&str]| {
+        element
+            .properties
+            .iter()
+            .position(|p| matches!(p, Property::Scalar(name, _) if names.contains(&name.as_str())))
+    };
+    let position = [slot(&["x"]), slot(&["y"]), slot(&["z"])];
+    let normal = [slot(&["nx"]), slot(&["ny"]), slot(&["nz"])];
+    let color = [
+        slot(&["red", "r", "diffuse_red"]),
+        slot(&["green", "g", "diffuse_green"]),
+        slot(&["blue", "b", "diffuse_blue"]),
+    ];
+    let uv = [
+        slot(&["u", "s", "texture_u", "texture_s"]),
+        slot(&["v", "t", "texture_v", "texture_t"]),
+    ];
+    let [Some(x), Some(y), Some(z)] = position else {
+        return Err(ImportError::format(
+            body.path,
+            "vertices need `x`, `y` and `z` properties",
+        ));
+    };
+    let normal = match normal {
+        [Some(a), Some(b), Some(c)] => Some([a, b, c]),
+        _ => None,
+    };
+    let color = match color {
+        [Some(a), Some(b), Some(c)] => Some([a, b, c]),
+        _ => None,
+    };
+    let uv = match uv {
+        [Some(a), Some(b)] => Some([a, b]),
+        _ => None,
+    };
+    let color_scale = color.map(|slots| {
+        slots.map(|k| match element.properties[k] {
+            Property::Scalar(_, kind) => 1f64 / kind.max_color(),
+            Property::List(..) => 1f64,
+        })
+    });
+
+    let mut values = vec![0f64; element.properties.len()];
+    for _ in 0..element.count {
+        for (value, property) in values.iter_mut().zip(&element.properties) {
+            *value = match property {
+                Property::Scalar(_, kind) => body.value(*kind)?,
+                Property::List(..) => {
+                    body.property(property)?;
+                    0f64
+                }
+            };
+        }
+        mesh.positions
+            .push(Point3::new(values[x], values[y], values[z]));
+        if let Some([a, b, c]) = normal {
+            mesh.normals
+                .push(Vec3::new(values[a], values[b], values[c]));
+        }
+        if let (Some([a, b, c]), Some([sa, sb, sc])) = (color, color_scale) {
+            mesh.colors
+                .push(Color::new(values[a] * sa, values[b] * sb, values[c] * sc));
+        }
+        if let Some([a, b]) = uv {
+            mesh.uvs.push([values[a], values[b]]);
+        }
+    }
+    Ok(())
+}
+
+fn read_faces(
+    body: &mut Body,
+    element: &Element,
+    vertex_count: usize,
+    mesh: &mut MeshData,
+) -> Result<(), ImportError> {
+    let indices = element
+        .properties
+        .iter()
+        .position(|p| {
+            matches!(p, Property::List(name, ..) if name == "vertex_indices" || name == "vertex_index")
+        })
+        .ok_or_else(|| ImportError::format(body.path, "faces need a `vertex_indices` list"))?;
+    for face in 0..element.count {
+        let mut corners = Vec::new();
+        for (k, property) in element.properties.iter().enumerate() {
+            let values = body.property(property)?;
+            if k == indices {
+                corners = values;
+            }
+        }
+        let mut vertices = Vec::with_capacity(corners.len());
+        for index in corners {
+            if index < 0f64 || index >= vertex_count as f64 || index.fract() != 0f64 {
+                return Err(ImportError::format(
+                    body.path,
+                    format!("face {} uses vertex {}, which does not exist", face, index),
+                ));
+            }
+            vertices.push(index as usize);
+        }
+        for k in 1..vertices.len().saturating_sub(1) {
+            mesh.indices
+                .push([vertices[0], vertices[k], vertices[k + 1]]);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_bytes(name: &str, bytes: &[u8]) -> Result<MeshData, ImportError> {
+        let path = std::env::temp_dir().join(format!("rtw-{}-{}.ply", name, std::process::id()));
+        fs::write(&path, bytes).unwrap();
+        let mesh = read(&path);
+        fs::remove_file(&path).unwrap();
+        mesh
+    }
+
+    fn message(result: Result<MeshData, ImportError>) -> String {
+        match result {
+            Err(ImportError::Format { message, .. }) => message,
+            Err(e) => panic!("expected a format error, found `{}`", e),
+            Ok(_) => panic!("expected a format error"),
+        }
+    }
+
+    const HEADER: &str = "ply
+format {} 1.0
+comment a unit square
+element vertex 4
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+property float u
+property float v
+element face 1
+property list uchar int vertex_indices
+end_header
+";
+
+    fn header(format: &str) -> Vec<u8> {
+        HEADER.replace("{}", format).into_bytes()
+    }
+
+    fn check_square(mesh: &MeshData) {
+        assert_eq!(mesh.positions.len(), 4);
+        assert_eq!(mesh.positions[2].x(), 1f64);
+        assert_eq!(mesh.positions[2].y(), 1f64);
+        assert_eq!(mesh.colors[1].x(), 1f64);
+        assert_eq!(mesh.colors[1].y(), 0f64);
+        assert_eq!(mesh.uvs[3], [0f64, 1f64]);
+        assert!(mesh.normals.is_empty());
+        // The quad is split into a fan.
+        assert_eq!(mesh.indices, vec![[0, 1, 2], [0, 2, 3]]);
+    }
+
+    #[test]
+    fn reads_ascii() {
+        let mut data = header("ascii");
+        data.extend_from_slice(
+            b"0 0 0 255 255 255 0 0\n1 0 0 255 0 0 1 0\n1 1 0 0 255 0 1 1\n0 1 0 0 0 255 0 1\n4 0 1 2 3\n",
+        );
+        check_square(&read_bytes("ascii", &data).unwrap());
+    }
+
+    #[test]
+    fn reads_binary_little_endian() {
+        let mut data = header("binary_little_endian");
+        let vertices = [
+            ([0f32, 0f32, 0f32], [255u8, 255, 255], [0f32, 0f32]),
+            ([1f32, 0f32, 0f32], [255u8, 0, 0], [1f32, 0f32]),
+            ([1f32, 1f32, 0f32], [0u8, 255, 0], [1f32, 1f32]),
+            ([0f32, 1f32, 0f32], [0u8, 0, 255], [0f32, 1f32]),
+        ];
+        for (position, color, uv) in vertices {
+            position.iter().for_each(|c| data.extend(c.to_le_bytes()));
+            data.extend(color);
+            uv.iter().for_each(|c| data.extend(c.to_le_bytes()));
+        }
+        data.push(4);
+        (0..4i32).for_each(|i| data.extend(i.to_le_bytes()));
+        check_square(&read_bytes("binary", &data).unwrap());
+
+        data.truncate(data.len() - 2);
+        assert_eq!(
+            message(read_bytes("truncated", &data)),
+            "unexpected end of file"
+        );
+    }
+
+    #[test]
+    fn reports_bad_files() {
+        let mut data = header("ascii");
+        data.extend_from_slice(
+            b"0 0 0 0 0 0 0 0\n1 0 0 0 0 0 0 0\n0 1 0 0 0 0 0 0\n0 0 1 0 0 0 0 0\n3 0 1 7\n",
+        );
+        assert_eq!(
+            message(read_bytes("range", &data)),
+            "face 0 uses vertex 7, which does not exist"
+        );
+        assert_eq!(
+            message(read_bytes("no-end", b"ply\nformat ascii 1.0\n")),
+            "missing `end_header`"
+        );
+        match read_bytes("big-endian", &header("binary_big_endian")) {
+            Err(ImportError::Syntax { line, message, .. }) => {
+                assert_eq!(
+                    (line, message.as_str()),
+                    (2, "unsupported format `binary_big_endian`")
+                );
+            }
+            _ => panic!("expected a syntax error"),
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use std::sync::Arc;
+
+use crate::{
+    aabb::Aabb,
+    hittable::{HitRecord, Hittable},
+    material::Scatter,
+    ray::Ray,
+    sampler::Sampler,
+    vec3::{cross, dot, Point3, Vec3},
+};
+
+// A parallelogram with one corner at `q` and sides `u` and `v`, facing
+// along u x v. Its UVs run along `u` and `v` from `q`.
+pub struct Quad {
+    q: Point3,
+    u: Vec3,
+    v: Vec3,
+    normal: Vec3,
+    d: f64,
+    // Maps a point in the plane, relative to `q`, onto its (u, v) coordinates.
+    w: Vec3,
+    area: f64,
+    mat_ptr: Arc<dyn Scatter>,
+}
+
+impl Quad {
+    // Returns `None` when the sides are parallel, as there is no plane.
+    pub fn new(q: Point3, u: Vec3, v: Vec3, mat_ptr: Arc<dyn Scatter>) -> Option<Self> {
+        let n = cross(&u, &v);
+        let area = n.modulus();
+        if area == 0f64 || !area.is_finite() {
+            return None;
+        }
+        let normal = n / area;
+        Some(Self {
+            q,
+            u,
+            v,
+            normal,
+            d: dot(&normal, &q),
+            w: n / n.modsq(),
+            area,
+            mat_ptr,
+        })
+    }
+}
+
+impl Hittable for Quad {
+    fn hit(
+        &self,
+        r: &Ray,
+        t_min: f64,
+        t_max: f64,
+        rec: &mut HitRecord,
+        _sampler: &mut dyn Sampler,
+    ) -> bool {
+        let denom = dot(&self.normal, &r.direction());
+        if denom.abs() < 1e-12 {
+            return false;
+        }
+        let t = (self.d - dot(&self.normal, &r.origin())) / denom;
+        if t < t_min || t > t_max {
+            return false;
+        }
+        let point = r.at(t);
+        let planar = point - self.q;
+        let alpha = dot(&self.w, &cross(&planar, &self.v));
+        let beta = dot(&self.w, &cross(&self.u, &planar));
+        if !(0f64..=1f64).contains(&alpha) || !(0f64..=1f64).contains(&beta) {
+            return false;
+        }
+        rec.t = t;
+        rec.point = point;
+        (rec.u, rec.v) = (alpha, beta);
+        rec.set_face_normal(r, &self.normal);
+        rec.mat_ptr = Some(self.mat_ptr.clone());
+        true
+    }
+
+    fn bounding_box(&self, _time: [f64; 2], output_box: &mut Aabb) -> bool {
+        let pad = Vec3::new(0.0001, 0.0001, 0.0001);
+        let mut min = self.q;
+        let mut max = self.q;
+        for corner in [self.q + self.u, self.q + self.v, self.q + self.u + self.v] {
+            for c in 0..3 {
+                min.e[c] = min.e[c].min(corner.e[c]);
+                max.e[c] = max.e[c].max(corner.e[c]);
+            }
+        }
+        *output_box = Aabb::new(min - pad, max + pad);
+        true
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.mat_ptr.is_emissive()
+    }
+
+    fn pdf_value(
+        &self,
+        origin: &Point3,
+        direction: &Vec3,
+        time: f64,
+        sampler: &mut dyn Sampler,
+    ) -> f64 {
+        let mut rec = HitRecord::default();
+        if !self.hit(
+            &Ray::new(*origin, *direction, time),
+            0.001,
+            f64::INFINITY,
+            &mut rec,
+            sampler,
+        ) {
+            return 0f64;
+        }
+        let distance_squared = rec.t * rec.t * direction.modsq();
+        let cosine = dot(direction, &self.normal).abs() / direction.modulus();
+        distance_squared / (cosine * self.area)
+    }
+
+    fn random(&self, origin: &Point3, _time: f64, sampler: &mut dyn Sampler) -> Vec3 {
+        let (a, b) = sampler.next_2d();
+        self.q + self.u * a + self.v * b - *origin
+    }
+}
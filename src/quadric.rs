@@ -0,0 +1,682 @@
+use std::{f64::consts::PI, sync::Arc};
+
+use crate::{
+    aabb::Aabb,
+    hittable::{HitRecord, Hittable},
+    material::Scatter,
+    ray::Ray,
+    sampler::Sampler,
+    vec3::{dot, Point3, Vec3},
+};
+
+// Analytic surfaces in their own frame, symmetric about the z axis. Each
+// sweeps `phi_max` degrees counterclockwise from +x, and its UVs run along
+// the sweep (u) and across it (v). Place them with `TransformedInstance`.
+
+pub struct Cylinder {
+    radius: f64,
+    z: [f64; 2],
+    phi_max: f64,
+    mat_ptr: Arc<dyn Scatter>,
+}
+
+impl Cylinder {
+    // An open tube between the two heights in `z`.
+    pub fn new(radius: f64, z: [f64; 2], phi_max: f64, mat_ptr: Arc<dyn Scatter>) -> Self {
+        Self {
+            radius,
+            z,
+            phi_max: phi_max.to_radians(),
+            mat_ptr,
+        }
+    }
+
+    fn area(&self) -> f64 {
+        (self.z[1] - self.z[0]) * self.radius * self.phi_max
+    }
+}
+
+impl Hittable for Cylinder {
+    fn hit(
+        &self,
+        r: &Ray,
+        t_min: f64,
+        t_max: f64,
+        rec: &mut HitRecord,
+        _sampler: &mut dyn Sampler,
+    ) -> bool {
+        let (o, d) = (r.origin(), r.direction());
+        let a = d.x() * d.x() + d.y() * d.y();
+        let b = 2f64 * (d.x() * o.x() + d.y() * o.y());
+        let c = o.x() * o.x() + o.y() * o.y() - self.radius * self.radius;
+        for t in quadratic(a, b, c) {
+            if t <= t_min || t >= t_max {
+                continue;
+            }
+            let p = r.at(t);
+            let phi = phi(&p);
+            if p.z() < self.z[0] || p.z() > self.z[1] || phi > self.phi_max {
+                continue;
+            }
+            let normal = Vec3::new(p.x(), p.y(), 0f64) / self.radius;
+            let v = (p.z() - self.z[0]) / (self.z[1] - self.z[0]);
+            set_hit(rec, r, t, &normal, phi / self.phi_max, v, &self.mat_ptr);
+            return true;
+        }
+        false
+    }
+
+    fn bounding_box(&self, _time: [f64; 2], output_box: &mut Aabb) -> bool {
+        *output_box = Aabb::new(
+            Point3::new(-self.radius, -self.radius, self.z[0]),
+            Point3::new(self.radius, self.radius, self.z[1]),
+        );
+        true
+    }
+    fn is_emissive(&self) -> bool {
+        self.mat_ptr.is_emissive()
+    }
+    fn pdf_value(
+        &self,
+        origin: &Point3,
+        direction: &Vec3,
+        time: f64,
+        sampler: &mut dyn Sampler,
+    ) -> f64 {
+        area_pdf_value(self, self.area(), origin, direction, time, sampler)
+    }
+    fn random(&self, origin: &Point3, _time: f64, sampler: &mut dyn Sampler) -> Vec3 {
+        let (a, b) = sampler.next_2d();
+        let z = self.z[0] + a * (self.z[1] - self.z[0]);
+        on_circle(self.radius, b * self.phi_max, z) - *origin
+    }
+}
+
+pub struct Cone {
+    radius: f64,
+    height: f64,
+    z: [f64; 2],
+    phi_max: f64,
+    mat_ptr: Arc<dyn Scatter>,
+}
+
+impl Cone {
+    // Base of `radius` at z = 0, apex at z = `height`, cut to the heights in
+    // `z`, which must lie between the two; the ends are open.
+    pub fn new(
+        radius: f64,
+        height: f64,
+        z: [f64; 2],
+        phi_max: f64,
+        mat_ptr: Arc<dyn Scatter>,
+    ) -> Self {
+        Self {
+            radius,
+            height,
+            z,
+            phi_max: phi_max.to_radians(),
+            mat_ptr,
+        }
+    }
+
+    // The distance from the apex at height `z`, as a fraction of the base's.
+    fn apex_distance(&self, z: f64) -> f64 {
+        1f64 - z / self.height
+    }
+
+    // The area of a full cone grows as the square of `apex_distance`.
+    fn area(&self) -> f64 {
+        let slant = (self.radius * self.radius + self.height * self.height).sqrt();
+        let (s0, s1) = (self.apex_distance(self.z[0]), self.apex_distance(self.z[1]));
+        self.phi_max / 2f64 * self.radius * slant * (s0 * s0 - s1 * s1)
+    }
+}
+
+impl Hittable for Cone {
+    fn hit(
+        &self,
+        r: &Ray,
+        t_min: f64,
+        t_max: f64,
+        rec: &mut HitRecord,
+        _sampler: &mut dyn Sampler,
+    ) -> bool {
+        let (o, d) = (r.origin(), r.direction());
+        let k = (self.radius / self.height).powi(2);
+        let oz = o.z() - self.height;
+        let a = d.x() * d.x() + d.y() * d.y() - k * d.z() * d.z();
+        let b = 2f64 * (d.x() * o.x() + d.y() * o.y() - k * d.z() * oz);
+        let c = o.x() * o.x() + o.y() * o.y() - k * oz * oz;
+        for t in quadratic(a, b, c) {
+            if t <= t_min || t >= t_max {
+                continue;
+            }
+            let p = r.at(t);
+            let phi = phi(&p);
+            if p.z() < self.z[0] || p.z() > self.z[1] || phi > self.phi_max {
+                continue;
+            }
+            let normal = Vec3::new(p.x(), p.y(), k * (self.height - p.z())).unit();
+            let v = (p.z() - self.z[0]) / (self.z[1] - self.z[0]);
+            set_hit(rec, r, t, &normal, phi / self.phi_max, v, &self.mat_ptr);
+            return true;
+        }
+        false
+    }
+
+    fn bounding_box(&self, _time: [f64; 2], output_box: &mut Aabb) -> bool {
+        // The cone is widest at its lower cut.
+        let radius = self.radius * self.apex_distance(self.z[0]);
+        *output_box = Aabb::new(
+            Point3::new(-radius, -radius, self.z[0]),
+            Point3::new(radius, radius, self.z[1]),
+        );
+        true
+    }
+    fn is_emissive(&self) -> bool {
+        self.mat_ptr.is_emissive()
+    }
+    fn pdf_value(
+        &self,
+        origin: &Point3,
+        direction: &Vec3,
+        time: f64,
+        sampler: &mut dyn Sampler,
+    ) -> f64 {
+        area_pdf_value(self, self.area(), origin, direction, time, sampler)
+    }
+    fn random(&self, origin: &Point3, _time: f64, sampler: &mut dyn Sampler) -> Vec3 {
+        // The area within distance s of the apex grows as s squared.
+        let (a, b) = sampler.next_2d();
+        let (s0, s1) = (self.apex_distance(self.z[0]), self.apex_distance(self.z[1]));
+        let s = (s1 * s1 + a * (s0 * s0 - s1 * s1)).sqrt();
+        on_circle(self.radius * s, b * self.phi_max, self.height * (1f64 - s)) - *origin
+    }
+}
+
+pub struct Annulus {
+    height: f64,
+    inner_radius: f64,
+    radius: f64,
+    phi_max: f64,
+    mat_ptr: Arc<dyn Scatter>,
+}
+
+impl Annulus {
+    // The ring between the two radii in the plane z = `height`, facing +z.
+    pub fn new(
+        height: f64,
+        inner_radius: f64,
+        radius: f64,
+        phi_max: f64,
+        mat_ptr: Arc<dyn Scatter>,
+    ) -> Self {
+        Self {
+            height,
+            inner_radius,
+            radius,
+            phi_max: phi_max.to_radians(),
+            mat_ptr,
+        }
+    }
+
+    fn area(&self) -> f64 {
+        self.phi_max / 2f64 * (self.radius * self.radius - self.inner_radius * self.inner_radius)
+    }
+}
+
+impl Hittable for Annulus {
+    fn hit(
+        &self,
+        r: &Ray,
+        t_min: f64,
+        t_max: f64,
+        rec: &mut HitRecord,
+        _sampler: &mut dyn Sampler,
+    ) -> bool {
+        let t = (self.height - r.origin().z()) / r.direction().z();
+        if !(t > t_min && t < t_max) {
+            return false;
+        }
+        let p = r.at(t);
+        let distance_squared = p.x() * p.x() + p.y() * p.y();
+        if distance_squared > self.radius * self.radius
+            || distance_squared < self.inner_radius * self.inner_radius
+        {
+            return false;
+        }
+        let phi = phi(&p);
+        if phi > self.phi_max {
+            return false;
+        }
+        let v = (self.radius - distance_squared.sqrt()) / (self.radius - self.inner_radius);
+        let normal = Vec3::new(0f64, 0f64, 1f64);
+        set_hit(rec, r, t, &normal, phi / self.phi_max, v, &self.mat_ptr);
+        true
+    }
+
+    fn bounding_box(&self, _time: [f64; 2], output_box: &mut Aabb) -> bool {
+        *output_box = Aabb::new(
+            Point3::new(-self.radius, -self.radius, self.height - 0.0001),
+            Point3::new(self.radius, self.radius, self.height + 0.0001),
+        );
+        true
+    }
+    fn is_emissive(&self) -> bool {
+        self.mat_ptr.is_emissive()
+    }
+    fn pdf_value(
+        &self,
+        origin: &Point3,
+        direction: &Vec3,
+        time: f64,
+        sampler: &mut dyn Sampler,
+    ) -> f64 {
+        area_pdf_value(self, self.area(), origin, direction, time, sampler)
+    }
+    fn random(&self, origin: &Point3, _time: f64, sampler: &mut dyn Sampler) -> Vec3 {
+        let (a, b) = sampler.next_2d();
+        let inner_squared = self.inner_radius * self.inner_radius;
+        let distance = (inner_squared + a * (self.radius * self.radius - inner_squared)).sqrt();
+        on_circle(distance, b * self.phi_max, self.height) - *origin
+    }
+}
+
+// An annulus without a hole.
+pub struct Disk {
+    annulus: Annulus,
+}
+
+impl Disk {
+    pub fn new(height: f64, radius: f64, phi_max: f64, mat_ptr: Arc<dyn Scatter>) -> Self {
+        Self {
+            annulus: Annulus::new(height, 0f64, radius, phi_max, mat_ptr),
+        }
+    }
+}
+
+impl Hittable for Disk {
+    fn hit(
+        &self,
+        r: &Ray,
+        t_min: f64,
+        t_max: f64,
+        rec: &mut HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> bool {
+        self.annulus.hit(r, t_min, t_max, rec, sampler)
+    }
+
+    fn bounding_box(&self, time: [f64; 2], output_box: &mut Aabb) -> bool {
+        self.annulus.bounding_box(time, output_box)
+    }
+    fn is_emissive(&self) -> bool {
+        self.annulus.is_emissive()
+    }
+    fn pdf_value(
+        &self,
+        origin: &Point3,
+        direction: &Vec3,
+        time: f64,
+        sampler: &mut dyn Sampler,
+    ) -> f64 {
+        self.annulus.pdf_value(origin, direction, time, sampler)
+    }
+    fn random(&self, origin: &Point3, time: f64, sampler: &mut dyn Sampler) -> Vec3 {
+        self.annulus.random(origin, time, sampler)
+    }
+}
+
+pub struct Paraboloid {
+    radius: f64,
+    z: [f64; 2],
+    phi_max: f64,
+    mat_ptr: Arc<dyn Scatter>,
+}
+
+impl Paraboloid {
+    // The bowl x² + y² = radius² z / z[1], cut to the heights in `z`, which
+    // must not be negative.
+    pub fn new(radius: f64, z: [f64; 2], phi_max: f64, mat_ptr: Arc<dyn Scatter>) -> Self {
+        Self {
+            radius,
+            z,
+            phi_max: phi_max.to_radians(),
+            mat_ptr,
+        }
+    }
+
+    // The squared distance from the axis grows by `k` per unit of height.
+    fn k(&self) -> f64 {
+        self.radius * self.radius / self.z[1]
+    }
+
+    // Area is proportional to (z + k / 4)^(3/2) between the cuts.
+    fn area_measure(&self, z: f64) -> f64 {
+        (z + self.k() / 4f64).powf(1.5)
+    }
+
+    fn area(&self) -> f64 {
+        let span = self.area_measure(self.z[1]) - self.area_measure(self.z[0]);
+        self.phi_max * self.k().sqrt() * 2f64 / 3f64 * span
+    }
+}
+
+impl Hittable for Paraboloid {
+    fn hit(
+        &self,
+        r: &Ray,
+        t_min: f64,
+        t_max: f64,
+        rec: &mut HitRecord,
+        _sampler: &mut dyn Sampler,
+    ) -> bool {
+        let (o, d) = (r.origin(), r.direction());
+        let k = self.k();
+        let a = d.x() * d.x() + d.y() * d.y();
+        let b = 2f64 * (d.x() * o.x() + d.y() * o.y()) - k * d.z();
+        let c = o.x() * o.x() + o.y() * o.y() - k * o.z();
+        for t in quadratic(a, b, c) {
+            if t <= t_min || t >= t_max {
+                continue;
+            }
+            let p = r.at(t);
+            let phi = phi(&p);
+            if p.z() < self.z[0] || p.z() > self.z[1] || phi > self.phi_max {
+                continue;
+            }
+            let normal = Vec3::new(2f64 * p.x(), 2f64 * p.y(), -k).unit();
+            let v = (p.z() - self.z[0]) / (self.z[1] - self.z[0]);
+            set_hit(rec, r, t, &normal, phi / self.phi_max, v, &self.mat_ptr);
+            return true;
+        }
+        false
+    }
+
+    fn bounding_box(&self, _time: [f64; 2], output_box: &mut Aabb) -> bool {
+        *output_box = Aabb::new(
+            Point3::new(-self.radius, -self.radius, self.z[0]),
+            Point3::new(self.radius, self.radius, self.z[1]),
+        );
+        true
+    }
+    fn is_emissive(&self) -> bool {
+        self.mat_ptr.is_emissive()
+    }
+    fn pdf_value(
+        &self,
+        origin: &Point3,
+        direction: &Vec3,
+        time: f64,
+        sampler: &mut dyn Sampler,
+    ) -> f64 {
+        area_pdf_value(self, self.area(), origin, direction, time, sampler)
+    }
+    fn random(&self, origin: &Point3, _time: f64, sampler: &mut dyn Sampler) -> Vec3 {
+        let (a, b) = sampler.next_2d();
+        let (m0, m1) = (self.area_measure(self.z[0]), self.area_measure(self.z[1]));
+        let z = (m0 + a * (m1 - m0)).powf(2f64 / 3f64) - self.k() / 4f64;
+        let z = z.clamp(self.z[0], self.z[1]);
+        on_circle((self.k() * z).sqrt(), b * self.phi_max, z) - *origin
+    }
+}
+
+pub struct Torus {
+    major_radius: f64,
+    minor_radius: f64,
+    theta: [f64; 2],
+    phi_max: f64,
+    mat_ptr: Arc<dyn Scatter>,
+}
+
+impl Torus {
+    // A tube of `minor_radius` around the circle of `major_radius` in the
+    // xy plane. Light sampling needs the major radius to be the larger.
+    // Around the tube it runs between the two angles in `theta`, in degrees
+    // from the outer equator up over the top, at most a full turn apart.
+    pub fn new(
+        major_radius: f64,
+        minor_radius: f64,
+        theta: [f64; 2],
+        phi_max: f64,
+        mat_ptr: Arc<dyn Scatter>,
+    ) -> Self {
+        Self {
+            major_radius,
+            minor_radius,
+            theta: theta.map(f64::to_radians),
+            phi_max: phi_max.to_radians(),
+            mat_ptr,
+        }
+    }
+
+    // The area of the tube between the first angle and `theta`, per radian
+    // of sweep.
+    fn area_measure(&self, theta: f64) -> f64 {
+        let (big, small) = (self.major_radius, self.minor_radius);
+        small * (big * (theta - self.theta[0]) + small * (theta.sin() - self.theta[0].sin()))
+    }
+
+    fn area(&self) -> f64 {
+        self.phi_max * self.area_measure(self.theta[1])
+    }
+}
+
+impl Hittable for Torus {
+    fn hit(
+        &self,
+        r: &Ray,
+        t_min: f64,
+        t_max: f64,
+        rec: &mut HitRecord,
+        _sampler: &mut dyn Sampler,
+    ) -> bool {
+        let (big, small) = (self.major_radius, self.minor_radius);
+        // Solve in distances along the unit direction, starting where the
+        // ray enters the bounding sphere, to keep the quartic well scaled.
+        let length = r.direction().modulus();
+        let d = r.direction() / length;
+        let bound = big + small;
+        let oc = r.origin();
+        let half_b = dot(&oc, &d);
+        let discriminant = half_b * half_b - (oc.modsq() - bound * bound);
+        if discriminant <= 0f64 {
+            return false;
+        }
+        let enter = (-half_b - discriminant.sqrt()).max(t_min * length);
+        let exit = (-half_b + discriminant.sqrt()).min(t_max * length);
+        if enter >= exit {
+            return false;
+        }
+        let o = r.origin() + d * enter;
+
+        let e = o.modsq() + big * big - small * small;
+        let f = dot(&o, &d);
+        let four_big_squared = 4f64 * big * big;
+        let coefficients = [
+            e * e - four_big_squared * (o.x() * o.x() + o.y() * o.y()),
+            4f64 * f * e - 2f64 * four_big_squared * (o.x() * d.x() + o.y() * d.y()),
+            2f64 * e + 4f64 * f * f - four_big_squared * (d.x() * d.x() + d.y() * d.y()),
+            4f64 * f,
+            1f64,
+        ];
+        for s in polynomial_roots(&coefficients, 0f64, exit - enter) {
+            let t = (enter + s) / length;
+            if t <= t_min || t >= t_max {
+                continue;
+            }
+            let p = r.at(t);
+            let phi = phi(&p);
+            if phi > self.phi_max {
+                continue;
+            }
+            let rho = (p.x() * p.x() + p.y() * p.y()).sqrt();
+            let center = Vec3::new(p.x(), p.y(), 0f64) * (big / rho);
+            let normal = (p - center) / small;
+            let theta = (p.z().atan2(rho - big) - self.theta[0]).rem_euclid(2f64 * PI);
+            let span = self.theta[1] - self.theta[0];
+            if theta > span {
+                continue;
+            }
+            let v = theta / span;
+            set_hit(rec, r, t, &normal, phi / self.phi_max, v, &self.mat_ptr);
+            return true;
+        }
+        false
+    }
+
+    fn bounding_box(&self, _time: [f64; 2], output_box: &mut Aabb) -> bool {
+        // The tube's extremes are at its ends and at whichever quarter
+        // turns lie between them.
+        let (big, small) = (self.major_radius, self.minor_radius);
+        let quarter = PI / 2f64;
+        let first = (self.theta[0] / quarter).ceil() as i64;
+        let last = (self.theta[1] / quarter).floor() as i64;
+        let turns = (first..=last).map(|i| i as f64 * quarter);
+        let (mut rho, mut z) = (f64::NEG_INFINITY, [f64::INFINITY, f64::NEG_INFINITY]);
+        for theta in self.theta.into_iter().chain(turns) {
+            rho = rho.max(big + small * theta.cos());
+            z = [z[0].min(small * theta.sin()), z[1].max(small * theta.sin())];
+        }
+        *output_box = Aabb::new(Point3::new(-rho, -rho, z[0]), Point3::new(rho, rho, z[1]));
+        true
+    }
+    fn is_emissive(&self) -> bool {
+        self.mat_ptr.is_emissive()
+    }
+    fn pdf_value(
+        &self,
+        origin: &Point3,
+        direction: &Vec3,
+        time: f64,
+        sampler: &mut dyn Sampler,
+    ) -> f64 {
+        area_pdf_value(self, self.area(), origin, direction, time, sampler)
+    }
+    fn random(&self, origin: &Point3, _time: f64, sampler: &mut dyn Sampler) -> Vec3 {
+        // The tube's outer side holds more area: theta has density
+        // proportional to R + r cos(theta), inverted by Newton's method.
+        let (a, b) = sampler.next_2d();
+        let (big, small) = (self.major_radius, self.minor_radius);
+        let [lo, hi] = self.theta;
+        let target = a * self.area_measure(hi);
+        let mut theta = lo + a * (hi - lo);
+        for _ in 0..8 {
+            let g = self.area_measure(theta) - target;
+            theta = (theta - g / (small * (big + small * theta.cos()))).clamp(lo, hi);
+        }
+        let rho = big + small * theta.cos();
+        on_circle(rho, b * self.phi_max, small * theta.sin()) - *origin
+    }
+}
+
+fn set_hit(
+    rec: &mut HitRecord,
+    r: &Ray,
+    t: f64,
+    outward_normal: &Vec3,
+    u: f64,
+    v: f64,
+    mat_ptr: &Arc<dyn Scatter>,
+) {
+    rec.t = t;
+    rec.point = r.at(t);
+    rec.set_face_normal(r, outward_normal);
+    (rec.u, rec.v) = (u, v);
+    rec.mat_ptr = Some(mat_ptr.clone());
+}
+
+// The angle of `p` about the z axis, in [0, 2π).
+fn phi(p: &Point3) -> f64 {
+    p.y().atan2(p.x()).rem_euclid(2f64 * PI)
+}
+
+fn on_circle(radius: f64, phi: f64, z: f64) -> Point3 {
+    Point3::new(radius * phi.cos(), radius * phi.sin(), z)
+}
+
+// Roots of a t² + b t + c in ascending order, falling back to the linear
+// case when `a` vanishes.
+fn quadratic(a: f64, b: f64, c: f64) -> Vec<f64> {
+    if a == 0f64 {
+        return if b == 0f64 { vec![] } else { vec![-c / b] };
+    }
+    let discriminant = b * b - 4f64 * a * c;
+    if discriminant < 0f64 {
+        return vec![];
+    }
+    // Avoids cancelling b against the square root.
+    let q = -0.5 * (b + b.signum() * discriminant.sqrt());
+    if q == 0f64 {
+        return vec![0f64];
+    }
+    let (t0, t1) = (q / a, c / q);
+    vec![t0.min(t1), t0.max(t1)]
+}
+
+// Real roots strictly inside (lo, hi) of the polynomial with the given
+// coefficients, constant term first, in ascending order. The derivative's
+// roots cut the interval into monotonic pieces holding at most one root
+// each, which bisection then pins down. Roots of even multiplicity, where
+// the sign does not change, are missed.
+fn polynomial_roots(coefficients: &[f64], lo: f64, hi: f64) -> Vec<f64> {
+    let degree = coefficients.len() - 1;
+    if degree == 1 {
+        let t = -coefficients[0] / coefficients[1];
+        return if t > lo && t < hi { vec![t] } else { vec![] };
+    }
+    let derivative: Vec<f64> = (1..=degree).map(|i| coefficients[i] * i as f64).collect();
+    let mut bounds = vec![lo];
+    bounds.extend(polynomial_roots(&derivative, lo, hi));
+    bounds.push(hi);
+
+    let value = |t: f64| coefficients.iter().rev().fold(0f64, |acc, c| acc * t + c);
+    let mut roots = Vec::new();
+    for piece in bounds.windows(2) {
+        let (mut a, mut b) = (piece[0], piece[1]);
+        let rising = value(a) < 0f64;
+        if (value(b) > 0f64) != rising || value(a) == 0f64 || value(b) == 0f64 {
+            continue;
+        }
+        for _ in 0..64 {
+            let mid = 0.5 * (a + b);
+            if mid <= a || mid >= b {
+                break;
+            }
+            if (value(mid) < 0f64) == rising {
+                a = mid;
+            } else {
+                b = mid;
+            }
+        }
+        roots.push(0.5 * (a + b));
+    }
+    roots
+}
+
+// Solid-angle density of `direction` from `origin` when points are drawn
+// uniformly over the shape's `area`. A direction that crosses the surface
+// more than once could have been drawn through any of those points, so
+// their densities add up.
+fn area_pdf_value(
+    shape: &dyn Hittable,
+    area: f64,
+    origin: &Point3,
+    direction: &Vec3,
+    time: f64,
+    sampler: &mut dyn Sampler,
+) -> f64 {
+    let r = Ray::new(*origin, *direction, time);
+    let mut rec = HitRecord::default();
+    let mut t_min = 0.001;
+    let mut pdf = 0f64;
+    while shape.hit(&r, t_min, f64::INFINITY, &mut rec, sampler) {
+        let distance_squared = rec.t * rec.t * direction.modsq();
+        let cosine = dot(direction, &rec.normal).abs() / direction.modulus();
+        if cosine > 0f64 {
+            pdf += distance_squared / (cosine * area);
+        }
+        t_min = rec.t + 0.001;
+    }
+    pdf
+}
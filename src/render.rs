@@ -0,0 +1,199 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    accumulator::Accumulator,
+    framebuffer::Framebuffer,
+    integrator::{Integrator, IntegratorKind},
+    sampler::{Sampler, SamplerKind},
+    scene::Scene,
+    vec3::Color,
+};
+
+#[derive(Clone, Copy, Debug)]
+pub struct RenderSettings {
+    pub width: usize,
+    pub height: usize,
+    pub samples_per_pixel: usize,
+    pub max_depth: usize,
+    pub rr_depth: usize,
+    pub threads: usize,
+    pub tile_size: usize,
+    pub seed: u64,
+    pub sampler: SamplerKind,
+    pub integrator: IntegratorKind,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self {
+            width: 920,
+            height: 920,
+            samples_per_pixel: 100,
+            max_depth: 50,
+            rr_depth: 3,
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            tile_size: 32,
+            seed: 0,
+            sampler: SamplerKind::Independent,
+            integrator: IntegratorKind::Naive,
+        }
+    }
+}
+
+// A rectangle of pixels; `y` counts rows from the top of the image.
+#[derive(Clone, Copy, Debug)]
+pub struct Tile {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct TileStats {
+    pub tile: Tile,
+    pub worker: usize,
+    pub elapsed: Duration,
+}
+
+pub fn tiles(width: usize, height: usize, tile_size: usize) -> Vec<Tile> {
+    let tile_size = tile_size.max(1);
+    let mut tiles = Vec::new();
+    for y in (0..height).step_by(tile_size) {
+        for x in (0..width).step_by(tile_size) {
+            tiles.push(Tile {
+                x,
+                y,
+                width: tile_size.min(width - x),
+                height: tile_size.min(height - y),
+            });
+        }
+    }
+    tiles
+}
+
+pub fn render(
+    scene: &Scene,
+    settings: &RenderSettings,
+    progress: impl FnMut(&TileStats),
+) -> (Framebuffer, Vec<TileStats>) {
+    let mut accumulator = Accumulator::new(settings.width, settings.height);
+    let stats = render_pass(
+        scene,
+        settings,
+        settings.samples_per_pixel,
+        &mut accumulator,
+        progress,
+    );
+    (accumulator.resolve(), stats)
+}
+
+// Adds `samples` more samples to every pixel of `accumulator`. Workers pull
+// tiles from a shared counter until none are left, so a thread that lands on
+// cheap tiles simply takes more of them. `progress` is called on the calling
+// thread as each tile is added.
+pub fn render_pass(
+    scene: &Scene,
+    settings: &RenderSettings,
+    samples: usize,
+    accumulator: &mut Accumulator,
+    mut progress: impl FnMut(&TileStats),
+) -> Vec<TileStats> {
+    let tiles = tiles(settings.width, settings.height, settings.tile_size);
+    let next = AtomicUsize::new(0);
+    let first_sample = accumulator.min_samples();
+    let integrator = settings
+        .integrator
+        .create(scene, settings.max_depth, settings.rr_depth);
+    let mut stats = Vec::with_capacity(tiles.len());
+
+    thread::scope(|s| {
+        let (tx, rx) = mpsc::channel();
+        for worker in 0..settings.threads.max(1) {
+            let tx = tx.clone();
+            let (tiles, next, integrator) = (&tiles, &next, &*integrator);
+            s.spawn(move || {
+                let mut sampler = settings
+                    .sampler
+                    .create(settings.seed, settings.samples_per_pixel);
+                while let Some(&tile) = tiles.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    let start = Instant::now();
+                    let sums = render_tile(
+                        scene,
+                        integrator,
+                        settings,
+                        tile,
+                        first_sample,
+                        samples,
+                        &mut *sampler,
+                    );
+                    let tile_stats = TileStats {
+                        tile,
+                        worker,
+                        elapsed: start.elapsed(),
+                    };
+                    if tx.send((tile_stats, sums)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(tx);
+
+        for (tile_stats, sums) in rx {
+            let tile = tile_stats.tile;
+            for (k, sum) in sums.into_iter().enumerate() {
+                accumulator.add(
+                    tile.x + k % tile.width,
+                    tile.y + k / tile.width,
+                    sum,
+                    samples as u64,
+                );
+            }
+            progress(&tile_stats);
+            stats.push(tile_stats);
+        }
+    });
+
+    stats
+}
+
+// Returns the sum, not the mean, of each pixel's samples. Samples are
+// numbered from `first_sample` so that later passes draw fresh sample
+// streams instead of repeating earlier ones.
+fn render_tile(
+    scene: &Scene,
+    integrator: &dyn Integrator,
+    settings: &RenderSettings,
+    tile: Tile,
+    first_sample: u64,
+    samples: usize,
+    sampler: &mut dyn Sampler,
+) -> Vec<Color> {
+    let mut sums = Vec::with_capacity(tile.width * tile.height);
+    let u_scale = 1f64 / (settings.width - 1).max(1) as f64;
+    let v_scale = 1f64 / (settings.height - 1).max(1) as f64;
+    for y in tile.y..tile.y + tile.height {
+        let i = settings.height - 1 - y;
+        for j in tile.x..tile.x + tile.width {
+            let mut pixel_color = Color::default();
+            for s in 0..samples as u64 {
+                sampler.start_pixel_sample(j, y, first_sample + s);
+                let (du, dv) = sampler.next_2d();
+                let u = (j as f64 + du) * u_scale;
+                let v = (i as f64 + dv) * v_scale;
+                let r = scene.camera.get_ray(u, v, sampler);
+                pixel_color += integrator.li(scene, r, sampler);
+            }
+            sums.push(pixel_color);
+        }
+    }
+    sums
+}
@@ -1,3 +1,13 @@
 pub fn clamp(x: f64, lower: f64, upper: f64) -> f64 {
     x.max(lower).min(upper)
 }
+
+// 64-bit FNV-1a, used where a hash must stay stable across builds and runs.
+pub fn fnv1a(data: &[u8]) -> u64 {
+    let mut hash = 0xcbf29ce484222325u64;
+    for &byte in data {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
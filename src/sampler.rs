@@ -0,0 +1,406 @@
+use rand::{Error, RngCore, SeedableRng};
+
+// Supplies the random numbers for one camera sample at a time. Before each
+// sample the renderer calls `start_pixel_sample`, after which the values a
+// sampler hands out depend only on its seed, the pixel and the sample index,
+// never on which thread renders the pixel or in what order.
+pub trait Sampler {
+    fn start_pixel_sample(&mut self, x: usize, y: usize, index: u64);
+    // Uniform in [0, 1).
+    fn next_1d(&mut self) -> f64;
+    fn next_2d(&mut self) -> (f64, f64) {
+        (self.next_1d(), self.next_1d())
+    }
+}
+
+pub struct IndependentSampler {
+    seed: u64,
+    rng: Pcg32,
+}
+
+impl IndependentSampler {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: Pcg32::new(seed, 0),
+        }
+    }
+}
+
+impl Sampler for IndependentSampler {
+    fn start_pixel_sample(&mut self, x: usize, y: usize, index: u64) {
+        let pixel = pixel_hash(x, y);
+        self.rng = Pcg32::new(mix64(self.seed ^ mix64(pixel ^ index)), pixel);
+    }
+
+    fn next_1d(&mut self) -> f64 {
+        unit_f64(self.rng.next_u64())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SamplerKind {
+    Independent,
+    Stratified,
+    Halton,
+    Sobol,
+}
+
+impl SamplerKind {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "independent" => Some(SamplerKind::Independent),
+            "stratified" => Some(SamplerKind::Stratified),
+            "halton" => Some(SamplerKind::Halton),
+            "sobol" => Some(SamplerKind::Sobol),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SamplerKind::Independent => "independent",
+            SamplerKind::Stratified => "stratified",
+            SamplerKind::Halton => "halton",
+            SamplerKind::Sobol => "sobol",
+        }
+    }
+
+    // `samples_per_pixel` is the total the image will receive; the
+    // stratified sampler sizes its strata from it.
+    pub fn create(self, seed: u64, samples_per_pixel: usize) -> Box<dyn Sampler> {
+        match self {
+            SamplerKind::Independent => Box::new(IndependentSampler::new(seed)),
+            SamplerKind::Stratified => Box::new(StratifiedSampler::new(seed, samples_per_pixel)),
+            SamplerKind::Halton => Box::new(HaltonSampler::new(seed)),
+            SamplerKind::Sobol => Box::new(SobolSampler::new(seed)),
+        }
+    }
+}
+
+// Jittered stratification of every dimension on its own: 1D dimensions use
+// `samples_per_pixel` strata and 2D dimensions a near-square grid. Which
+// sample lands in which stratum is shuffled per pixel and per dimension so
+// dimensions do not correlate with each other. Samples past the stratum
+// count start a fresh, independently shuffled round.
+pub struct StratifiedSampler {
+    seed: u64,
+    strata: u64,
+    grid: (u64, u64),
+    pixel_seed: u64,
+    index: u64,
+    dimension: u64,
+}
+
+impl StratifiedSampler {
+    pub fn new(seed: u64, samples_per_pixel: usize) -> Self {
+        let strata = (samples_per_pixel as u64).clamp(1, u32::MAX as u64);
+        let columns = (strata as f64).sqrt().ceil() as u64;
+        let rows = strata.div_ceil(columns);
+        Self {
+            seed,
+            strata,
+            grid: (columns, rows),
+            pixel_seed: 0,
+            index: 0,
+            dimension: 0,
+        }
+    }
+
+    fn stratum(&mut self, count: u64) -> (u64, u64) {
+        let round = self.index / count;
+        let hash = mix64(self.pixel_seed ^ mix64(self.dimension ^ mix64(round)));
+        self.dimension += 1;
+        let stratum = permute((self.index % count) as u32, count as u32, hash as u32);
+        (stratum as u64, hash)
+    }
+}
+
+impl Sampler for StratifiedSampler {
+    fn start_pixel_sample(&mut self, x: usize, y: usize, index: u64) {
+        self.pixel_seed = mix64(self.seed ^ pixel_hash(x, y));
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    fn next_1d(&mut self) -> f64 {
+        let (stratum, hash) = self.stratum(self.strata);
+        (stratum as f64 + unit_f64(mix64(hash))) / self.strata as f64
+    }
+
+    fn next_2d(&mut self) -> (f64, f64) {
+        let (columns, rows) = self.grid;
+        let (stratum, hash) = self.stratum(columns * rows);
+        let jitter = mix64(hash);
+        (
+            ((stratum % columns) as f64 + unit_f64(jitter)) / columns as f64,
+            ((stratum / columns) as f64 + unit_f64(mix64(jitter))) / rows as f64,
+        )
+    }
+}
+
+const PRIMES: [u64; 64] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+    101, 103, 107, 109, 113, 127, 131, 137, 139, 149, 151, 157, 163, 167, 173, 179, 181, 191, 193,
+    197, 199, 211, 223, 227, 229, 233, 239, 241, 251, 257, 263, 269, 271, 277, 281, 283, 293, 307,
+    311,
+];
+
+// Dimension `d` is the radical inverse of the sample index in the d-th prime
+// base, with the digits of every pixel scrambled differently. Dimensions
+// beyond the prime table fall back to hashed random numbers, since
+// high-base Halton dimensions correlate badly anyway.
+pub struct HaltonSampler {
+    seed: u64,
+    pixel_seed: u64,
+    index: u64,
+    dimension: usize,
+}
+
+impl HaltonSampler {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            pixel_seed: 0,
+            index: 0,
+            dimension: 0,
+        }
+    }
+}
+
+impl Sampler for HaltonSampler {
+    fn start_pixel_sample(&mut self, x: usize, y: usize, index: u64) {
+        self.pixel_seed = mix64(self.seed ^ pixel_hash(x, y));
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    fn next_1d(&mut self) -> f64 {
+        let dimension = self.dimension;
+        self.dimension += 1;
+        let hash = mix64(self.pixel_seed ^ dimension as u64);
+        match PRIMES.get(dimension) {
+            Some(&base) => scrambled_radical_inverse(base, self.index, hash),
+            None => unit_f64(mix64(hash ^ mix64(self.index))),
+        }
+    }
+}
+
+// Random-digit scrambling: every digit position gets its own random shift
+// modulo the base. Digits past the last nonzero one are scrambled too, which
+// keeps the points away from a shared grid.
+fn scrambled_radical_inverse(base: u64, mut index: u64, seed: u64) -> f64 {
+    let inv_base = 1f64 / base as f64;
+    let mut inv_base_m = 1f64;
+    let mut result = 0f64;
+    let mut level = 0u64;
+    while 1f64 - (base - 1) as f64 * inv_base_m < 1f64 {
+        let shift = mix64(seed ^ level) % base;
+        let digit = (index % base + shift) % base;
+        index /= base;
+        inv_base_m *= inv_base;
+        result += digit as f64 * inv_base_m;
+        level += 1;
+    }
+    result.min(ONE_MINUS_EPSILON)
+}
+
+// Owen-scrambled Sobol points, following Burley's "Practical Hash-based Owen
+// Scrambling" (JCGT 2020): each 2D request takes the first two Sobol
+// dimensions at a shuffled index, so requests are padded together without
+// needing higher-dimensional direction numbers.
+pub struct SobolSampler {
+    seed: u64,
+    pixel_seed: u32,
+    index: u32,
+    dimension: u32,
+}
+
+impl SobolSampler {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            pixel_seed: 0,
+            index: 0,
+            dimension: 0,
+        }
+    }
+}
+
+impl Sampler for SobolSampler {
+    fn start_pixel_sample(&mut self, x: usize, y: usize, index: u64) {
+        self.pixel_seed = mix64(self.seed ^ pixel_hash(x, y)) as u32;
+        self.index = index as u32;
+        self.dimension = 0;
+    }
+
+    fn next_1d(&mut self) -> f64 {
+        self.next_2d().0
+    }
+
+    fn next_2d(&mut self) -> (f64, f64) {
+        let seed = hash_u32(self.pixel_seed ^ hash_u32(self.dimension));
+        self.dimension += 1;
+        let index = nested_uniform_scramble(self.index, seed);
+        let (x, y) = sobol_2d(index);
+        (
+            unit_f32_bits(nested_uniform_scramble(x, hash_u32(seed ^ 0x5bd1e995))),
+            unit_f32_bits(nested_uniform_scramble(y, hash_u32(seed ^ 0x68e31da4))),
+        )
+    }
+}
+
+// The first two Sobol dimensions: the van der Corput sequence and the
+// dimension generated by the polynomial x + 1.
+fn sobol_2d(index: u32) -> (u32, u32) {
+    let x = index.reverse_bits();
+    let mut y = 0u32;
+    let mut direction = 1u32 << 31;
+    let mut bits = index;
+    while bits != 0 {
+        if bits & 1 != 0 {
+            y ^= direction;
+        }
+        bits >>= 1;
+        direction ^= direction >> 1;
+    }
+    (x, y)
+}
+
+fn nested_uniform_scramble(x: u32, seed: u32) -> u32 {
+    laine_karras_permutation(x.reverse_bits(), seed).reverse_bits()
+}
+
+fn laine_karras_permutation(mut x: u32, seed: u32) -> u32 {
+    x = x.wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6c50b47c);
+    x ^= x.wrapping_mul(0xb82f1e52);
+    x ^= x.wrapping_mul(0xc7afe638);
+    x ^= x.wrapping_mul(0x8d22f6e6);
+    x
+}
+
+// Kensler's hashed permutation ("Correlated Multi-Jittered Sampling"):
+// maps `i` in [0, l) to a distinct element of [0, l) chosen by `p`.
+fn permute(mut i: u32, l: u32, p: u32) -> u32 {
+    let mut w = l - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+    loop {
+        i ^= p;
+        i = i.wrapping_mul(0xe170893d);
+        i ^= p >> 16;
+        i ^= (i & w) >> 4;
+        i ^= p >> 8;
+        i = i.wrapping_mul(0x0929eb3f);
+        i ^= p >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | p >> 27);
+        i = i.wrapping_mul(0x6935fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dcb303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e501cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860a3df);
+        i &= w;
+        i ^= i >> 5;
+        if i < l {
+            break;
+        }
+    }
+    (i.wrapping_add(p)) % l
+}
+
+// The PCG32 generator (XSH RR variant) with selectable stream.
+#[derive(Clone)]
+pub struct Pcg32 {
+    state: u64,
+    inc: u64,
+}
+
+impl Pcg32 {
+    const MULTIPLIER: u64 = 6364136223846793005;
+
+    pub fn new(seed: u64, stream: u64) -> Self {
+        let mut rng = Self {
+            state: 0,
+            inc: (stream << 1) | 1,
+        };
+        rng.next_u32();
+        rng.state = rng.state.wrapping_add(seed);
+        rng.next_u32();
+        rng
+    }
+}
+
+impl RngCore for Pcg32 {
+    fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old.wrapping_mul(Self::MULTIPLIER).wrapping_add(self.inc);
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        xorshifted.rotate_right((old >> 59) as u32)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let hi = self.next_u32() as u64;
+        (hi << 32) | self.next_u32() as u64
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(4) {
+            let bytes = self.next_u32().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl SeedableRng for Pcg32 {
+    type Seed = [u8; 16];
+
+    fn from_seed(seed: Self::Seed) -> Self {
+        let (state, stream) = seed.split_at(8);
+        Self::new(
+            u64::from_le_bytes(state.try_into().unwrap()),
+            u64::from_le_bytes(stream.try_into().unwrap()),
+        )
+    }
+
+    fn seed_from_u64(seed: u64) -> Self {
+        Self::new(seed, 0)
+    }
+}
+
+// SplitMix64 finalizer; scatters nearby inputs (neighbouring pixels,
+// consecutive sample indices) across the whole seed space.
+pub fn mix64(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+fn pixel_hash(x: usize, y: usize) -> u64 {
+    mix64(mix64(x as u64) ^ y as u64)
+}
+
+fn hash_u32(x: u32) -> u32 {
+    mix64(x as u64) as u32
+}
+
+const ONE_MINUS_EPSILON: f64 = 1f64 - f64::EPSILON / 2f64;
+
+fn unit_f64(bits: u64) -> f64 {
+    (bits >> 11) as f64 * (1f64 / (1u64 << 53) as f64)
+}
+
+fn unit_f32_bits(bits: u32) -> f64 {
+    bits as f64 * (1f64 / (1u64 << 32) as f64)
+}
@@ -0,0 +1,1677 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use rand::Rng;
+
+use crate::{
+    aabb::Aabb,
+    bvh::{BvhNode, BvhOptions, BvhStats},
+    camera::Camera,
+    constant_medium::ConstantMedium,
+    csg::{Csg, CsgOp},
+    cube::Cube,
+    gltf,
+    heightfield::{HeightMap, Heightfield},
+    hittable::{AnimatedInstance, Hittable, TransformedInstance},
+    hittable_list::HittableList,
+    import::ImportError,
+    json::{self, Value, ValueKind},
+    material::{Dielectric, DiffuseLight, Isotropic, Lambertian, Metal, Scatter},
+    obj,
+    perlin::Perlin,
+    ply,
+    quad::Quad,
+    quadric::{Annulus, Cone, Cylinder, Disk, Paraboloid, Torus},
+    sampler::Pcg32,
+    sdf::{
+        Capsule, Repeat, RoundedBox, Sdf, SdfBox, SdfShape, SdfTorus, SmoothUnion, Subtraction,
+        Twist,
+    },
+    sphere::{MovingSphere, Sphere},
+    stl,
+    texture::{CheckerTexture, ImageTexture, NoiseTexture, SolidColor, Texture},
+    transform::{AnimatedTransform, Keyframe, Mat4, Quat, Transform},
+    triangle::{Triangle, TriangleMesh},
+    vec3::{cross, dot, Color, Point3, Vec3},
+};
+
+pub struct Scene {
+    pub world: HittableList,
+    pub camera: Camera,
+    pub aspect_ratio: f64,
+    pub background: Color,
+    // Statistics of every BVH built while loading, by the field (or file)
+    // it was built for.
+    pub bvh_stats: Vec<(String, BvhStats)>,
+}
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(io::Error),
+    Syntax(json::ParseError),
+    Import(ImportError),
+    Field {
+        line: usize,
+        column: usize,
+        field: String,
+        message: String,
+    },
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneError::Io(e) => write!(f, "{}", e),
+            SceneError::Syntax(e) => write!(f, "{}", e),
+            SceneError::Import(e) => write!(f, "{}", e),
+            SceneError::Field {
+                line,
+                column,
+                field,
+                message,
+            } => write!(f, "{}:{}: {}: {}", line, column, field, message),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+impl From<io::Error> for SceneError {
+    fn from(e: io::Error) -> Self {
+        SceneError::Io(e)
+    }
+}
+
+impl From<json::ParseError> for SceneError {
+    fn from(e: json::ParseError) -> Self {
+        SceneError::Syntax(e)
+    }
+}
+
+impl From<ImportError> for SceneError {
+    fn from(e: ImportError) -> Self {
+        SceneError::Import(e)
+    }
+}
+
+// JSON scene files, or glTF files rendered on their own. Every BVH in the
+// scene is built and laid out as `bvh` says.
+pub fn load(path: &Path, bvh: BvhOptions) -> Result<Scene, SceneError> {
+    if let Some("gltf" | "glb") = path.extension().and_then(|e| e.to_str()) {
+        return gltf_scene(path, bvh);
+    }
+    let src = fs::read_to_string(path)?;
+    let base_dir = path.parent().unwrap_or(Path::new("."));
+    parse(&src, base_dir, bvh)
+}
+
+// The first glTF camera, or a view of the whole model from +Z when there is
+// none. Lights are not imported, so a sky background provides the light.
+fn gltf_scene(path: &Path, bvh: BvhOptions) -> Result<Scene, SceneError> {
+    let model = gltf::load(path, bvh.builder, &mut Pcg32::new(0, 0))?;
+    let bvh_stats = vec![(path.display().to_string(), model.world.stats())];
+    let (camera, aspect_ratio) = match model.cameras.first() {
+        Some(camera) => {
+            let aspect_ratio = camera.aspect_ratio.unwrap_or(1.5);
+            (camera.camera(aspect_ratio), aspect_ratio)
+        }
+        None => {
+            let mut bbox = Aabb::default();
+            model.world.bounding_box([0f64, 1f64], &mut bbox);
+            let center = (bbox.min + bbox.max) * 0.5;
+            let radius = (bbox.max - bbox.min).modulus() * 0.5;
+            let vfov = 40f64;
+            let distance = radius / (vfov.to_radians() / 2f64).sin();
+            let lookfrom = center + Vec3::new(0f64, 0f64, distance);
+            let aspect_ratio = 1.5;
+            let camera = Camera::new(
+                lookfrom,
+                center,
+                Vec3::new(0f64, 1f64, 0f64),
+                vfov,
+                aspect_ratio,
+                0f64,
+                distance,
+                [0f64, 1f64],
+            );
+            (camera, aspect_ratio)
+        }
+    };
+    let mut world = HittableList::new();
+    world.push(bvh.layout.create(model.world));
+    Ok(Scene {
+        world,
+        camera,
+        aspect_ratio,
+        background: Color::new(0.7, 0.8, 1.0),
+        bvh_stats,
+    })
+}
+
+// Relative file references (image textures) are resolved against `base_dir`.
+pub fn parse(src: &str, base_dir: &Path, bvh: BvhOptions) -> Result<Scene, SceneError> {
+    let root = json::parse(src)?;
+    let seed = match root.get("seed") {
+        Some(v) => number(v, "seed")? as u64,
+        None => 0,
+    };
+    let mut loader = Loader {
+        base_dir: base_dir.to_path_buf(),
+        textures: HashMap::new(),
+        materials: HashMap::new(),
+        shapes: HashMap::new(),
+        rng: RefCell::new(Pcg32::new(seed, 0)),
+        bvh,
+        bvh_stats: RefCell::new(Vec::new()),
+    };
+    loader.scene(&root)
+}
+
+struct Loader {
+    base_dir: PathBuf,
+    textures: HashMap<String, Arc<dyn Texture>>,
+    materials: HashMap<String, Arc<dyn Scatter>>,
+    // Objects built once and shared by every reference to their name.
+    shapes: HashMap<String, Arc<dyn Hittable>>,
+    // Drives construction-time randomness (noise tables, BVH split axes) so
+    // that a scene file always builds the same scene.
+    rng: RefCell<Pcg32>,
+    bvh: BvhOptions,
+    bvh_stats: RefCell<Vec<(String, BvhStats)>>,
+}
+
+impl Loader {
+    fn scene(&mut self, root: &Value) -> Result<Scene, SceneError> {
+        check_keys(
+            root,
+            "",
+            &[
+                "camera",
+                "background",
+                "seed",
+                "textures",
+                "materials",
+                "shapes",
+                "objects",
+            ],
+        )?;
+        if let Some(textures) = root.get("textures") {
+            for (name, v) in object(textures, "textures")? {
+                let texture = self.texture(v, &join("textures", name))?;
+                self.textures.insert(name.clone(), texture);
+            }
+        }
+        if let Some(materials) = root.get("materials") {
+            for (name, v) in object(materials, "materials")? {
+                let material = self.material(v, &join("materials", name))?;
+                self.materials.insert(name.clone(), material);
+            }
+        }
+        if let Some(shapes) = root.get("shapes") {
+            for (name, v) in object(shapes, "shapes")? {
+                let shape = self.object(v, &join("shapes", name))?;
+                self.shapes.insert(name.clone(), shape);
+            }
+        }
+        let background = match root.get("background") {
+            Some(v) => vec3(v, "background")?,
+            None => Color::default(),
+        };
+        let (camera, aspect_ratio) = camera(required(root, "", "camera")?, "camera")?;
+        let mut world = HittableList::new();
+        for (i, v) in array(required(root, "", "objects")?, "objects")?
+            .iter()
+            .enumerate()
+        {
+            world.push(self.object_ref(v, &format!("objects[{}]", i))?);
+        }
+        Ok(Scene {
+            world,
+            camera,
+            aspect_ratio,
+            background,
+            bvh_stats: self.bvh_stats.take(),
+        })
+    }
+
+    // A Perlin height map: `resolution` samples along x and z, `frequency`
+    // noise cells across and `octaves` layers of detail.
+    fn height_noise(&self, v: &Value, field: &str) -> Result<HeightMap, SceneError> {
+        check_keys(v, field, &["resolution", "frequency", "octaves"])?;
+        let resolution = required(v, field, "resolution")?;
+        let [width, depth] = numbers::<2>(resolution, &join(field, "resolution"))?;
+        if width < 2f64 || depth < 2f64 || width.fract() != 0f64 || depth.fract() != 0f64 {
+            return Err(error(
+                resolution,
+                &join(field, "resolution"),
+                "must be integers of at least 2",
+            ));
+        }
+        let frequency = opt_number(v, field, "frequency", 4f64)?;
+        let octaves = opt_number(v, field, "octaves", 4f64)?;
+        if octaves < 1f64 || octaves.fract() != 0f64 {
+            return Err(error(
+                required(v, field, "octaves")?,
+                &join(field, "octaves"),
+                "must be a positive integer",
+            ));
+        }
+        let noise = Perlin::new(&mut *self.rng.borrow_mut());
+        Ok(HeightMap::perlin(
+            &noise,
+            width as usize,
+            depth as usize,
+            frequency,
+            octaves as u32,
+        ))
+    }
+
+    // `count` random placements inside the box from `min` to `max`, each
+    // turned about y and scaled by a factor drawn from `scale`.
+    fn scatter(&self, v: &Value, field: &str) -> Result<Vec<Transform>, SceneError> {
+        check_keys(v, field, &["count", "min", "max", "scale"])?;
+        let count_value = required(v, field, "count")?;
+        let count = number(count_value, &join(field, "count"))?;
+        if count < 0f64 || count.fract() != 0f64 {
+            return Err(error(
+                count_value,
+                &join(field, "count"),
+                "must be a non-negative integer",
+            ));
+        }
+        let min = vec3(required(v, field, "min")?, &join(field, "min"))?;
+        let max = vec3(required(v, field, "max")?, &join(field, "max"))?;
+        let scale = match v.get("scale") {
+            Some(s) => range(s, &join(field, "scale"))?,
+            None => [1f64, 1f64],
+        };
+        if scale[0] <= 0f64 {
+            return Err(error(
+                required(v, field, "scale")?,
+                &join(field, "scale"),
+                "must be positive",
+            ));
+        }
+        let mut rng = self.rng.borrow_mut();
+        let transforms = (0..count as usize)
+            .map(|_| {
+                let offset = Vec3::new(
+                    min.x() + (max.x() - min.x()) * rng.gen::<f64>(),
+                    min.y() + (max.y() - min.y()) * rng.gen::<f64>(),
+                    min.z() + (max.z() - min.z()) * rng.gen::<f64>(),
+                );
+                let angle = 360f64 * rng.gen::<f64>();
+                let factor = scale[0] + (scale[1] - scale[0]) * rng.gen::<f64>();
+                let m = Mat4::translate(&offset)
+                    * Mat4::rotate_y(angle)
+                    * Mat4::scale(&Vec3::new(factor, factor, factor));
+                Transform::new(m).expect("positive scale is invertible")
+            })
+            .collect();
+        Ok(transforms)
+    }
+
+    fn bvh(&self, list: &HittableList, time: [f64; 2], field: &str) -> Arc<dyn Hittable> {
+        let node = self
+            .bvh
+            .builder
+            .build(list, time, &mut *self.rng.borrow_mut());
+        self.finish_bvh(node, field)
+    }
+
+    // Records the statistics of a built tree and lays it out.
+    fn finish_bvh(&self, node: BvhNode, field: &str) -> Arc<dyn Hittable> {
+        self.bvh_stats
+            .borrow_mut()
+            .push((field.to_string(), node.stats()));
+        self.bvh.layout.create(node)
+    }
+
+    fn texture_ref(&self, v: &Value, field: &str) -> Result<Arc<dyn Texture>, SceneError> {
+        match &v.kind {
+            ValueKind::String(name) => self
+                .textures
+                .get(name)
+                .cloned()
+                .ok_or_else(|| error(v, field, format!("unknown texture `{}`", name))),
+            ValueKind::Array(_) => Ok(Arc::new(SolidColor::from_color(vec3(v, field)?))),
+            ValueKind::Object(_) => self.texture(v, field),
+            _ => Err(expected(v, field, "texture name, color or texture")),
+        }
+    }
+
+    fn texture(&self, v: &Value, field: &str) -> Result<Arc<dyn Texture>, SceneError> {
+        let (kind, kind_value) = type_of(v, field)?;
+        let texture: Arc<dyn Texture> = match kind {
+            "solid" => {
+                check_keys(v, field, &["type", "color"])?;
+                Arc::new(SolidColor::from_color(vec3(
+                    required(v, field, "color")?,
+                    &join(field, "color"),
+                )?))
+            }
+            "checker" => {
+                check_keys(v, field, &["type", "even", "odd"])?;
+                let even = self.texture_ref(required(v, field, "even")?, &join(field, "even"))?;
+                let odd = self.texture_ref(required(v, field, "odd")?, &join(field, "odd"))?;
+                Arc::new(CheckerTexture::new(even, odd))
+            }
+            "noise" => {
+                check_keys(v, field, &["type", "scale"])?;
+                let scale = opt_number(v, field, "scale", 1f64)?;
+                Arc::new(NoiseTexture::new(
+                    Perlin::new(&mut *self.rng.borrow_mut()),
+                    scale,
+                ))
+            }
+            "image" => {
+                check_keys(v, field, &["type", "file"])?;
+                let file_value = required(v, field, "file")?;
+                let file_field = join(field, "file");
+                let path = self.base_dir.join(string(file_value, &file_field)?);
+                Arc::new(ImageTexture::open(&path).map_err(|e| {
+                    error(
+                        file_value,
+                        &file_field,
+                        format!("cannot load `{}`: {}", path.display(), e),
+                    )
+                })?)
+            }
+            _ => {
+                return Err(error(
+                    kind_value,
+                    &join(field, "type"),
+                    format!("unknown texture type `{}`", kind),
+                ))
+            }
+        };
+        Ok(texture)
+    }
+
+    fn material_ref(&self, v: &Value, field: &str) -> Result<Arc<dyn Scatter>, SceneError> {
+        match &v.kind {
+            ValueKind::String(name) => self
+                .materials
+                .get(name)
+                .cloned()
+                .ok_or_else(|| error(v, field, format!("unknown material `{}`", name))),
+            ValueKind::Object(_) => self.material(v, field),
+            _ => Err(expected(v, field, "material name or material")),
+        }
+    }
+
+    fn material(&self, v: &Value, field: &str) -> Result<Arc<dyn Scatter>, SceneError> {
+        let (kind, kind_value) = type_of(v, field)?;
+        let material: Arc<dyn Scatter> = match kind {
+            "lambertian" => {
+                check_keys(v, field, &["type", "albedo"])?;
+                let albedo =
+                    self.texture_ref(required(v, field, "albedo")?, &join(field, "albedo"))?;
+                Arc::new(Lambertian::from_texture(albedo))
+            }
+            "metal" => {
+                check_keys(v, field, &["type", "albedo", "fuzz"])?;
+                let albedo = vec3(required(v, field, "albedo")?, &join(field, "albedo"))?;
+                Arc::new(Metal::new(albedo, opt_number(v, field, "fuzz", 0f64)?))
+            }
+            "dielectric" => {
+                check_keys(v, field, &["type", "ir"])?;
+                Arc::new(Dielectric::new(number(
+                    required(v, field, "ir")?,
+                    &join(field, "ir"),
+                )?))
+            }
+            "diffuse_light" => {
+                check_keys(v, field, &["type", "emit"])?;
+                let emit = self.texture_ref(required(v, field, "emit")?, &join(field, "emit"))?;
+                Arc::new(DiffuseLight::from_texture(emit))
+            }
+            "isotropic" => {
+                check_keys(v, field, &["type", "albedo"])?;
+                let albedo =
+                    self.texture_ref(required(v, field, "albedo")?, &join(field, "albedo"))?;
+                Arc::new(Isotropic::from_texture(albedo))
+            }
+            _ => {
+                return Err(error(
+                    kind_value,
+                    &join(field, "type"),
+                    format!("unknown material type `{}`", kind),
+                ))
+            }
+        };
+        Ok(material)
+    }
+
+    fn objects(&self, v: &Value, field: &str) -> Result<HittableList, SceneError> {
+        let mut list = HittableList::new();
+        for (i, item) in array(v, field)?.iter().enumerate() {
+            list.push(self.object_ref(item, &format!("{}[{}]", field, i))?);
+        }
+        Ok(list)
+    }
+
+    fn object_ref(&self, v: &Value, field: &str) -> Result<Arc<dyn Hittable>, SceneError> {
+        match &v.kind {
+            ValueKind::String(name) => self
+                .shapes
+                .get(name)
+                .cloned()
+                .ok_or_else(|| error(v, field, format!("unknown shape `{}`", name))),
+            ValueKind::Object(_) => self.object(v, field),
+            _ => Err(expected(v, field, "shape name or object")),
+        }
+    }
+
+    fn object(&self, v: &Value, field: &str) -> Result<Arc<dyn Hittable>, SceneError> {
+        let (kind, kind_value) = type_of(v, field)?;
+        let get = |key: &str| required(v, field, key);
+        let material = |key: &str| self.material_ref(get(key)?, &join(field, key));
+        let num = |key: &str| number(get(key)?, &join(field, key));
+        let point = |key: &str| vec3(get(key)?, &join(field, key));
+        let span = |key: &str| range(get(key)?, &join(field, key));
+        let positive = |key: &str| match num(key)? {
+            n if n > 0f64 => Ok(n),
+            _ => Err(error(get(key)?, &join(field, key), "must be positive")),
+        };
+        let phi_max = || match opt_number(v, field, "phi_max", 360f64)? {
+            phi if phi > 0f64 && phi <= 360f64 => Ok(phi),
+            _ => Err(error(
+                get("phi_max")?,
+                &join(field, "phi_max"),
+                "must be more than 0 and at most 360 degrees",
+            )),
+        };
+        let object: Arc<dyn Hittable> = match kind {
+            "sphere" => {
+                check_keys(v, field, &["type", "center", "radius", "material"])?;
+                Arc::new(Sphere::new(
+                    point("center")?,
+                    num("radius")?,
+                    material("material")?,
+                ))
+            }
+            "moving_sphere" => {
+                check_keys(
+                    v,
+                    field,
+                    &["type", "center0", "center1", "time", "radius", "material"],
+                )?;
+                Arc::new(MovingSphere::new(
+                    [point("center0")?, point("center1")?],
+                    span("time")?,
+                    num("radius")?,
+                    material("material")?,
+                ))
+            }
+            "xy_rect" => {
+                check_keys(v, field, &["type", "x", "y", "k", "material"])?;
+                let ([x0, x1], [y0, y1]) = (span("x")?, span("y")?);
+                quad(
+                    v,
+                    field,
+                    Quad::new(
+                        Point3::new(x0, y0, num("k")?),
+                        Vec3::new(x1 - x0, 0f64, 0f64),
+                        Vec3::new(0f64, y1 - y0, 0f64),
+                        material("material")?,
+                    ),
+                )?
+            }
+            "zx_rect" => {
+                check_keys(v, field, &["type", "x", "z", "k", "material"])?;
+                let ([x0, x1], [z0, z1]) = (span("x")?, span("z")?);
+                quad(
+                    v,
+                    field,
+                    Quad::new(
+                        Point3::new(x0, num("k")?, z0),
+                        Vec3::new(x1 - x0, 0f64, 0f64),
+                        Vec3::new(0f64, 0f64, z1 - z0),
+                        material("material")?,
+                    ),
+                )?
+            }
+            "yz_rect" => {
+                check_keys(v, field, &["type", "y", "z", "k", "material"])?;
+                let ([y0, y1], [z0, z1]) = (span("y")?, span("z")?);
+                quad(
+                    v,
+                    field,
+                    Quad::new(
+                        Point3::new(num("k")?, y0, z0),
+                        Vec3::new(0f64, y1 - y0, 0f64),
+                        Vec3::new(0f64, 0f64, z1 - z0),
+                        material("material")?,
+                    ),
+                )?
+            }
+            "quad" => {
+                check_keys(v, field, &["type", "q", "u", "v", "material"])?;
+                quad(
+                    v,
+                    field,
+                    Quad::new(point("q")?, point("u")?, point("v")?, material("material")?),
+                )?
+            }
+            "cylinder" => {
+                check_keys(v, field, &["type", "radius", "z", "phi_max", "material"])?;
+                Arc::new(Cylinder::new(
+                    positive("radius")?,
+                    span("z")?,
+                    phi_max()?,
+                    material("material")?,
+                ))
+            }
+            "cone" => {
+                check_keys(
+                    v,
+                    field,
+                    &["type", "radius", "height", "z", "phi_max", "material"],
+                )?;
+                let height = positive("height")?;
+                let z = match v.get("z") {
+                    Some(_) => span("z")?,
+                    None => [0f64, height],
+                };
+                if z[0] < 0f64 || z[1] > height || z[0] == z[1] {
+                    return Err(error(
+                        get("z")?,
+                        &join(field, "z"),
+                        "heights must differ and lie between 0 and `height`",
+                    ));
+                }
+                Arc::new(Cone::new(
+                    positive("radius")?,
+                    height,
+                    z,
+                    phi_max()?,
+                    material("material")?,
+                ))
+            }
+            "disk" => {
+                check_keys(
+                    v,
+                    field,
+                    &["type", "radius", "height", "phi_max", "material"],
+                )?;
+                Arc::new(Disk::new(
+                    opt_number(v, field, "height", 0f64)?,
+                    positive("radius")?,
+                    phi_max()?,
+                    material("material")?,
+                ))
+            }
+            "annulus" => {
+                check_keys(
+                    v,
+                    field,
+                    &[
+                        "type",
+                        "inner_radius",
+                        "radius",
+                        "height",
+                        "phi_max",
+                        "material",
+                    ],
+                )?;
+                let (inner_radius, radius) = (num("inner_radius")?, positive("radius")?);
+                if inner_radius < 0f64 || inner_radius >= radius {
+                    return Err(error(
+                        get("inner_radius")?,
+                        &join(field, "inner_radius"),
+                        "must be at least 0 and less than `radius`",
+                    ));
+                }
+                Arc::new(Annulus::new(
+                    opt_number(v, field, "height", 0f64)?,
+                    inner_radius,
+                    radius,
+                    phi_max()?,
+                    material("material")?,
+                ))
+            }
+            "paraboloid" => {
+                check_keys(v, field, &["type", "radius", "z", "phi_max", "material"])?;
+                let z = span("z")?;
+                if z[0] < 0f64 || z[1] <= 0f64 {
+                    return Err(error(
+                        get("z")?,
+                        &join(field, "z"),
+                        "heights must not be negative, and the top must be above 0",
+                    ));
+                }
+                Arc::new(Paraboloid::new(
+                    positive("radius")?,
+                    z,
+                    phi_max()?,
+                    material("material")?,
+                ))
+            }
+            "torus" => {
+                check_keys(
+                    v,
+                    field,
+                    &[
+                        "type",
+                        "major_radius",
+                        "minor_radius",
+                        "theta",
+                        "phi_max",
+                        "material",
+                    ],
+                )?;
+                let (major, minor) = (positive("major_radius")?, positive("minor_radius")?);
+                if minor >= major {
+                    return Err(error(
+                        get("minor_radius")?,
+                        &join(field, "minor_radius"),
+                        "must be less than `major_radius`",
+                    ));
+                }
+                let theta = match v.get("theta") {
+                    Some(_) => span("theta")?,
+                    None => [0f64, 360f64],
+                };
+                if theta[0] == theta[1] || theta[1] - theta[0] > 360f64 {
+                    return Err(error(
+                        get("theta")?,
+                        &join(field, "theta"),
+                        "angles must be more than 0 and at most 360 degrees apart",
+                    ));
+                }
+                Arc::new(Torus::new(
+                    major,
+                    minor,
+                    theta,
+                    phi_max()?,
+                    material("material")?,
+                ))
+            }
+            "cube" => {
+                check_keys(v, field, &["type", "min", "max", "material"])?;
+                Arc::new(Cube::new(
+                    point("min")?,
+                    point("max")?,
+                    material("material")?,
+                ))
+            }
+            "box" => {
+                check_keys(v, field, &["type", "corner", "edges", "material"])?;
+                let edges = numbers_of::<3, _>(get("edges")?, &join(field, "edges"), vec3)?;
+                if dot(&cross(&edges[0], &edges[1]), &edges[2]) == 0f64 {
+                    return Err(error(
+                        get("edges")?,
+                        &join(field, "edges"),
+                        "edges must not lie in one plane",
+                    ));
+                }
+                Arc::new(Cube::oriented(
+                    point("corner")?,
+                    edges,
+                    material("material")?,
+                ))
+            }
+            "heightfield" => {
+                check_keys(
+                    v,
+                    field,
+                    &["type", "file", "noise", "corner", "size", "material"],
+                )?;
+                let map = match (v.get("file"), v.get("noise")) {
+                    (Some(file_value), None) => {
+                        let file_field = join(field, "file");
+                        let path = self.base_dir.join(string(file_value, &file_field)?);
+                        HeightMap::open(&path).map_err(|e| {
+                            error(
+                                file_value,
+                                &file_field,
+                                format!("cannot load `{}`: {}", path.display(), e),
+                            )
+                        })?
+                    }
+                    (None, Some(noise)) => self.height_noise(noise, &join(field, "noise"))?,
+                    _ => {
+                        return Err(error(
+                            v,
+                            field,
+                            "heightfield needs exactly one of `file` and `noise`",
+                        ))
+                    }
+                };
+                let size = point("size")?;
+                if size.x() <= 0f64 || size.y() <= 0f64 || size.z() <= 0f64 {
+                    return Err(error(
+                        get("size")?,
+                        &join(field, "size"),
+                        "must be positive",
+                    ));
+                }
+                let (width, depth) = (map.width(), map.depth());
+                Arc::new(
+                    Heightfield::new(map, point("corner")?, size, material("material")?)
+                        .ok_or_else(|| {
+                            error(
+                                v,
+                                field,
+                                format!(
+                                    "height map is {} x {}; it needs at least 2 x 2 samples",
+                                    width, depth
+                                ),
+                            )
+                        })?,
+                )
+            }
+            "sdf" => {
+                check_keys(v, field, &["type", "shape", "material"])?;
+                Arc::new(SdfShape::new(
+                    sdf(get("shape")?, &join(field, "shape"))?,
+                    material("material")?,
+                ))
+            }
+            "triangle" => {
+                check_keys(v, field, &["type", "vertices", "material"])?;
+                let [p0, p1, p2] =
+                    numbers_of::<3, _>(get("vertices")?, &join(field, "vertices"), vec3)?;
+                Arc::new(Triangle::new(p0, p1, p2, material("material")?))
+            }
+            "mesh" => {
+                check_keys(
+                    v,
+                    field,
+                    &["type", "positions", "normals", "uvs", "indices", "material"],
+                )?;
+                let optional = |key: &str| v.get(key).map(|items| (items, join(field, key)));
+                let positions = list_of(get("positions")?, &join(field, "positions"), vec3)?;
+                let normals = optional("normals")
+                    .map(|(items, key)| list_of(items, &key, vec3))
+                    .transpose()?
+                    .unwrap_or_default();
+                let uvs = optional("uvs")
+                    .map(|(items, key)| list_of(items, &key, numbers::<2>))
+                    .transpose()?
+                    .unwrap_or_default();
+                let indices = list_of(get("indices")?, &join(field, "indices"), |v, field| {
+                    let face = numbers::<3>(v, field)?;
+                    let mut out = [0usize; 3];
+                    for (i, n) in face.into_iter().enumerate() {
+                        if n < 0f64 || n.fract() != 0f64 {
+                            return Err(error(v, field, "indices must be non-negative integers"));
+                        }
+                        out[i] = n as usize;
+                    }
+                    Ok(out)
+                })?;
+                if indices.is_empty() {
+                    return Err(error(
+                        get("indices")?,
+                        &join(field, "indices"),
+                        "mesh needs at least one face",
+                    ));
+                }
+                let mesh =
+                    TriangleMesh::new(positions, normals, uvs, indices, material("material")?)
+                        .map_err(|e| error(v, field, e.to_string()))?;
+                let triangles = TriangleMesh::triangles(&Arc::new(mesh));
+                self.bvh(&triangles, [0f64, 1f64], field)
+            }
+            "obj" => {
+                check_keys(v, field, &["type", "file", "material"])?;
+                let file = self
+                    .base_dir
+                    .join(string(get("file")?, &join(field, "file"))?);
+                let default_material = match v.get("material") {
+                    Some(m) => self.material_ref(m, &join(field, "material"))?,
+                    None => Arc::new(Lambertian::from_color(Color::new(0.8, 0.8, 0.8))),
+                };
+                let model = obj::load(
+                    &file,
+                    default_material,
+                    self.bvh.builder,
+                    &mut *self.rng.borrow_mut(),
+                )
+                .map_err(|e| error(v, field, e.to_string()))?;
+                self.finish_bvh(model, field)
+            }
+            "gltf" => {
+                check_keys(v, field, &["type", "file"])?;
+                let file = self
+                    .base_dir
+                    .join(string(get("file")?, &join(field, "file"))?);
+                let model = gltf::load(&file, self.bvh.builder, &mut *self.rng.borrow_mut())
+                    .map_err(|e| error(v, field, e.to_string()))?;
+                self.finish_bvh(model.world, field)
+            }
+            "ply" | "stl" => {
+                check_keys(v, field, &["type", "file", "material"])?;
+                let file = self
+                    .base_dir
+                    .join(string(get("file")?, &join(field, "file"))?);
+                let read = if kind == "ply" { ply::read } else { stl::read };
+                let mut data = read(&file).map_err(|e| error(v, field, e.to_string()))?;
+                // Vertex colors stand in for the material unless one is given.
+                let material: Arc<dyn Scatter> = match v.get("material") {
+                    Some(m) => self.material_ref(m, &join(field, "material"))?,
+                    None => match data.vertex_color_texture() {
+                        Some(colors) => Arc::new(Lambertian::from_texture(Arc::new(colors))),
+                        None => Arc::new(Lambertian::from_color(Color::new(0.8, 0.8, 0.8))),
+                    },
+                };
+                let mesh = data
+                    .into_mesh(material)
+                    .map_err(|e| error(v, field, format!("{}: {}", file.display(), e)))?;
+                let triangles = TriangleMesh::triangles(&Arc::new(mesh));
+                self.bvh(&triangles, [0f64, 1f64], field)
+            }
+            "constant_medium" => {
+                check_keys(v, field, &["type", "boundary", "density", "albedo"])?;
+                let boundary = self.object_ref(get("boundary")?, &join(field, "boundary"))?;
+                let albedo = self.texture_ref(get("albedo")?, &join(field, "albedo"))?;
+                Arc::new(ConstantMedium::from_texture(
+                    boundary,
+                    num("density")?,
+                    albedo,
+                ))
+            }
+            "transform" => {
+                check_keys(
+                    v,
+                    field,
+                    &[
+                        "type",
+                        "object",
+                        "translate",
+                        "rotate",
+                        "scale",
+                        "matrix",
+                        "keyframes",
+                    ],
+                )?;
+                let inner = self.object_ref(get("object")?, &join(field, "object"))?;
+                match v.get("keyframes") {
+                    Some(keys) => {
+                        only_key(v, field, "keyframes")?;
+                        let motion = keyframes(keys, &join(field, "keyframes"))?;
+                        Arc::new(AnimatedInstance::new(motion, inner))
+                    }
+                    None => Arc::new(TransformedInstance::new(transform(v, field)?, inner)),
+                }
+            }
+            // Kept for older scene files; both are special cases of
+            // `transform`.
+            "translate" => {
+                check_keys(v, field, &["type", "offset", "object"])?;
+                let inner = self.object_ref(get("object")?, &join(field, "object"))?;
+                let offset = Transform::new(Mat4::translate(&point("offset")?));
+                Arc::new(TransformedInstance::new(offset.unwrap(), inner))
+            }
+            "rotate_y" => {
+                check_keys(v, field, &["type", "angle", "object"])?;
+                let inner = self.object_ref(get("object")?, &join(field, "object"))?;
+                let rotation = Transform::new(Mat4::rotate_y(num("angle")?));
+                Arc::new(TransformedInstance::new(rotation.unwrap(), inner))
+            }
+            "instances" => {
+                check_keys(v, field, &["type", "object", "transforms", "scatter"])?;
+                let shared = self.object_ref(get("object")?, &join(field, "object"))?;
+                let mut transforms = match v.get("transforms") {
+                    Some(items) => list_of(items, &join(field, "transforms"), |v, field| {
+                        check_keys(v, field, &["translate", "rotate", "scale", "matrix"])?;
+                        transform(v, field)
+                    })?,
+                    None => Vec::new(),
+                };
+                if let Some(scatter) = v.get("scatter") {
+                    transforms.extend(self.scatter(scatter, &join(field, "scatter"))?);
+                }
+                if transforms.is_empty() {
+                    return Err(error(v, field, "needs at least one instance"));
+                }
+                let mut list = HittableList::new();
+                for transform in transforms {
+                    list.push(Arc::new(TransformedInstance::new(
+                        transform,
+                        shared.clone(),
+                    )));
+                }
+                self.bvh(&list, [0f64, 1f64], field)
+            }
+            "list" => {
+                check_keys(v, field, &["type", "objects"])?;
+                Arc::new(self.objects(get("objects")?, &join(field, "objects"))?)
+            }
+            "bvh" => {
+                check_keys(v, field, &["type", "objects", "time"])?;
+                let list = self.objects(get("objects")?, &join(field, "objects"))?;
+                if list.objects.is_empty() {
+                    return Err(error(
+                        get("objects")?,
+                        &join(field, "objects"),
+                        "bvh needs at least one object",
+                    ));
+                }
+                let time = match v.get("time") {
+                    Some(t) => range(t, &join(field, "time"))?,
+                    None => [0f64, 1f64],
+                };
+                self.bvh(&list, time, field)
+            }
+            _ => {
+                let op = CsgOp::from_name(kind).ok_or_else(|| {
+                    error(
+                        kind_value,
+                        &join(field, "type"),
+                        format!("unknown object type `{}`", kind),
+                    )
+                })?;
+                check_keys(v, field, &["type", "objects"])?;
+                let list = self.objects(get("objects")?, &join(field, "objects"))?;
+                if list.objects.len() < 2 {
+                    return Err(error(
+                        get("objects")?,
+                        &join(field, "objects"),
+                        format!("{} needs at least two objects", op.name()),
+                    ));
+                }
+                // Later objects combine with the result so far, so a
+                // difference carves every later object out of the first.
+                let mut objects = list.objects.into_iter();
+                let first = objects.next().unwrap();
+                objects.fold(first, |csg, object| Arc::new(Csg::new(op, csg, object)))
+            }
+        };
+        Ok(object)
+    }
+}
+
+fn camera(v: &Value, field: &str) -> Result<(Camera, f64), SceneError> {
+    check_keys(
+        v,
+        field,
+        &[
+            "lookfrom",
+            "lookat",
+            "vup",
+            "vfov",
+            "aspect_ratio",
+            "aperture",
+            "focus_dist",
+            "time",
+            "motion",
+        ],
+    )?;
+    let lookfrom = vec3(required(v, field, "lookfrom")?, &join(field, "lookfrom"))?;
+    let lookat = vec3(required(v, field, "lookat")?, &join(field, "lookat"))?;
+    let vup = match v.get("vup") {
+        Some(up) => vec3(up, &join(field, "vup"))?,
+        None => Vec3::new(0f64, 1f64, 0f64),
+    };
+    let vfov = opt_number(v, field, "vfov", 40f64)?;
+    let aspect_ratio = opt_number(v, field, "aspect_ratio", 1f64)?;
+    if aspect_ratio <= 0f64 {
+        return Err(error(
+            required(v, field, "aspect_ratio")?,
+            &join(field, "aspect_ratio"),
+            "must be positive",
+        ));
+    }
+    let aperture = opt_number(v, field, "aperture", 0f64)?;
+    let focus_dist = opt_number(v, field, "focus_dist", (lookfrom - lookat).modulus())?;
+    let time = match v.get("time") {
+        Some(t) => range(t, &join(field, "time"))?,
+        None => [0f64, 1f64],
+    };
+    let mut camera = Camera::new(
+        lookfrom,
+        lookat,
+        vup,
+        vfov,
+        aspect_ratio,
+        aperture,
+        focus_dist,
+        time,
+    );
+    if let Some(motion) = v.get("motion") {
+        camera.set_motion(keyframes(motion, &join(field, "motion"))?);
+    }
+    Ok((camera, aspect_ratio))
+}
+
+fn join(field: &str, key: &str) -> String {
+    if field.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", field, key)
+    }
+}
+
+fn error(v: &Value, field: &str, message: impl Into<String>) -> SceneError {
+    SceneError::Field {
+        line: v.line,
+        column: v.column,
+        field: field.to_string(),
+        message: message.into(),
+    }
+}
+
+fn quad(v: &Value, field: &str, quad: Option<Quad>) -> Result<Arc<dyn Hittable>, SceneError> {
+    match quad {
+        Some(quad) => Ok(Arc::new(quad)),
+        None => Err(error(v, field, "sides must span a nonzero area")),
+    }
+}
+
+fn expected(v: &Value, field: &str, what: &str) -> SceneError {
+    error(
+        v,
+        field,
+        format!("expected {}, found {}", what, v.type_name()),
+    )
+}
+
+fn check_keys(v: &Value, field: &str, allowed: &[&str]) -> Result<(), SceneError> {
+    for (key, value) in object(v, field)? {
+        if !allowed.contains(&key.as_str()) {
+            return Err(error(value, &join(field, key), "unknown field"));
+        }
+    }
+    Ok(())
+}
+
+fn required<'a>(v: &'a Value, field: &str, key: &str) -> Result<&'a Value, SceneError> {
+    v.get(key)
+        .ok_or_else(|| error(v, &join(field, key), "missing field"))
+}
+
+fn type_of<'a>(v: &'a Value, field: &str) -> Result<(&'a str, &'a Value), SceneError> {
+    object(v, field)?;
+    let kind = required(v, field, "type")?;
+    Ok((string(kind, &join(field, "type"))?, kind))
+}
+
+fn object<'a>(v: &'a Value, field: &str) -> Result<&'a [(String, Value)], SceneError> {
+    v.as_object().ok_or_else(|| expected(v, field, "object"))
+}
+
+fn array<'a>(v: &'a Value, field: &str) -> Result<&'a [Value], SceneError> {
+    v.as_array().ok_or_else(|| expected(v, field, "array"))
+}
+
+fn string<'a>(v: &'a Value, field: &str) -> Result<&'a str, SceneError> {
+    v.as_str().ok_or_else(|| expected(v, field, "string"))
+}
+
+fn number(v: &Value, field: &str) -> Result<f64, SceneError> {
+    v.as_f64().ok_or_else(|| expected(v, field, "number"))
+}
+
+fn opt_number(v: &Value, field: &str, key: &str, default: f64) -> Result<f64, SceneError> {
+    match v.get(key) {
+        Some(n) => number(n, &join(field, key)),
+        None => Ok(default),
+    }
+}
+
+fn numbers<const N: usize>(v: &Value, field: &str) -> Result<[f64; N], SceneError> {
+    let items = array(v, field)?;
+    if items.len() != N {
+        return Err(error(
+            v,
+            field,
+            format!("expected {} numbers, found {}", N, items.len()),
+        ));
+    }
+    let mut out = [0f64; N];
+    for (i, item) in items.iter().enumerate() {
+        out[i] = number(item, &format!("{}[{}]", field, i))?;
+    }
+    Ok(out)
+}
+
+fn list_of<T>(
+    v: &Value,
+    field: &str,
+    item: impl Fn(&Value, &str) -> Result<T, SceneError>,
+) -> Result<Vec<T>, SceneError> {
+    array(v, field)?
+        .iter()
+        .enumerate()
+        .map(|(i, value)| item(value, &format!("{}[{}]", field, i)))
+        .collect()
+}
+
+fn numbers_of<const N: usize, T>(
+    v: &Value,
+    field: &str,
+    item: impl Fn(&Value, &str) -> Result<T, SceneError>,
+) -> Result<[T; N], SceneError> {
+    let items = list_of(v, field, item)?;
+    let found = items.len();
+    items
+        .try_into()
+        .map_err(|_| error(v, field, format!("expected {} items, found {}", N, found)))
+}
+
+fn vec3(v: &Value, field: &str) -> Result<Vec3, SceneError> {
+    let [x, y, z] = numbers::<3>(v, field)?;
+    Ok(Point3::new(x, y, z))
+}
+
+// `matrix` (four rows of four) or scale, then `rotate` (degrees about x,
+// then y, then z), then `translate`.
+fn transform(v: &Value, field: &str) -> Result<Transform, SceneError> {
+    let m = if let Some(matrix) = v.get("matrix") {
+        only_key(v, field, "matrix")?;
+        let field = join(field, "matrix");
+        let m = numbers_of::<4, _>(matrix, &field, numbers::<4>)?;
+        // Only affine transforms are supported, and points and vectors are
+        // mapped by the top three rows alone.
+        if m[3] != [0f64, 0f64, 0f64, 1f64] {
+            return Err(error(
+                &array(matrix, &field)?[3],
+                &format!("{}[3]", field),
+                "the bottom row must be [0, 0, 0, 1]",
+            ));
+        }
+        Mat4::new(m)
+    } else {
+        let mut m = Mat4::IDENTITY;
+        if let Some(scale) = v.get("scale") {
+            m = Mat4::scale(&scale_factors(scale, &join(field, "scale"))?) * m;
+        }
+        if let Some(rotate) = v.get("rotate") {
+            let [x, y, z] = numbers::<3>(rotate, &join(field, "rotate"))?;
+            m = Mat4::rotate_z(z) * Mat4::rotate_y(y) * Mat4::rotate_x(x) * m;
+        }
+        if let Some(translate) = v.get("translate") {
+            m = Mat4::translate(&vec3(translate, &join(field, "translate"))?) * m;
+        }
+        m
+    };
+    Transform::new(m).ok_or_else(|| error(v, field, "transform is not invertible"))
+}
+
+// `key` replaces the other ways of giving a transform.
+fn only_key(v: &Value, field: &str, key: &str) -> Result<(), SceneError> {
+    for other in ["translate", "rotate", "scale", "matrix", "keyframes"] {
+        if let (true, Some(value)) = (other != key, v.get(other)) {
+            return Err(error(
+                value,
+                &join(field, other),
+                format!("cannot be combined with `{}`", key),
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn scale_factors(v: &Value, field: &str) -> Result<Vec3, SceneError> {
+    match v.as_f64() {
+        Some(s) => Ok(Vec3::new(s, s, s)),
+        None => vec3(v, field),
+    }
+}
+
+// Keys of `time` and any of `translate`, `rotate` (degrees about x, then y,
+// then z) and `scale`.
+fn keyframes(v: &Value, field: &str) -> Result<AnimatedTransform, SceneError> {
+    let keys = list_of(v, field, |key, field| {
+        check_keys(key, field, &["time", "translate", "rotate", "scale"])?;
+        let optional = |name: &str, default: Vec3| match key.get(name) {
+            Some(value) if name == "scale" => scale_factors(value, &join(field, name)),
+            Some(value) => vec3(value, &join(field, name)),
+            None => Ok(default),
+        };
+        Ok(Keyframe {
+            time: number(required(key, field, "time")?, &join(field, "time"))?,
+            translation: optional("translate", Vec3::default())?,
+            rotation: Quat::from_euler(&optional("rotate", Vec3::default())?),
+            scale: optional("scale", Vec3::new(1f64, 1f64, 1f64))?,
+        })
+    })?;
+    if keys.is_empty() {
+        return Err(error(v, field, "needs at least one keyframe"));
+    }
+    AnimatedTransform::new(keys).ok_or_else(|| {
+        error(
+            v,
+            field,
+            "scale must not be zero or change sign between keyframes",
+        )
+    })
+}
+
+// A distance field for an `sdf` object: a primitive, or a combinator of
+// other fields.
+fn sdf(v: &Value, field: &str) -> Result<Arc<dyn Sdf>, SceneError> {
+    let (kind, kind_value) = type_of(v, field)?;
+    let get = |key: &str| required(v, field, key);
+    let point = |key: &str| vec3(get(key)?, &join(field, key));
+    let center = || match v.get("center") {
+        Some(c) => vec3(c, &join(field, "center")),
+        None => Ok(Point3::default()),
+    };
+    let positive = |key: &str| match number(get(key)?, &join(field, key))? {
+        n if n > 0f64 => Ok(n),
+        _ => Err(error(get(key)?, &join(field, key), "must be positive")),
+    };
+    let half_extents = || {
+        let half = point("half_extents")?;
+        if half.x() <= 0f64 || half.y() <= 0f64 || half.z() <= 0f64 {
+            return Err(error(
+                get("half_extents")?,
+                &join(field, "half_extents"),
+                "must be positive",
+            ));
+        }
+        Ok(half)
+    };
+    let smoothness = || match opt_number(v, field, "smoothness", 0f64)? {
+        k if k >= 0f64 => Ok(k),
+        _ => Err(error(
+            get("smoothness")?,
+            &join(field, "smoothness"),
+            "must not be negative",
+        )),
+    };
+    let operands = |key: &str| {
+        let operands = list_of(get(key)?, &join(field, key), sdf)?;
+        if operands.len() < 2 {
+            return Err(error(
+                get(key)?,
+                &join(field, key),
+                format!("{} needs at least two objects", kind),
+            ));
+        }
+        Ok(operands)
+    };
+    let distance: Arc<dyn Sdf> = match kind {
+        "box" => {
+            check_keys(v, field, &["type", "center", "half_extents"])?;
+            Arc::new(SdfBox::new(center()?, half_extents()?))
+        }
+        "rounded_box" => {
+            check_keys(v, field, &["type", "center", "half_extents", "radius"])?;
+            let half = half_extents()?;
+            let radius = positive("radius")?;
+            if radius > half.x().min(half.y()).min(half.z()) {
+                return Err(error(
+                    get("radius")?,
+                    &join(field, "radius"),
+                    "must not exceed the smallest half extent",
+                ));
+            }
+            Arc::new(RoundedBox::new(center()?, half, radius))
+        }
+        "torus" => {
+            check_keys(
+                v,
+                field,
+                &["type", "center", "major_radius", "minor_radius"],
+            )?;
+            Arc::new(SdfTorus::new(
+                center()?,
+                positive("major_radius")?,
+                positive("minor_radius")?,
+            ))
+        }
+        "capsule" => {
+            check_keys(v, field, &["type", "a", "b", "radius"])?;
+            Arc::new(Capsule::new(point("a")?, point("b")?, positive("radius")?))
+        }
+        "smooth_union" | "subtraction" => {
+            check_keys(v, field, &["type", "objects", "smoothness"])?;
+            let k = smoothness()?;
+            let mut operands = operands("objects")?.into_iter();
+            let first = operands.next().unwrap();
+            operands.fold(first, |a, b| -> Arc<dyn Sdf> {
+                match kind {
+                    "smooth_union" => Arc::new(SmoothUnion::new(a, b, k)),
+                    _ => Arc::new(Subtraction::new(a, b, k)),
+                }
+            })
+        }
+        "repeat" => {
+            check_keys(v, field, &["type", "object", "spacing", "copies"])?;
+            let spacing = point("spacing")?;
+            let counts = numbers::<3>(get("copies")?, &join(field, "copies"))?;
+            let mut copies = [1usize; 3];
+            for c in 0..3 {
+                let n = counts[c];
+                if n < 1f64 || n.fract() != 0f64 {
+                    return Err(error(
+                        get("copies")?,
+                        &join(field, "copies"),
+                        "copies must be positive integers",
+                    ));
+                }
+                if n > 1f64 && spacing.e[c] <= 0f64 {
+                    return Err(error(
+                        get("spacing")?,
+                        &join(field, "spacing"),
+                        "spacing must be positive along axes with several copies",
+                    ));
+                }
+                copies[c] = n as usize;
+            }
+            let object = sdf(get("object")?, &join(field, "object"))?;
+            Arc::new(Repeat::new(object, spacing, copies))
+        }
+        "twist" => {
+            check_keys(v, field, &["type", "object", "rate"])?;
+            let rate = number(get("rate")?, &join(field, "rate"))?;
+            Arc::new(Twist::new(
+                sdf(get("object")?, &join(field, "object"))?,
+                rate,
+            ))
+        }
+        _ => {
+            return Err(error(
+                kind_value,
+                &join(field, "type"),
+                format!("unknown distance field type `{}`", kind),
+            ))
+        }
+    };
+    Ok(distance)
+}
+
+fn range(v: &Value, field: &str) -> Result<[f64; 2], SceneError> {
+    let r = numbers::<2>(v, field)?;
+    if r[0] > r[1] {
+        return Err(error(v, field, "range start is greater than its end"));
+    }
+    Ok(r)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scene(src: &str, base_dir: &Path) -> Result<Scene, SceneError> {
+        parse(src, base_dir, BvhOptions::default())
+    }
+
+    // A scene of `objects` sharing the material `m`.
+    fn with_objects(objects: &str) -> String {
+        format!(
+            r#"{{
+  "camera": {{"lookfrom": [0, 0, 5], "lookat": [0, 0, 0]}},
+  "materials": {{"m": {{"type": "lambertian", "albedo": [0.5, 0.5, 0.5]}}}},
+  "objects": [{}]
+}}"#,
+            objects
+        )
+    }
+
+    fn field_error(src: &str) -> (usize, usize, String, String) {
+        match scene(src, Path::new(".")) {
+            Err(SceneError::Field {
+                line,
+                column,
+                field,
+                message,
+            }) => (line, column, field, message),
+            Err(e) => panic!("expected a field error, found `{}`", e),
+            Ok(_) => panic!("expected a field error, but the scene loaded"),
+        }
+    }
+
+    // A fresh directory holding `files`, for the kinds that read from disk.
+    fn directory(name: &str, files: &[(&str, &[u8])]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rtw-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for (file, bytes) in files {
+            fs::write(dir.join(file), bytes).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn syntax_errors_keep_their_position() {
+        match scene("{\n  \"camera\": {,\n}", Path::new(".")) {
+            Err(SceneError::Syntax(e)) => {
+                assert_eq!((e.line, e.column), (2, 14));
+                assert_eq!(e.message, "expected `\"`");
+            }
+            _ => panic!("expected a syntax error"),
+        }
+    }
+
+    #[test]
+    fn allows_comments_and_trailing_commas() {
+        let src = r#"// Two spheres.
+{
+  "camera": {"lookfrom": [0, 0, 5], "lookat": [0, 0, 0],}, // default fov
+  "objects": [
+    {"type": "sphere", "center": [0, 0, 0], "radius": 1, "material": {"type": "metal", "albedo": [1, 1, 1],},},
+    {"type": "sphere", "center": [0, 3, 0], "radius": 1, "material": {"type": "dielectric", "ir": 1.5}},
+  ],
+}
+"#;
+        let scene = scene(src, Path::new(".")).unwrap_or_else(|e| panic!("{}", e));
+        assert_eq!(scene.world.objects.len(), 2);
+    }
+
+    #[test]
+    fn rejects_unknown_keys() {
+        let src = "{\"camera\": {\"lookfrom\": [0, 0, 5], \"lookat\": [0, 0, 0]},\n \"objects\": [], \"lights\": []}";
+        assert_eq!(
+            field_error(src),
+            (2, 27, "lights".to_string(), "unknown field".to_string())
+        );
+        let src = with_objects(
+            r#"{"type": "sphere", "center": [0, 0, 0], "radius": 1, "color": [1, 0, 0], "material": "m"}"#,
+        );
+        let (line, _, field, message) = field_error(&src);
+        assert_eq!((line, field.as_str()), (4, "objects[0].color"));
+        assert_eq!(message, "unknown field");
+    }
+
+    #[test]
+    fn names_the_field_at_fault() {
+        let cases = [
+            (
+                r#"{"type": "sphere", "center": [0, 0], "radius": 1, "material": "m"}"#,
+                "objects[0].center",
+                "expected 3 numbers, found 2",
+            ),
+            (
+                r#"{"type": "sphere", "center": [0, 0, 0], "radius": "1", "material": "m"}"#,
+                "objects[0].radius",
+                "expected number, found string",
+            ),
+            (
+                r#"{"type": "sphere", "center": [0, 0, 0], "material": "m"}"#,
+                "objects[0].radius",
+                "missing field",
+            ),
+            (
+                r#"{"type": "sphere", "center": [0, 0, 0], "radius": 1, "material": "gold"}"#,
+                "objects[0].material",
+                "unknown material `gold`",
+            ),
+            (
+                r#"{"type": "teapot"}"#,
+                "objects[0].type",
+                "unknown object type `teapot`",
+            ),
+            (
+                r#"{"type": "cone", "radius": 1, "height": 1, "z": [0, 2], "material": "m"}"#,
+                "objects[0].z",
+                "heights must differ and lie between 0 and `height`",
+            ),
+            (
+                r#"{"type": "transform", "matrix": [[1, 0, 0, 0], [0, 1, 0, 0], [0, 0, 1, 0], [0, 0, 0, 2]],
+                    "object": {"type": "sphere", "center": [0, 0, 0], "radius": 1, "material": "m"}}"#,
+                "objects[0].matrix[3]",
+                "the bottom row must be [0, 0, 0, 1]",
+            ),
+        ];
+        for (object, expected_field, expected_message) in cases {
+            let (_, _, field, message) = field_error(&with_objects(object));
+            assert_eq!(
+                (field.as_str(), message.as_str()),
+                (expected_field, expected_message)
+            );
+        }
+    }
+
+    #[test]
+    fn loads_every_texture_and_material() {
+        let mut png = Vec::new();
+        image::RgbImage::new(2, 2)
+            .write_to(&mut io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        let dir = directory("textures", &[("tex.png", &png)]);
+        let src = r#"{
+  "camera": {"lookfrom": [0, 0, 5], "lookat": [0, 0, 0]},
+  "textures": {
+    "solid": {"type": "solid", "color": [1, 0, 0]},
+    "checker": {"type": "checker", "even": "solid", "odd": [0, 0, 1]},
+    "noise": {"type": "noise", "scale": 4},
+    "image": {"type": "image", "file": "tex.png"}
+  },
+  "materials": {
+    "lambertian": {"type": "lambertian", "albedo": "checker"},
+    "metal": {"type": "metal", "albedo": [0.8, 0.8, 0.8], "fuzz": 0.1},
+    "dielectric": {"type": "dielectric", "ir": 1.5},
+    "light": {"type": "diffuse_light", "emit": "image"},
+    "isotropic": {"type": "isotropic", "albedo": "noise"}
+  },
+  "objects": [
+    {"type": "sphere", "center": [0, 0, 0], "radius": 1, "material": "lambertian"},
+    {"type": "sphere", "center": [2, 0, 0], "radius": 1, "material": "metal"},
+    {"type": "sphere", "center": [4, 0, 0], "radius": 1, "material": "dielectric"},
+    {"type": "sphere", "center": [6, 0, 0], "radius": 1, "material": "light"},
+    {"type": "sphere", "center": [8, 0, 0], "radius": 1, "material": "isotropic"}
+  ]
+}"#;
+        let scene = scene(src, &dir).unwrap_or_else(|e| panic!("{}", e));
+        assert_eq!(scene.world.objects.len(), 5);
+        let mut lights = HittableList::new();
+        scene.world.collect_lights(&mut lights);
+        assert_eq!(lights.objects.len(), 1);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn loads_every_object_kind() {
+        let triangle: Vec<u8> = [0f32, 0f32, 0f32, 1f32, 0f32, 0f32, 0f32, 1f32, 0f32]
+            .iter()
+            .flat_map(|c| c.to_le_bytes())
+            .collect();
+        let mut stl = vec![0u8; 80];
+        stl.extend(1u32.to_le_bytes());
+        stl.extend([0u8; 12]);
+        stl.extend(&triangle);
+        stl.extend([0u8; 2]);
+        let gltf = r#"{
+  "asset": {"version": "2.0"},
+  "buffers": [{"uri": "tiny.bin", "byteLength": 36}],
+  "bufferViews": [{"buffer": 0, "byteLength": 36}],
+  "accessors": [{"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3"}],
+  "meshes": [{"primitives": [{"attributes": {"POSITION": 0}}]}],
+  "nodes": [{"mesh": 0}]
+}"#;
+        let mut height = Vec::new();
+        image::GrayImage::from_raw(2, 2, vec![0, 64, 128, 255])
+            .unwrap()
+            .write_to(&mut io::Cursor::new(&mut height), image::ImageFormat::Png)
+            .unwrap();
+        let dir = directory(
+            "objects",
+            &[
+                ("tiny.obj", b"v 0 0 0\nv 1 0 0\nv 0 1 0\nusemtl missing\nf 1 2 3\n"),
+                (
+                    "tiny.ply",
+                    b"ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\nproperty float y\nproperty float z\nelement face 1\nproperty list uchar int vertex_indices\nend_header\n0 0 0\n1 0 0\n0 1 0\n3 0 1 2\n",
+                ),
+                ("tiny.stl", &stl),
+                ("tiny.gltf", gltf.as_bytes()),
+                ("tiny.bin", &triangle),
+                ("height.png", &height),
+            ],
+        );
+
+        let sphere = r#"{"type": "sphere", "center": [0, 0, 0], "radius": 1, "material": "m"}"#;
+        let objects = [
+            sphere.to_string(),
+            r#"{"type": "moving_sphere", "center0": [0, 0, 0], "center1": [1, 0, 0], "time": [0, 1], "radius": 1, "material": "m"}"#.to_string(),
+            r#"{"type": "xy_rect", "x": [0, 1], "y": [0, 1], "k": 0, "material": "m"}"#.to_string(),
+            r#"{"type": "zx_rect", "x": [0, 1], "z": [0, 1], "k": 0, "material": "m"}"#.to_string(),
+            r#"{"type": "yz_rect", "y": [0, 1], "z": [0, 1], "k": 0, "material": "m"}"#.to_string(),
+            r#"{"type": "quad", "q": [0, 0, 0], "u": [1, 0, 0], "v": [0, 1, 1], "material": "m"}"#.to_string(),
+            r#"{"type": "cylinder", "radius": 1, "z": [0, 2], "phi_max": 270, "material": "m"}"#.to_string(),
+            r#"{"type": "cone", "radius": 1, "height": 2, "z": [0.5, 1.5], "material": "m"}"#.to_string(),
+            r#"{"type": "disk", "radius": 1, "height": 1, "material": "m"}"#.to_string(),
+            r#"{"type": "annulus", "inner_radius": 0.5, "radius": 1, "material": "m"}"#.to_string(),
+            r#"{"type": "paraboloid", "radius": 1, "z": [0, 1], "material": "m"}"#.to_string(),
+            r#"{"type": "torus", "major_radius": 1, "minor_radius": 0.25, "theta": [-90, 90], "material": "m"}"#.to_string(),
+            r#"{"type": "cube", "min": [0, 0, 0], "max": [1, 1, 1], "material": "m"}"#.to_string(),
+            r#"{"type": "box", "corner": [0, 0, 0], "edges": [[1, 1, 0], [-1, 1, 0], [0, 0, 1]], "material": "m"}"#.to_string(),
+            r#"{"type": "heightfield", "noise": {"resolution": [4, 4]}, "corner": [0, 0, 0], "size": [1, 1, 1], "material": "m"}"#.to_string(),
+            r#"{"type": "heightfield", "file": "height.png", "corner": [0, 0, 0], "size": [1, 1, 1], "material": "m"}"#.to_string(),
+            r#"{"type": "sdf", "shape": {"type": "twist", "rate": 30, "object": {"type": "repeat", "spacing": [3, 0, 0], "copies": [2, 1, 1], "object": {"type": "smooth_union", "smoothness": 0.1, "objects": [
+                {"type": "box", "half_extents": [1, 1, 1]},
+                {"type": "subtraction", "objects": [{"type": "rounded_box", "half_extents": [1, 1, 1], "radius": 0.2}, {"type": "capsule", "a": [0, 0, 0], "b": [0, 1, 0], "radius": 0.5}]},
+                {"type": "torus", "major_radius": 1, "minor_radius": 0.2}
+            ]}}}, "material": "m"}"#.to_string(),
+            r#"{"type": "triangle", "vertices": [[0, 0, 0], [1, 0, 0], [0, 1, 0]], "material": "m"}"#.to_string(),
+            r#"{"type": "mesh", "positions": [[0, 0, 0], [1, 0, 0], [0, 1, 0]], "uvs": [[0, 0], [1, 0], [0, 1]], "indices": [[0, 1, 2]], "material": "m"}"#.to_string(),
+            r#"{"type": "obj", "file": "tiny.obj"}"#.to_string(),
+            r#"{"type": "ply", "file": "tiny.ply", "material": "m"}"#.to_string(),
+            r#"{"type": "stl", "file": "tiny.stl"}"#.to_string(),
+            r#"{"type": "gltf", "file": "tiny.gltf"}"#.to_string(),
+            format!(r#"{{"type": "constant_medium", "boundary": {}, "density": 0.5, "albedo": [1, 1, 1]}}"#, sphere),
+            format!(r#"{{"type": "transform", "scale": 2, "rotate": [0, 45, 0], "translate": [1, 0, 0], "object": {}}}"#, sphere),
+            format!(r#"{{"type": "transform", "keyframes": [{{"time": 0}}, {{"time": 1, "translate": [1, 0, 0]}}], "object": {}}}"#, sphere),
+            format!(r#"{{"type": "translate", "offset": [1, 0, 0], "object": {}}}"#, sphere),
+            format!(r#"{{"type": "rotate_y", "angle": 30, "object": {}}}"#, sphere),
+            format!(r#"{{"type": "instances", "object": {}, "transforms": [{{"translate": [3, 0, 0]}}], "scatter": {{"count": 2, "min": [0, 0, 0], "max": [5, 0, 5]}}}}"#, sphere),
+            format!(r#"{{"type": "list", "objects": [{}]}}"#, sphere),
+            format!(r#"{{"type": "bvh", "objects": [{}, {}]}}"#, sphere, sphere),
+            format!(r#"{{"type": "union", "objects": [{}, {}]}}"#, sphere, sphere),
+            format!(r#"{{"type": "intersection", "objects": [{}, {}]}}"#, sphere, sphere),
+            format!(r#"{{"type": "difference", "objects": [{}, {}]}}"#, sphere, sphere),
+        ];
+        for object in &objects {
+            let scene =
+                scene(&with_objects(object), &dir).unwrap_or_else(|e| panic!("{}: {}", object, e));
+            let mut abox = Aabb::default();
+            assert!(
+                scene.world.objects[0].bounding_box([0f64, 1f64], &mut abox),
+                "{} has no bounding box",
+                object
+            );
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn shares_named_shapes() {
+        let src = r#"{
+  "camera": {"lookfrom": [0, 0, 5], "lookat": [0, 0, 0]},
+  "materials": {"m": {"type": "lambertian", "albedo": [0.5, 0.5, 0.5]}},
+  "shapes": {"ball": {"type": "sphere", "center": [0, 0, 0], "radius": 1, "material": "m"}},
+  "objects": ["ball", {"type": "translate", "offset": [3, 0, 0], "object": "ball"}]
+}"#;
+        let scene = scene(src, Path::new(".")).unwrap_or_else(|e| panic!("{}", e));
+        assert_eq!(scene.world.objects.len(), 2);
+    }
+
+    #[test]
+    fn loads_the_example_scenes() {
+        for entry in fs::read_dir("scenes").unwrap() {
+            let path = entry.unwrap().path();
+            if let Err(e) = load(&path, BvhOptions::default()) {
+                panic!("{}: {}", path.display(), e);
+            }
+        }
+    }
+}
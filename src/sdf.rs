@@ -0,0 +1,401 @@
+use std::sync::Arc;
+
+use crate::{
+    aabb::Aabb,
+    hittable::{HitRecord, Hittable},
+    material::Scatter,
+    ray::Ray,
+    sampler::Sampler,
+    vec3::{dot, Point3, Vec3},
+};
+
+// Steps after which a ray that has not reached the surface counts as a miss.
+const MAX_STEPS: usize = 512;
+// The hit tolerance, as a fraction of the bounding box diagonal.
+const SURFACE_EPSILON: f64 = 1e-6;
+
+// A signed distance field: negative inside, positive outside, and never
+// more than the distance to the surface, so sphere tracing can step by it.
+// The surface must lie within `bounds`.
+pub trait Sdf: Send + Sync {
+    fn distance(&self, p: &Point3) -> f64;
+    fn bounds(&self) -> Aabb;
+}
+
+// Renders the zero set of a distance field by sphere tracing, with normals
+// from central differences of the field. UVs are not defined.
+pub struct SdfShape {
+    sdf: Arc<dyn Sdf>,
+    abox: Aabb,
+    epsilon: f64,
+    mat_ptr: Arc<dyn Scatter>,
+}
+
+impl SdfShape {
+    pub fn new(sdf: Arc<dyn Sdf>, mat_ptr: Arc<dyn Scatter>) -> Self {
+        let bounds = sdf.bounds();
+        let diagonal = (bounds.max - bounds.min).modulus();
+        let epsilon = SURFACE_EPSILON * diagonal;
+        // Surfaces may touch their bounds, as a box's faces do.
+        let pad = Vec3::new(1f64, 1f64, 1f64) * (100f64 * epsilon);
+        Self {
+            sdf,
+            abox: Aabb::new(bounds.min - pad, bounds.max + pad),
+            epsilon,
+            mat_ptr,
+        }
+    }
+
+    fn normal(&self, p: &Point3) -> Vec3 {
+        let h = 10f64 * self.epsilon;
+        let mut n = Vec3::default();
+        for c in 0..3 {
+            let mut offset = Vec3::default();
+            offset.e[c] = h;
+            n.e[c] = self.sdf.distance(&(*p + offset)) - self.sdf.distance(&(*p - offset));
+        }
+        n.unit()
+    }
+}
+
+impl Hittable for SdfShape {
+    fn hit(
+        &self,
+        r: &Ray,
+        t_min: f64,
+        t_max: f64,
+        rec: &mut HitRecord,
+        _sampler: &mut dyn Sampler,
+    ) -> bool {
+        let (mut t, t_end) = match self.abox.clip(r, t_min, t_max) {
+            Some(span) => span,
+            None => return false,
+        };
+        let length = r.direction().modulus();
+        // Rays that start inside march out to the surface the same way,
+        // with the field's sign flipped. The side is taken from the first
+        // sample clear of the surface, so rays leaving it do not hit it again.
+        let mut side = 0f64;
+        for _ in 0..MAX_STEPS {
+            if t > t_end {
+                return false;
+            }
+            let d = self.sdf.distance(&r.at(t));
+            if side == 0f64 {
+                if d.abs() < self.epsilon {
+                    t += self.epsilon / length;
+                    continue;
+                }
+                side = d.signum();
+            }
+            let d = d * side;
+            if d < self.epsilon {
+                rec.t = t;
+                rec.point = r.at(t);
+                (rec.u, rec.v) = (0f64, 0f64);
+                rec.set_face_normal(r, &self.normal(&rec.point));
+                rec.mat_ptr = Some(self.mat_ptr.clone());
+                return true;
+            }
+            t += d / length;
+        }
+        false
+    }
+
+    fn bounding_box(&self, _time: [f64; 2], output_box: &mut Aabb) -> bool {
+        *output_box = self.abox.clone();
+        true
+    }
+}
+
+fn max_component(v: &Vec3) -> f64 {
+    v.x().max(v.y()).max(v.z())
+}
+
+fn abs(v: &Vec3) -> Vec3 {
+    Vec3::new(v.x().abs(), v.y().abs(), v.z().abs())
+}
+
+fn max(v: &Vec3, m: f64) -> Vec3 {
+    Vec3::new(v.x().max(m), v.y().max(m), v.z().max(m))
+}
+
+fn box_distance(p: &Point3, half: &Vec3) -> f64 {
+    let q = abs(p) - *half;
+    max(&q, 0f64).modulus() + max_component(&q).min(0f64)
+}
+
+pub struct SdfBox {
+    center: Point3,
+    half: Vec3,
+}
+
+impl SdfBox {
+    pub fn new(center: Point3, half_extents: Vec3) -> Self {
+        Self {
+            center,
+            half: half_extents,
+        }
+    }
+}
+
+impl Sdf for SdfBox {
+    fn distance(&self, p: &Point3) -> f64 {
+        box_distance(&(*p - self.center), &self.half)
+    }
+
+    fn bounds(&self) -> Aabb {
+        Aabb::new(self.center - self.half, self.center + self.half)
+    }
+}
+
+// A box with its edges rounded off to `radius`, which must not exceed any
+// half extent.
+pub struct RoundedBox {
+    center: Point3,
+    half: Vec3,
+    radius: f64,
+}
+
+impl RoundedBox {
+    pub fn new(center: Point3, half_extents: Vec3, radius: f64) -> Self {
+        Self {
+            center,
+            half: half_extents,
+            radius,
+        }
+    }
+}
+
+impl Sdf for RoundedBox {
+    fn distance(&self, p: &Point3) -> f64 {
+        let inner = self.half - Vec3::new(self.radius, self.radius, self.radius);
+        box_distance(&(*p - self.center), &inner) - self.radius
+    }
+
+    fn bounds(&self) -> Aabb {
+        Aabb::new(self.center - self.half, self.center + self.half)
+    }
+}
+
+// A torus about an axis parallel to z, like `quadric::Torus`.
+pub struct SdfTorus {
+    center: Point3,
+    major: f64,
+    minor: f64,
+}
+
+impl SdfTorus {
+    pub fn new(center: Point3, major: f64, minor: f64) -> Self {
+        Self {
+            center,
+            major,
+            minor,
+        }
+    }
+}
+
+impl Sdf for SdfTorus {
+    fn distance(&self, p: &Point3) -> f64 {
+        let p = *p - self.center;
+        let ring = (p.x() * p.x() + p.y() * p.y()).sqrt() - self.major;
+        (ring * ring + p.z() * p.z()).sqrt() - self.minor
+    }
+
+    fn bounds(&self) -> Aabb {
+        let r = self.major + self.minor;
+        let extent = Vec3::new(r, r, self.minor);
+        Aabb::new(self.center - extent, self.center + extent)
+    }
+}
+
+// The points within `radius` of the segment from `a` to `b`.
+pub struct Capsule {
+    a: Point3,
+    b: Point3,
+    radius: f64,
+}
+
+impl Capsule {
+    pub fn new(a: Point3, b: Point3, radius: f64) -> Self {
+        Self { a, b, radius }
+    }
+}
+
+impl Sdf for Capsule {
+    fn distance(&self, p: &Point3) -> f64 {
+        let pa = *p - self.a;
+        let ba = self.b - self.a;
+        let h = match ba.modsq() {
+            len if len > 0f64 => (dot(&pa, &ba) / len).clamp(0f64, 1f64),
+            _ => 0f64,
+        };
+        (pa - ba * h).modulus() - self.radius
+    }
+
+    fn bounds(&self) -> Aabb {
+        let r = Vec3::new(self.radius, self.radius, self.radius);
+        Aabb::surrounding_box(
+            &Aabb::new(self.a - r, self.a + r),
+            &Aabb::new(self.b - r, self.b + r),
+        )
+    }
+}
+
+// The union of two fields, blended over a distance of about `k` where they
+// meet. `k` of zero is a plain union.
+pub struct SmoothUnion {
+    a: Arc<dyn Sdf>,
+    b: Arc<dyn Sdf>,
+    k: f64,
+}
+
+impl SmoothUnion {
+    pub fn new(a: Arc<dyn Sdf>, b: Arc<dyn Sdf>, k: f64) -> Self {
+        Self { a, b, k }
+    }
+}
+
+impl Sdf for SmoothUnion {
+    fn distance(&self, p: &Point3) -> f64 {
+        let (a, b) = (self.a.distance(p), self.b.distance(p));
+        if self.k <= 0f64 {
+            return a.min(b);
+        }
+        let h = (0.5 + 0.5 * (b - a) / self.k).clamp(0f64, 1f64);
+        b + (a - b) * h - self.k * h * (1f64 - h)
+    }
+
+    // The blend bulges out by at most k / 4.
+    fn bounds(&self) -> Aabb {
+        let pad = Vec3::new(1f64, 1f64, 1f64) * (self.k.max(0f64) / 4f64);
+        let abox = Aabb::surrounding_box(&self.a.bounds(), &self.b.bounds());
+        Aabb::new(abox.min - pad, abox.max + pad)
+    }
+}
+
+// `a` with `b` carved out of it, the edge rounded over about `k`.
+pub struct Subtraction {
+    a: Arc<dyn Sdf>,
+    b: Arc<dyn Sdf>,
+    k: f64,
+}
+
+impl Subtraction {
+    pub fn new(a: Arc<dyn Sdf>, b: Arc<dyn Sdf>, k: f64) -> Self {
+        Self { a, b, k }
+    }
+}
+
+impl Sdf for Subtraction {
+    fn distance(&self, p: &Point3) -> f64 {
+        let (a, b) = (self.a.distance(p), -self.b.distance(p));
+        if self.k <= 0f64 {
+            return a.max(b);
+        }
+        let h = (0.5 - 0.5 * (b - a) / self.k).clamp(0f64, 1f64);
+        b + (a - b) * h + self.k * h * (1f64 - h)
+    }
+
+    fn bounds(&self) -> Aabb {
+        self.a.bounds()
+    }
+}
+
+// `copies[i]` copies of a field along each axis, at least one, `spacing`
+// apart and centered on the original. The copies must not overlap for the
+// distance to stay a bound.
+pub struct Repeat {
+    sdf: Arc<dyn Sdf>,
+    spacing: Vec3,
+    copies: [usize; 3],
+    // The middle of the original, which picks the nearest copy.
+    center: Point3,
+}
+
+impl Repeat {
+    pub fn new(sdf: Arc<dyn Sdf>, spacing: Vec3, copies: [usize; 3]) -> Self {
+        let abox = sdf.bounds();
+        Self {
+            sdf,
+            spacing,
+            copies,
+            center: (abox.min + abox.max) * 0.5,
+        }
+    }
+
+    fn offset(&self) -> Vec3 {
+        let mut offset = Vec3::default();
+        for c in 0..3 {
+            offset.e[c] = 0.5 * self.spacing.e[c] * (self.copies[c] - 1) as f64;
+        }
+        offset
+    }
+}
+
+impl Sdf for Repeat {
+    fn distance(&self, p: &Point3) -> f64 {
+        // Number the copies 0, 1, 2, ... from the first and move `p` back
+        // by the nearest one's displacement from the original.
+        let offset = self.offset();
+        let mut q = *p;
+        for c in 0..3 {
+            if self.copies[c] > 1 {
+                let last = (self.copies[c] - 1) as f64;
+                let from_first = p.e[c] - self.center.e[c] + offset.e[c];
+                let id = (from_first / self.spacing.e[c]).round().clamp(0f64, last);
+                q.e[c] -= id * self.spacing.e[c] - offset.e[c];
+            }
+        }
+        self.sdf.distance(&q)
+    }
+
+    fn bounds(&self) -> Aabb {
+        let abox = self.sdf.bounds();
+        let offset = self.offset();
+        Aabb::new(abox.min - offset, abox.max + offset)
+    }
+}
+
+// Twists a field about the z axis by `rate` degrees per unit of height.
+pub struct Twist {
+    sdf: Arc<dyn Sdf>,
+    rate: f64,
+    // Keeps the distance a bound despite the shear.
+    lipschitz: f64,
+    abox: Aabb,
+}
+
+impl Twist {
+    pub fn new(sdf: Arc<dyn Sdf>, rate: f64) -> Self {
+        let rate = rate.to_radians();
+        let inner = sdf.bounds();
+        let mut radius = 0f64;
+        for x in [inner.min.x(), inner.max.x()] {
+            for y in [inner.min.y(), inner.max.y()] {
+                radius = radius.max((x * x + y * y).sqrt());
+            }
+        }
+        Self {
+            sdf,
+            rate,
+            lipschitz: (1f64 + rate * rate * radius * radius).sqrt(),
+            abox: Aabb::new(
+                Point3::new(-radius, -radius, inner.min.z()),
+                Point3::new(radius, radius, inner.max.z()),
+            ),
+        }
+    }
+}
+
+impl Sdf for Twist {
+    fn distance(&self, p: &Point3) -> f64 {
+        let (sin, cos) = (self.rate * p.z()).sin_cos();
+        let q = Point3::new(cos * p.x() + sin * p.y(), cos * p.y() - sin * p.x(), p.z());
+        self.sdf.distance(&q) / self.lipschitz
+    }
+
+    fn bounds(&self) -> Aabb {
+        self.abox.clone()
+    }
+}
@@ -1,21 +1,23 @@
-use std::{f64::consts::PI, rc::Rc};
+use std::{f64::consts::PI, sync::Arc};
 
 use crate::{
     aabb::Aabb,
     hittable::{HitRecord, Hittable},
     material::Scatter,
+    onb::Onb,
     ray::Ray,
-    vec3::{dot, Point3, Vec3},
+    sampler::Sampler,
+    vec3::{dot, random_unit_sphere, Point3, Vec3},
 };
 
 pub struct Sphere {
     center: Point3,
     radius: f64,
-    mat_ptr: Rc<dyn Scatter>,
+    mat_ptr: Arc<dyn Scatter>,
 }
 
 impl Sphere {
-    pub fn new(center: Point3, radius: f64, mat_ptr: Rc<dyn Scatter>) -> Self {
+    pub fn new(center: Point3, radius: f64, mat_ptr: Arc<dyn Scatter>) -> Self {
         Self {
             center,
             radius,
@@ -30,7 +32,14 @@ impl Sphere {
 }
 
 impl Hittable for Sphere {
-    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+    fn hit(
+        &self,
+        r: &Ray,
+        t_min: f64,
+        t_max: f64,
+        rec: &mut HitRecord,
+        _sampler: &mut dyn Sampler,
+    ) -> bool {
         let oc = r.origin() - self.center;
         let a = r.direction().modsq();
         let half_b = dot(&oc, &r.direction());
@@ -64,21 +73,46 @@ impl Hittable for Sphere {
     fn bounding_box(&self, _time: [f64; 2], output_box: &mut Aabb) -> bool {
         *output_box = Aabb::new(
             self.center - Vec3::new(self.radius, self.radius, self.radius),
-            self.center - Vec3::new(self.radius, self.radius, self.radius),
+            self.center + Vec3::new(self.radius, self.radius, self.radius),
         );
         true
     }
+    fn is_emissive(&self) -> bool {
+        self.mat_ptr.is_emissive()
+    }
+    fn pdf_value(
+        &self,
+        origin: &Point3,
+        direction: &Vec3,
+        time: f64,
+        sampler: &mut dyn Sampler,
+    ) -> f64 {
+        let mut rec = HitRecord::default();
+        let r = Ray::new(*origin, *direction, time);
+        if !self.hit(&r, 0.001, f64::INFINITY, &mut rec, sampler) {
+            return 0f64;
+        }
+        1f64 / cone_solid_angle(&self.center, self.radius, origin)
+    }
+    fn random(&self, origin: &Point3, _time: f64, sampler: &mut dyn Sampler) -> Vec3 {
+        random_in_cone(&self.center, self.radius, origin, sampler)
+    }
 }
 
 pub struct MovingSphere {
     center: [Point3; 2],
     time: [f64; 2],
     radius: f64,
-    mat_ptr: Rc<dyn Scatter>,
+    mat_ptr: Arc<dyn Scatter>,
 }
 
 impl MovingSphere {
-    pub fn new(center: [Point3; 2], time: [f64; 2], radius: f64, mat_ptr: Rc<dyn Scatter>) -> Self {
+    pub fn new(
+        center: [Point3; 2],
+        time: [f64; 2],
+        radius: f64,
+        mat_ptr: Arc<dyn Scatter>,
+    ) -> Self {
         Self {
             center,
             time,
@@ -94,7 +128,14 @@ impl MovingSphere {
 }
 
 impl Hittable for MovingSphere {
-    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+    fn hit(
+        &self,
+        r: &Ray,
+        t_min: f64,
+        t_max: f64,
+        rec: &mut HitRecord,
+        _sampler: &mut dyn Sampler,
+    ) -> bool {
         let oc = r.origin() - self.center(r.time());
         let a = r.direction().modsq();
         let half_b = dot(&oc, &r.direction());
@@ -136,4 +177,55 @@ impl Hittable for MovingSphere {
         *output_box = Aabb::surrounding_box(&box0, &box1);
         true
     }
+    fn is_emissive(&self) -> bool {
+        self.mat_ptr.is_emissive()
+    }
+    fn pdf_value(
+        &self,
+        origin: &Point3,
+        direction: &Vec3,
+        time: f64,
+        sampler: &mut dyn Sampler,
+    ) -> f64 {
+        let mut rec = HitRecord::default();
+        let r = Ray::new(*origin, *direction, time);
+        if !self.hit(&r, 0.001, f64::INFINITY, &mut rec, sampler) {
+            return 0f64;
+        }
+        1f64 / cone_solid_angle(&self.center(time), self.radius, origin)
+    }
+    fn random(&self, origin: &Point3, time: f64, sampler: &mut dyn Sampler) -> Vec3 {
+        random_in_cone(&self.center(time), self.radius, origin, sampler)
+    }
+}
+
+// The solid angle the sphere subtends from `origin`; from inside, every
+// direction hits it.
+fn cone_solid_angle(center: &Point3, radius: f64, origin: &Point3) -> f64 {
+    let distance_squared = (*center - *origin).modsq();
+    if distance_squared <= radius * radius {
+        return 4f64 * PI;
+    }
+    let cos_theta_max = (1f64 - radius * radius / distance_squared).sqrt();
+    2f64 * PI * (1f64 - cos_theta_max)
+}
+
+// Uniform over the cone of directions from `origin` that hit the sphere.
+fn random_in_cone(
+    center: &Point3,
+    radius: f64,
+    origin: &Point3,
+    sampler: &mut dyn Sampler,
+) -> Vec3 {
+    let direction = *center - *origin;
+    let distance_squared = direction.modsq();
+    if distance_squared <= radius * radius {
+        return random_unit_sphere(sampler);
+    }
+    let cos_theta_max = (1f64 - radius * radius / distance_squared).sqrt();
+    let (a, b) = sampler.next_2d();
+    let z = 1f64 + a * (cos_theta_max - 1f64);
+    let r = (1f64 - z * z).max(0f64).sqrt();
+    let phi = 2f64 * PI * b;
+    Onb::from_w(&direction).local(r * phi.cos(), r * phi.sin(), z)
 }
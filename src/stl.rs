@@ -0,0 +1,126 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use crate::{
+    import::{ImportError, MeshData},
+    vec3::Point3,
+};
+
+const HEADER: usize = 80;
+const RECORD: usize = 50;
+
+// Reads a binary STL file: an 80-byte header, a triangle count and one
+// 50-byte record per triangle. Corners with identical coordinates become
+// one shared vertex; the stored facet normals are ignored because the
+// winding already gives them.
+pub fn read(path: &Path) -> Result<MeshData, ImportError> {
+    let data = fs::read(path).map_err(|e| ImportError::Io(path.to_path_buf(), e))?;
+    let count = data
+        .get(HEADER..HEADER + 4)
+        .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()) as usize);
+    let expected = count.map(|count| HEADER + 4 + RECORD * count);
+    if expected != Some(data.len()) {
+        // ASCII files start with `solid`, but so may a binary header, so
+        // only a size mismatch tells them apart.
+        let message = match (count, expected) {
+            _ if data.starts_with(b"solid") => "ASCII STL is not supported".to_string(),
+            (Some(count), Some(expected)) => format!(
+                "header promises {} triangles ({} bytes) but the file has {} bytes",
+                count,
+                expected,
+                data.len()
+            ),
+            _ => "file is too short for an STL header".to_string(),
+        };
+        return Err(ImportError::format(path, message));
+    }
+    let count = count.unwrap_or_default();
+    if count == 0 {
+        return Err(ImportError::format(path, "file has no faces"));
+    }
+
+    let mut mesh = MeshData::default();
+    let mut vertices: HashMap<[u32; 3], usize> = HashMap::new();
+    for record in data[HEADER + 4..].chunks_exact(RECORD) {
+        let mut face = [0usize; 3];
+        for (corner, index) in face.iter_mut().enumerate() {
+            // The facet normal takes the first 12 bytes.
+            let start = 12 * (corner + 1);
+            let bits: [u32; 3] = std::array::from_fn(|c| {
+                let at = start + 4 * c;
+                u32::from_le_bytes(record[at..at + 4].try_into().unwrap())
+            });
+            *index = *vertices.entry(bits).or_insert_with(|| {
+                let [x, y, z] = bits.map(|b| f32::from_bits(b) as f64);
+                mesh.positions.push(Point3::new(x, y, z));
+                mesh.positions.len() - 1
+            });
+        }
+        mesh.indices.push(face);
+    }
+    Ok(mesh)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_bytes(name: &str, bytes: &[u8]) -> Result<MeshData, ImportError> {
+        let path = std::env::temp_dir().join(format!("rtw-{}-{}.stl", name, std::process::id()));
+        fs::write(&path, bytes).unwrap();
+        let mesh = read(&path);
+        fs::remove_file(&path).unwrap();
+        mesh
+    }
+
+    fn message(result: Result<MeshData, ImportError>) -> String {
+        match result {
+            Err(ImportError::Format { message, .. }) => message,
+            Err(e) => panic!("expected a format error, found `{}`", e),
+            Ok(_) => panic!("expected a format error"),
+        }
+    }
+
+    fn stl(triangles: &[[[f32; 3]; 3]]) -> Vec<u8> {
+        let mut data = vec![0u8; HEADER];
+        data.extend((triangles.len() as u32).to_le_bytes());
+        for triangle in triangles {
+            data.extend([0u8; 12]);
+            for c in triangle.iter().flatten() {
+                data.extend(c.to_le_bytes());
+            }
+            data.extend([0u8; 2]);
+        }
+        data
+    }
+
+    #[test]
+    fn shares_identical_corners() {
+        let data = stl(&[
+            [[0f32, 0f32, 0f32], [1f32, 0f32, 0f32], [1f32, 1f32, 0f32]],
+            [[0f32, 0f32, 0f32], [1f32, 1f32, 0f32], [0f32, 1f32, 0f32]],
+        ]);
+        let mesh = read_bytes("square", &data).unwrap();
+        assert_eq!(mesh.positions.len(), 4);
+        assert_eq!(mesh.indices, vec![[0, 1, 2], [0, 2, 3]]);
+        assert_eq!(mesh.positions[3].y(), 1f64);
+    }
+
+    #[test]
+    fn reports_bad_files() {
+        let mut data = stl(&[[[0f32; 3]; 3]]);
+        data.pop();
+        assert_eq!(
+            message(read_bytes("short", &data)),
+            "header promises 1 triangles (134 bytes) but the file has 133 bytes"
+        );
+        assert_eq!(
+            message(read_bytes("ascii", b"solid cube\nendsolid cube\n")),
+            "ASCII STL is not supported"
+        );
+        assert_eq!(message(read_bytes("empty", &stl(&[]))), "file has no faces");
+        assert_eq!(
+            message(read_bytes("tiny", &[0u8; 10])),
+            "file is too short for an STL header"
+        );
+    }
+}
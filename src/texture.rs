@@ -1,6 +1,6 @@
-use std::{path::Path, rc::Rc};
+use std::{path::Path, sync::Arc};
 
-use image::{ImageBuffer, Rgb};
+use image::{ImageBuffer, ImageResult, Rgb};
 
 use crate::{
     perlin::Perlin,
@@ -8,7 +8,7 @@ use crate::{
     vec3::{Color, Vec3},
 };
 
-pub trait Texture {
+pub trait Texture: Send + Sync {
     fn value(&self, u: f64, v: f64, p: &Vec3) -> Color;
 }
 
@@ -35,19 +35,19 @@ impl Texture for SolidColor {
 }
 
 pub struct CheckerTexture {
-    odd: Rc<dyn Texture>,
-    even: Rc<dyn Texture>,
+    odd: Arc<dyn Texture>,
+    even: Arc<dyn Texture>,
 }
 
 impl CheckerTexture {
-    pub fn new(even: Rc<dyn Texture>, odd: Rc<dyn Texture>) -> Self {
+    pub fn new(even: Arc<dyn Texture>, odd: Arc<dyn Texture>) -> Self {
         Self { odd, even }
     }
 
     pub fn from_color(c1: Color, c2: Color) -> Self {
         Self {
-            odd: Rc::new(SolidColor::from_color(c2)),
-            even: Rc::new(SolidColor::from_color(c1)),
+            odd: Arc::new(SolidColor::from_color(c2)),
+            even: Arc::new(SolidColor::from_color(c1)),
         }
     }
 }
@@ -85,13 +85,16 @@ pub struct ImageTexture {
 }
 
 impl ImageTexture {
-    pub fn new(file: &Path) -> Self {
-        let data = image::io::Reader::open(file)
-            .unwrap()
-            .decode()
-            .unwrap()
-            .into_rgb8();
-        Self { data }
+    pub fn open(file: &Path) -> ImageResult<Self> {
+        let data = image::io::Reader::open(file)?.decode()?.into_rgb8();
+        Ok(Self { data })
+    }
+
+    // Decodes an image embedded in another file, in any format `image` can
+    // guess from the bytes.
+    pub fn from_memory(bytes: &[u8]) -> ImageResult<Self> {
+        let data = image::load_from_memory(bytes)?.into_rgb8();
+        Ok(Self { data })
     }
 }
 
@@ -113,3 +116,34 @@ impl Texture for ImageTexture {
         a
     }
 }
+
+// Per-vertex colors of a mesh, interpolated across each face. Faces are
+// addressed through UVs: face `f` spans u in [2f, 2f + 1], and u - 2f and v
+// are the barycentric weights of its second and third corners.
+pub struct VertexColorTexture {
+    faces: Vec<[Color; 3]>,
+}
+
+impl VertexColorTexture {
+    pub fn new(faces: Vec<[Color; 3]>) -> Self {
+        Self { faces }
+    }
+
+    pub fn face_uvs(face: usize) -> [[f64; 2]; 3] {
+        let u = 2f64 * face as f64;
+        [[u, 0f64], [u + 1f64, 0f64], [u, 1f64]]
+    }
+}
+
+impl Texture for VertexColorTexture {
+    fn value(&self, u: f64, v: f64, _p: &Vec3) -> Color {
+        if self.faces.is_empty() {
+            return Color::default();
+        }
+        let face = ((u / 2f64).floor().max(0f64) as usize).min(self.faces.len() - 1);
+        let b1 = clamp(u - 2f64 * face as f64, 0f64, 1f64);
+        let b2 = clamp(v, 0f64, 1f64 - b1);
+        let [c0, c1, c2] = self.faces[face];
+        c0 * (1f64 - b1 - b2) + c1 * b1 + c2 * b2
+    }
+}
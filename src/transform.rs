@@ -0,0 +1,508 @@
+use std::ops::Mul;
+
+use crate::{
+    aabb::Aabb,
+    ray::Ray,
+    vec3::{Point3, Vec3},
+};
+
+// A row-major 4x4 matrix acting on column vectors, so `a * b` applies `b`
+// first.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Mat4 {
+    pub m: [[f64; 4]; 4],
+}
+
+impl Mat4 {
+    pub const IDENTITY: Mat4 = Mat4 {
+        m: [
+            [1f64, 0f64, 0f64, 0f64],
+            [0f64, 1f64, 0f64, 0f64],
+            [0f64, 0f64, 1f64, 0f64],
+            [0f64, 0f64, 0f64, 1f64],
+        ],
+    };
+
+    pub fn new(m: [[f64; 4]; 4]) -> Self {
+        Self { m }
+    }
+
+    pub fn translate(offset: &Vec3) -> Self {
+        let mut out = Self::IDENTITY;
+        for r in 0..3 {
+            out.m[r][3] = offset.e[r];
+        }
+        out
+    }
+
+    pub fn scale(factors: &Vec3) -> Self {
+        let mut out = Self::IDENTITY;
+        for r in 0..3 {
+            out.m[r][r] = factors.e[r];
+        }
+        out
+    }
+
+    pub fn rotate_x(degrees: f64) -> Self {
+        Self::rotate(&Vec3::new(1f64, 0f64, 0f64), degrees)
+    }
+
+    pub fn rotate_y(degrees: f64) -> Self {
+        Self::rotate(&Vec3::new(0f64, 1f64, 0f64), degrees)
+    }
+
+    pub fn rotate_z(degrees: f64) -> Self {
+        Self::rotate(&Vec3::new(0f64, 0f64, 1f64), degrees)
+    }
+
+    // Counter-clockwise about `axis` when looking down it (Rodrigues).
+    pub fn rotate(axis: &Vec3, degrees: f64) -> Self {
+        let a = axis.unit();
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        let (x, y, z) = (a.x(), a.y(), a.z());
+        let t = 1f64 - cos;
+        Self::new([
+            [
+                t * x * x + cos,
+                t * x * y - sin * z,
+                t * x * z + sin * y,
+                0f64,
+            ],
+            [
+                t * x * y + sin * z,
+                t * y * y + cos,
+                t * y * z - sin * x,
+                0f64,
+            ],
+            [
+                t * x * z - sin * y,
+                t * y * z + sin * x,
+                t * z * z + cos,
+                0f64,
+            ],
+            [0f64, 0f64, 0f64, 1f64],
+        ])
+    }
+
+    pub fn transpose(&self) -> Self {
+        let mut out = *self;
+        for r in 0..4 {
+            for c in 0..4 {
+                out.m[r][c] = self.m[c][r];
+            }
+        }
+        out
+    }
+
+    // Determinant of the upper-left 3x3 block, the volume scale of an affine
+    // transform.
+    pub fn determinant3(&self) -> f64 {
+        let m = &self.m;
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    }
+
+    // Gauss-Jordan elimination with partial pivoting; `None` for singular
+    // matrices.
+    pub fn inverse(&self) -> Option<Self> {
+        let mut a = self.m;
+        let mut inv = Self::IDENTITY.m;
+        for col in 0..4 {
+            let pivot = (col..4).max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))?;
+            if a[pivot][col].abs() < 1e-12 {
+                return None;
+            }
+            a.swap(col, pivot);
+            inv.swap(col, pivot);
+            let scale = 1f64 / a[col][col];
+            for c in 0..4 {
+                a[col][c] *= scale;
+                inv[col][c] *= scale;
+            }
+            for row in 0..4 {
+                if row == col {
+                    continue;
+                }
+                let factor = a[row][col];
+                for c in 0..4 {
+                    a[row][c] -= factor * a[col][c];
+                    inv[row][c] -= factor * inv[col][c];
+                }
+            }
+        }
+        Some(Self::new(inv))
+    }
+
+    // Points pick up the translation; the projective row is ignored.
+    pub fn point(&self, p: &Point3) -> Point3 {
+        self.vector(p) + Vec3::new(self.m[0][3], self.m[1][3], self.m[2][3])
+    }
+
+    pub fn vector(&self, v: &Vec3) -> Vec3 {
+        let row = |r: usize| self.m[r][0] * v.x() + self.m[r][1] * v.y() + self.m[r][2] * v.z();
+        Vec3::new(row(0), row(1), row(2))
+    }
+}
+
+impl Mul for Mat4 {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        let mut out = Self::new([[0f64; 4]; 4]);
+        for r in 0..4 {
+            for c in 0..4 {
+                out.m[r][c] = (0..4).map(|k| self.m[r][k] * rhs.m[k][c]).sum();
+            }
+        }
+        out
+    }
+}
+
+// An invertible affine transform with its inverse kept alongside, so points,
+// directions and normals can be mapped either way without re-inverting.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+    m: Mat4,
+    inv: Mat4,
+}
+
+impl Transform {
+    pub fn new(m: Mat4) -> Option<Self> {
+        Some(Self {
+            m,
+            inv: m.inverse()?,
+        })
+    }
+
+    pub fn identity() -> Self {
+        Self {
+            m: Mat4::IDENTITY,
+            inv: Mat4::IDENTITY,
+        }
+    }
+
+    pub fn matrix(&self) -> &Mat4 {
+        &self.m
+    }
+
+    pub fn inverse(&self) -> Self {
+        Self {
+            m: self.inv,
+            inv: self.m,
+        }
+    }
+
+    pub fn point(&self, p: &Point3) -> Point3 {
+        self.m.point(p)
+    }
+
+    pub fn vector(&self, v: &Vec3) -> Vec3 {
+        self.m.vector(v)
+    }
+
+    // Normals go through the inverse transpose to stay perpendicular to the
+    // transformed surface. The result is not normalized.
+    pub fn normal(&self, n: &Vec3) -> Vec3 {
+        let column = |c: usize| {
+            self.inv.m[0][c] * n.x() + self.inv.m[1][c] * n.y() + self.inv.m[2][c] * n.z()
+        };
+        Vec3::new(column(0), column(1), column(2))
+    }
+
+    // The direction is not renormalized, so hit distances along the
+    // transformed ray equal those along `r`.
+    pub fn ray(&self, r: &Ray) -> Ray {
+        Ray::new(
+            self.point(&r.origin()),
+            self.vector(&r.direction()),
+            r.time(),
+        )
+    }
+
+    // The tightest box around the transformed corners of `b` (Arvo's
+    // method). Zero entries are skipped so infinite boxes stay finite where
+    // the transform allows.
+    pub fn bbox(&self, b: &Aabb) -> Aabb {
+        let mut min = Point3::new(self.m.m[0][3], self.m.m[1][3], self.m.m[2][3]);
+        let mut max = min;
+        for r in 0..3 {
+            for c in 0..3 {
+                let a = self.m.m[r][c];
+                if a == 0f64 {
+                    continue;
+                }
+                let (lo, hi) = (a * b.min.e[c], a * b.max.e[c]);
+                min.e[r] += lo.min(hi);
+                max.e[r] += lo.max(hi);
+            }
+        }
+        Aabb::new(min, max)
+    }
+}
+
+impl Mul for Transform {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        Self {
+            m: self.m * rhs.m,
+            inv: rhs.inv * self.inv,
+        }
+    }
+}
+
+// A unit quaternion for rotations that interpolate smoothly.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Quat {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    pub w: f64,
+}
+
+impl Quat {
+    pub const IDENTITY: Quat = Quat {
+        x: 0f64,
+        y: 0f64,
+        z: 0f64,
+        w: 1f64,
+    };
+
+    // Normalizes, so glTF-style quaternions with rounding error are fine.
+    pub fn new(x: f64, y: f64, z: f64, w: f64) -> Self {
+        let norm = (x * x + y * y + z * z + w * w).sqrt();
+        if norm == 0f64 {
+            return Self::IDENTITY;
+        }
+        Self {
+            x: x / norm,
+            y: y / norm,
+            z: z / norm,
+            w: w / norm,
+        }
+    }
+
+    pub fn from_axis_angle(axis: &Vec3, degrees: f64) -> Self {
+        let a = axis.unit();
+        let (sin, cos) = (degrees.to_radians() / 2f64).sin_cos();
+        Self::new(a.x() * sin, a.y() * sin, a.z() * sin, cos)
+    }
+
+    // Degrees about x, then y, then z, like `Mat4::rotate_z(z) *
+    // Mat4::rotate_y(y) * Mat4::rotate_x(x)`.
+    pub fn from_euler(degrees: &Vec3) -> Self {
+        let axis =
+            |x: f64, y: f64, z: f64, angle: f64| Self::from_axis_angle(&Vec3::new(x, y, z), angle);
+        axis(0f64, 0f64, 1f64, degrees.z())
+            * axis(0f64, 1f64, 0f64, degrees.y())
+            * axis(1f64, 0f64, 0f64, degrees.x())
+    }
+
+    fn dot(&self, other: &Quat) -> f64 {
+        self.x * other.x + self.y * other.y + self.z * other.z + self.w * other.w
+    }
+
+    // The rotation angle between two orientations, in radians.
+    pub fn angle_to(&self, other: &Quat) -> f64 {
+        2f64 * self.dot(other).abs().min(1f64).acos()
+    }
+
+    // Constant angular speed along the shorter arc.
+    pub fn slerp(&self, other: &Quat, s: f64) -> Self {
+        let mut cos = self.dot(other);
+        let mut other = *other;
+        if cos < 0f64 {
+            cos = -cos;
+            other = Quat {
+                x: -other.x,
+                y: -other.y,
+                z: -other.z,
+                w: -other.w,
+            };
+        }
+        // Nearly parallel: the sine below vanishes, and a normalized lerp is
+        // indistinguishable.
+        let (a, b) = if cos > 0.9995 {
+            (1f64 - s, s)
+        } else {
+            let theta = cos.acos();
+            let sin = theta.sin();
+            (((1f64 - s) * theta).sin() / sin, (s * theta).sin() / sin)
+        };
+        Self::new(
+            a * self.x + b * other.x,
+            a * self.y + b * other.y,
+            a * self.z + b * other.z,
+            a * self.w + b * other.w,
+        )
+    }
+
+    pub fn to_mat4(&self) -> Mat4 {
+        let Quat { x, y, z, w } = *self;
+        Mat4::new([
+            [
+                1f64 - 2f64 * (y * y + z * z),
+                2f64 * (x * y - z * w),
+                2f64 * (x * z + y * w),
+                0f64,
+            ],
+            [
+                2f64 * (x * y + z * w),
+                1f64 - 2f64 * (x * x + z * z),
+                2f64 * (y * z - x * w),
+                0f64,
+            ],
+            [
+                2f64 * (x * z - y * w),
+                2f64 * (y * z + x * w),
+                1f64 - 2f64 * (x * x + y * y),
+                0f64,
+            ],
+            [0f64, 0f64, 0f64, 1f64],
+        ])
+    }
+}
+
+impl Mul for Quat {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        Self::new(
+            self.w * rhs.x + self.x * rhs.w + self.y * rhs.z - self.z * rhs.y,
+            self.w * rhs.y - self.x * rhs.z + self.y * rhs.w + self.z * rhs.x,
+            self.w * rhs.z + self.x * rhs.y - self.y * rhs.x + self.z * rhs.w,
+            self.w * rhs.w - self.x * rhs.x - self.y * rhs.y - self.z * rhs.z,
+        )
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Keyframe {
+    pub time: f64,
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+// Keyframes applied as scale, then rotation, then translation. Between keys
+// translation and scale are interpolated linearly and rotation by slerp;
+// before the first key and after the last the transform holds still.
+#[derive(Clone)]
+pub struct AnimatedTransform {
+    keys: Vec<Keyframe>,
+}
+
+impl AnimatedTransform {
+    // `None` without keys, or if some scale component is zero or changes
+    // sign, which would make the transform singular at some time.
+    pub fn new(mut keys: Vec<Keyframe>) -> Option<Self> {
+        keys.sort_by(|a, b| a.time.total_cmp(&b.time));
+        let first = keys.first()?.scale;
+        for key in &keys {
+            for c in 0..3 {
+                if key.scale.e[c] == 0f64 || key.scale.e[c].signum() != first.e[c].signum() {
+                    return None;
+                }
+            }
+        }
+        Some(Self { keys })
+    }
+
+    pub fn keys(&self) -> &[Keyframe] {
+        &self.keys
+    }
+
+    // The keys around `time` and how far along between them it is.
+    fn segment(&self, time: f64) -> (&Keyframe, &Keyframe, f64) {
+        let next = self.keys.partition_point(|k| k.time <= time);
+        if next == 0 {
+            return (&self.keys[0], &self.keys[0], 0f64);
+        }
+        if next == self.keys.len() {
+            let last = &self.keys[next - 1];
+            return (last, last, 0f64);
+        }
+        let (a, b) = (&self.keys[next - 1], &self.keys[next]);
+        (a, b, (time - a.time) / (b.time - a.time))
+    }
+
+    pub fn at(&self, time: f64) -> Transform {
+        let (a, b, s) = self.segment(time);
+        let translation = a.translation * (1f64 - s) + b.translation * s;
+        let rotation = a.rotation.slerp(&b.rotation, s).to_mat4();
+        let scale = a.scale * (1f64 - s) + b.scale * s;
+        let inverse_scale = Vec3::new(1f64 / scale.x(), 1f64 / scale.y(), 1f64 / scale.z());
+        Transform {
+            m: Mat4::translate(&translation) * rotation * Mat4::scale(&scale),
+            inv: Mat4::scale(&inverse_scale)
+                * rotation.transpose()
+                * Mat4::translate(&-translation),
+        }
+    }
+
+    // Bounds `b` over the whole motion in `time`. The box is transformed at
+    // every key and at steps small enough in angle that, together with a
+    // pad for how far a rotating corner can bulge from the straight line
+    // between two steps, nothing in between escapes.
+    pub fn bbox(&self, b: &Aabb, time: [f64; 2]) -> Aabb {
+        const MAX_STEP: f64 = std::f64::consts::PI / 32f64;
+        let mut times = vec![time[0]];
+        times.extend(
+            self.keys
+                .iter()
+                .map(|k| k.time)
+                .filter(|&t| t > time[0] && t < time[1]),
+        );
+        times.push(time[1]);
+
+        let corners: Vec<Vec3> = (0..8)
+            .map(|i| {
+                let pick = |c: usize| {
+                    if i & (1 << c) == 0 {
+                        b.min.e[c]
+                    } else {
+                        b.max.e[c]
+                    }
+                };
+                Vec3::new(pick(0), pick(1), pick(2))
+            })
+            .collect();
+        let mut out = self.at(time[0]).bbox(b);
+        let mut pad = 0f64;
+        for pair in times.windows(2) {
+            let (start, end) = (self.segment(pair[0]), self.segment(pair[1]));
+            let angle = start
+                .0
+                .rotation
+                .slerp(&start.1.rotation, start.2)
+                .angle_to(&end.0.rotation.slerp(&end.1.rotation, end.2));
+            let steps = (angle / MAX_STEP).ceil().max(1f64) as usize;
+            for k in 1..=steps {
+                let t = pair[0] + (pair[1] - pair[0]) * k as f64 / steps as f64;
+                out = Aabb::surrounding_box(&out, &self.at(t).bbox(b));
+            }
+            if angle == 0f64 {
+                continue;
+            }
+            // Rotation bends a corner's path off the chord by at most
+            // r (1 - cos(step / 2)), and changing scale adds at most
+            // |change| sin(step / 2) / 2 per step.
+            let step = angle / steps as f64;
+            let scale_at = |t: f64| {
+                let (a, b, s) = self.segment(t);
+                a.scale * (1f64 - s) + b.scale * s
+            };
+            let (s0, s1) = (scale_at(pair[0]), scale_at(pair[1]));
+            for p in &corners {
+                let radius = (s0 * *p).modulus().max((s1 * *p).modulus());
+                let change = ((s1 - s0) * *p).modulus() / steps as f64;
+                pad = pad.max(
+                    radius * (1f64 - (step / 2f64).cos()) + change * (step / 2f64).sin() / 2f64,
+                );
+            }
+        }
+        let pad = Vec3::new(pad, pad, pad);
+        Aabb::new(out.min - pad, out.max + pad)
+    }
+}
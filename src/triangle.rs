@@ -0,0 +1,309 @@
+use std::{error::Error, fmt, sync::Arc};
+
+use crate::{
+    aabb::Aabb,
+    hittable::{HitRecord, Hittable},
+    hittable_list::HittableList,
+    material::Scatter,
+    ray::Ray,
+    sampler::Sampler,
+    vec3::{cross, dot, Point3, Vec3},
+};
+
+pub struct Triangle {
+    p: [Point3; 3],
+    mat_ptr: Arc<dyn Scatter>,
+}
+
+impl Triangle {
+    pub fn new(p0: Point3, p1: Point3, p2: Point3, mat_ptr: Arc<dyn Scatter>) -> Self {
+        Self {
+            p: [p0, p1, p2],
+            mat_ptr,
+        }
+    }
+}
+
+impl Hittable for Triangle {
+    fn hit(
+        &self,
+        r: &Ray,
+        t_min: f64,
+        t_max: f64,
+        rec: &mut HitRecord,
+        _sampler: &mut dyn Sampler,
+    ) -> bool {
+        let (t, b1, b2) = match intersect(r, &self.p, t_min, t_max) {
+            Some(hit) => hit,
+            None => return false,
+        };
+        rec.t = t;
+        rec.point = r.at(t);
+        (rec.u, rec.v) = (b1, b2);
+        rec.set_face_normal(r, &face_normal(&self.p));
+        rec.mat_ptr = Some(self.mat_ptr.clone());
+        true
+    }
+
+    fn bounding_box(&self, _time: [f64; 2], output_box: &mut Aabb) -> bool {
+        *output_box = triangle_box(&self.p);
+        true
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.mat_ptr.is_emissive()
+    }
+
+    fn pdf_value(
+        &self,
+        origin: &Point3,
+        direction: &Vec3,
+        time: f64,
+        _sampler: &mut dyn Sampler,
+    ) -> f64 {
+        triangle_pdf_value(&self.p, origin, direction, time)
+    }
+
+    fn random(&self, origin: &Point3, _time: f64, sampler: &mut dyn Sampler) -> Vec3 {
+        random_on_triangle(&self.p, sampler) - *origin
+    }
+}
+
+#[derive(Debug)]
+pub enum MeshError {
+    // A face refers to a vertex past the end of the position list.
+    IndexOutOfRange {
+        face: usize,
+        index: usize,
+    },
+    // Normals and UVs must be absent or given once per position.
+    AttributeCount {
+        attribute: &'static str,
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl fmt::Display for MeshError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MeshError::IndexOutOfRange { face, index } => {
+                write!(
+                    f,
+                    "face {} uses vertex {}, which does not exist",
+                    face, index
+                )
+            }
+            MeshError::AttributeCount {
+                attribute,
+                expected,
+                found,
+            } => write!(
+                f,
+                "mesh has {} {} but {} vertex positions",
+                found, attribute, expected
+            ),
+        }
+    }
+}
+
+impl Error for MeshError {}
+
+// Vertex attributes are shared between faces; every face indexes all of
+// them with the same three indices. Without normals the faces are flat, and
+// without UVs a hit's UV is its barycentric coordinates.
+pub struct TriangleMesh {
+    positions: Vec<Point3>,
+    normals: Vec<Vec3>,
+    uvs: Vec<[f64; 2]>,
+    indices: Vec<[usize; 3]>,
+    mat_ptr: Arc<dyn Scatter>,
+}
+
+impl TriangleMesh {
+    pub fn new(
+        positions: Vec<Point3>,
+        normals: Vec<Vec3>,
+        uvs: Vec<[f64; 2]>,
+        indices: Vec<[usize; 3]>,
+        mat_ptr: Arc<dyn Scatter>,
+    ) -> Result<Self, MeshError> {
+        for (attribute, found) in [("normals", normals.len()), ("uvs", uvs.len())] {
+            if found != 0 && found != positions.len() {
+                return Err(MeshError::AttributeCount {
+                    attribute,
+                    expected: positions.len(),
+                    found,
+                });
+            }
+        }
+        for (face, corners) in indices.iter().enumerate() {
+            if let Some(&index) = corners.iter().find(|&&i| i >= positions.len()) {
+                return Err(MeshError::IndexOutOfRange { face, index });
+            }
+        }
+        Ok(Self {
+            positions,
+            normals,
+            uvs,
+            indices,
+            mat_ptr,
+        })
+    }
+
+    pub fn face_count(&self) -> usize {
+        self.indices.len()
+    }
+
+    // One Hittable per face, ready for `BvhNode::from_list`.
+    pub fn triangles(mesh: &Arc<TriangleMesh>) -> HittableList {
+        let mut list = HittableList::new();
+        for face in 0..mesh.indices.len() {
+            list.push(Arc::new(MeshTriangle {
+                mesh: mesh.clone(),
+                face,
+            }));
+        }
+        list
+    }
+
+    fn corners(&self, face: usize) -> [Point3; 3] {
+        self.indices[face].map(|i| self.positions[i])
+    }
+}
+
+pub struct MeshTriangle {
+    mesh: Arc<TriangleMesh>,
+    face: usize,
+}
+
+impl Hittable for MeshTriangle {
+    fn hit(
+        &self,
+        r: &Ray,
+        t_min: f64,
+        t_max: f64,
+        rec: &mut HitRecord,
+        _sampler: &mut dyn Sampler,
+    ) -> bool {
+        let mesh = &self.mesh;
+        let p = mesh.corners(self.face);
+        let (t, b1, b2) = match intersect(r, &p, t_min, t_max) {
+            Some(hit) => hit,
+            None => return false,
+        };
+        let [i0, i1, i2] = mesh.indices[self.face];
+        let b0 = 1f64 - b1 - b2;
+        rec.t = t;
+        rec.point = r.at(t);
+        (rec.u, rec.v) = if mesh.uvs.is_empty() {
+            (b1, b2)
+        } else {
+            let (uv0, uv1, uv2) = (mesh.uvs[i0], mesh.uvs[i1], mesh.uvs[i2]);
+            (
+                b0 * uv0[0] + b1 * uv1[0] + b2 * uv2[0],
+                b0 * uv0[1] + b1 * uv1[1] + b2 * uv2[1],
+            )
+        };
+        // The side is decided by the geometric normal; the interpolated
+        // normal only shades.
+        rec.set_face_normal(r, &face_normal(&p));
+        if !mesh.normals.is_empty() {
+            let shading = mesh.normals[i0] * b0 + mesh.normals[i1] * b1 + mesh.normals[i2] * b2;
+            if !shading.near_zero() {
+                let shading = shading.unit();
+                rec.normal = if rec.front_face { shading } else { -shading };
+            }
+        }
+        rec.mat_ptr = Some(mesh.mat_ptr.clone());
+        true
+    }
+
+    fn bounding_box(&self, _time: [f64; 2], output_box: &mut Aabb) -> bool {
+        *output_box = triangle_box(&self.mesh.corners(self.face));
+        true
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.mesh.mat_ptr.is_emissive()
+    }
+
+    fn pdf_value(
+        &self,
+        origin: &Point3,
+        direction: &Vec3,
+        time: f64,
+        _sampler: &mut dyn Sampler,
+    ) -> f64 {
+        triangle_pdf_value(&self.mesh.corners(self.face), origin, direction, time)
+    }
+
+    fn random(&self, origin: &Point3, _time: f64, sampler: &mut dyn Sampler) -> Vec3 {
+        random_on_triangle(&self.mesh.corners(self.face), sampler) - *origin
+    }
+}
+
+// Möller–Trumbore: returns the ray parameter and the barycentric weights of
+// the second and third corners.
+pub fn intersect(r: &Ray, p: &[Point3; 3], t_min: f64, t_max: f64) -> Option<(f64, f64, f64)> {
+    let edge1 = p[1] - p[0];
+    let edge2 = p[2] - p[0];
+    let pvec = cross(&r.direction(), &edge2);
+    let det = dot(&edge1, &pvec);
+    if det.abs() < 1e-12 {
+        return None;
+    }
+    let inv_det = 1f64 / det;
+    let tvec = r.origin() - p[0];
+    let b1 = dot(&tvec, &pvec) * inv_det;
+    if !(0f64..=1f64).contains(&b1) {
+        return None;
+    }
+    let qvec = cross(&tvec, &edge1);
+    let b2 = dot(&r.direction(), &qvec) * inv_det;
+    if b2 < 0f64 || b1 + b2 > 1f64 {
+        return None;
+    }
+    let t = dot(&edge2, &qvec) * inv_det;
+    if t < t_min || t > t_max {
+        return None;
+    }
+    Some((t, b1, b2))
+}
+
+pub fn face_normal(p: &[Point3; 3]) -> Vec3 {
+    cross(&(p[1] - p[0]), &(p[2] - p[0])).unit()
+}
+
+// Padded so axis-aligned faces still have volume.
+fn triangle_box(p: &[Point3; 3]) -> Aabb {
+    let pad = Vec3::new(0.0001, 0.0001, 0.0001);
+    let mut min = p[0];
+    let mut max = p[0];
+    for corner in &p[1..] {
+        for c in 0..3 {
+            min.e[c] = min.e[c].min(corner.e[c]);
+            max.e[c] = max.e[c].max(corner.e[c]);
+        }
+    }
+    Aabb::new(min - pad, max + pad)
+}
+
+fn triangle_pdf_value(p: &[Point3; 3], origin: &Point3, direction: &Vec3, time: f64) -> f64 {
+    let r = Ray::new(*origin, *direction, time);
+    let t = match intersect(&r, p, 0.001, f64::INFINITY) {
+        Some((t, _, _)) => t,
+        None => return 0f64,
+    };
+    let normal = cross(&(p[1] - p[0]), &(p[2] - p[0]));
+    let area = 0.5 * normal.modulus();
+    let distance_squared = t * t * direction.modsq();
+    let cosine = dot(direction, &normal).abs() / (direction.modulus() * normal.modulus());
+    distance_squared / (cosine * area)
+}
+
+fn random_on_triangle(p: &[Point3; 3], sampler: &mut dyn Sampler) -> Point3 {
+    let (a, b) = sampler.next_2d();
+    let s = a.sqrt();
+    p[0] * (1f64 - s) + p[1] * (s * (1f64 - b)) + p[2] * (s * b)
+}
@@ -2,7 +2,9 @@ use core::{fmt, panic};
 use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Range, Sub, SubAssign};
 
 use image::Rgb;
-use rand::prelude::*;
+use rand::Rng;
+
+use crate::sampler::Sampler;
 
 #[derive(PartialEq, Clone, Copy)]
 pub struct Vec3 {
@@ -73,7 +75,7 @@ impl Vec3 {
         self.x().abs() < s && self.y().abs() < s && self.z().abs() < s
     }
 
-    pub fn random(rng: &mut ThreadRng) -> Self {
+    pub fn random(rng: &mut impl Rng) -> Self {
         Self {
             e: [
                 rng.gen_range(0f64..1f64),
@@ -82,7 +84,7 @@ impl Vec3 {
             ],
         }
     }
-    pub fn randomr(rng: &mut ThreadRng, r: Range<f64>) -> Self {
+    pub fn randomr(rng: &mut impl Rng, r: Range<f64>) -> Self {
         Self {
             e: [
                 rng.gen_range(r.clone()),
@@ -220,19 +222,29 @@ pub fn refract(uv: &Vec3, n: &Vec3, etai_over_etat: f64) -> Vec3 {
     r_out_para + r_out_perp
 }
 
-pub fn random_unit_sphere(rng: &mut ThreadRng) -> Vec3 {
-    let phi = rng.gen_range(0.0..std::f64::consts::PI);
-    let varphi = rng.gen_range(0.0..std::f64::consts::PI * 2.0);
-    Vec3::new(
-        phi.sin() * varphi.cos(),
-        phi.sin() * varphi.sin(),
-        phi.cos(),
-    )
-}
-
-pub fn random_unit_disk(rng: &mut ThreadRng) -> Vec3 {
-    let varphi = rng.gen_range(0.0..std::f64::consts::PI * 2.0);
-    Vec3 {
-        e: [varphi.cos(), varphi.sin(), 0f64],
-    }
+// Uniform over the sphere's surface: z is uniform in [-1, 1] by Archimedes'
+// hat-box theorem.
+pub fn random_unit_sphere(sampler: &mut dyn Sampler) -> Vec3 {
+    let (u, v) = sampler.next_2d();
+    let z = 1f64 - 2f64 * u;
+    let r = (1f64 - z * z).max(0f64).sqrt();
+    let phi = 2f64 * std::f64::consts::PI * v;
+    Vec3::new(r * phi.cos(), r * phi.sin(), z)
+}
+
+// Shirley-Chiu concentric mapping, which keeps neighbouring samples
+// neighbours and so preserves the stratification of the 2D sample.
+pub fn random_unit_disk(sampler: &mut dyn Sampler) -> Vec3 {
+    let (u, v) = sampler.next_2d();
+    let (a, b) = (2f64 * u - 1f64, 2f64 * v - 1f64);
+    if a == 0f64 && b == 0f64 {
+        return Vec3::new(0f64, 0f64, 0f64);
+    }
+    let quarter_pi = std::f64::consts::FRAC_PI_4;
+    let (r, theta) = if a.abs() > b.abs() {
+        (a, quarter_pi * (b / a))
+    } else {
+        (b, 2f64 * quarter_pi - quarter_pi * (a / b))
+    };
+    Vec3::new(r * theta.cos(), r * theta.sin(), 0f64)
 }